@@ -0,0 +1,183 @@
+//! Who plays: registered accounts, which sign in with a name and password, and guests, who
+//! get a generated name and no password until they register.
+
+use std::io;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use blake2::{Blake2s256, Digest};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{store::now, utility::random_id};
+
+/// Sessions end this many seconds after signing in.
+pub const SESSION_LIFETIME: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct User {
+    pub id: String,
+    /// Unique regardless of case.
+    pub name: String,
+    /// The salted argon2 hash of the password in PHC format; guests have none.
+    pub password_hash: Option<String>,
+    pub created_at: u64,
+}
+
+impl User {
+    const ID_LENGTH: usize = 12;
+    const GUEST_PREFIX: &'static str = "Guest-";
+    const MAX_NAME_LENGTH: usize = 20;
+    const MIN_PASSWORD_LENGTH: usize = 8;
+
+    /// A guest with a random name such as `Guest-042917`.
+    pub fn guest() -> Self {
+        let number = rand::thread_rng().gen_range(0..1_000_000);
+        Self {
+            id: random_id(User::ID_LENGTH),
+            name: format!("{}{number:06}", User::GUEST_PREFIX),
+            password_hash: None,
+            created_at: now(),
+        }
+    }
+
+    pub fn is_guest(&self) -> bool {
+        self.password_hash.is_none()
+    }
+
+    /// Gives the user a name and password, turning a guest into an account that keeps its
+    /// games. Unfit names and passwords are an `InvalidInput` error.
+    pub fn register(&mut self, name: &str, password: &str) -> io::Result<()> {
+        let valid_characters = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if name.len() < 3 || name.len() > User::MAX_NAME_LENGTH || !valid_characters {
+            return Err(invalid(format!(
+                "Names are 3 to {} letters, digits, - or _",
+                User::MAX_NAME_LENGTH
+            )));
+        }
+        if name
+            .to_ascii_lowercase()
+            .starts_with(&User::GUEST_PREFIX.to_ascii_lowercase())
+        {
+            return Err(invalid(format!(
+                "Names starting with {} are kept for guests",
+                User::GUEST_PREFIX
+            )));
+        }
+        if password.chars().count() < User::MIN_PASSWORD_LENGTH {
+            return Err(invalid(format!(
+                "Passwords are at least {} characters",
+                User::MIN_PASSWORD_LENGTH
+            )));
+        }
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        self.name = name.to_string();
+        self.password_hash = Some(hash.to_string());
+        Ok(())
+    }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        let Some(hash) = self.password_hash.as_deref() else {
+            return false;
+        };
+        PasswordHash::new(hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+}
+
+/// A signed-in browser, known by the random token in its session cookie. Stores keep only the
+/// token's hash, so reading them signs nobody in.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Session {
+    pub user_id: String,
+    pub created_at: u64,
+}
+
+impl Session {
+    pub const TOKEN_LENGTH: usize = 32;
+
+    /// The hex BLAKE2s hash of the token, which stores key the session by. Tokens are random,
+    /// so an unsalted fast hash is enough.
+    pub fn token_hash(token: &str) -> String {
+        Blake2s256::digest(token.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        now().saturating_sub(self.created_at) > SESSION_LIFETIME
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registration_checks_names_and_passwords() {
+        let mut user = User::guest();
+        let rejected = [
+            ("ab", "password"),
+            ("a23456789012345678901", "password"),
+            ("two words", "password"),
+            ("naïve", "password"),
+            ("Guest-1", "password"),
+            ("guest-player", "password"),
+            ("player", "1234567"),
+            ("player", "ééééééé"),
+        ];
+        for (name, password) in rejected {
+            let error = user.register(name, password).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                io::ErrorKind::InvalidInput,
+                "{name} {password}"
+            );
+            assert!(user.is_guest(), "{name} {password}");
+        }
+        user.register("a_2345678901234567-9", "éééééééé").unwrap();
+        assert_eq!(user.name, "a_2345678901234567-9");
+        assert!(!user.is_guest());
+        assert!(user.verify_password("éééééééé"));
+    }
+
+    #[test]
+    fn only_the_password_verifies() {
+        let mut user = User::guest();
+        assert!(!user.verify_password(""));
+        user.register("Guests", "correct horse").unwrap();
+        assert!(user.verify_password("correct horse"));
+        assert!(!user.verify_password("Correct horse"));
+        assert!(!user.verify_password(""));
+        user.password_hash = Some("not a hash".to_string());
+        assert!(!user.verify_password("correct horse"));
+    }
+
+    #[test]
+    fn sessions_expire_after_their_lifetime() {
+        let session = |created_at| Session {
+            user_id: "user".to_string(),
+            created_at,
+        };
+        assert!(!session(now()).is_expired());
+        assert!(!session(now() - SESSION_LIFETIME + 60).is_expired());
+        assert!(session(now() - SESSION_LIFETIME - 60).is_expired());
+        // A clock set back must not end every session.
+        assert!(!session(now() + 60).is_expired());
+    }
+}
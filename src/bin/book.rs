@@ -0,0 +1,79 @@
+use std::{env, fs, process, time::Duration};
+
+use checkers::engine::{OpeningBook, PdnGame, SearchLimits};
+
+const DEFAULT_PATH: &str = "data/book.txt";
+const DEFAULT_MAX_PLIES: usize = 12;
+const SELF_PLAY_DEPTH: u8 = 4;
+
+/// Usage:
+/// - `book pdn <directory> [output_path] [max_plies]` builds from every `.pdn` file in a directory
+/// - `book selfplay <games> [output_path] [max_plies]` builds from engine self-play
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let (Some(source), Some(input)) = (args.first(), args.get(1)) else {
+        usage();
+    };
+    let path = args.get(2).map_or(DEFAULT_PATH, String::as_str);
+    let max_plies = match args.get(3).map(|arg| arg.parse::<usize>()) {
+        Some(Ok(max_plies)) => max_plies,
+        Some(Err(_)) => usage(),
+        None => DEFAULT_MAX_PLIES,
+    };
+    let book = match source.as_str() {
+        "pdn" => from_pdn_directory(input, max_plies),
+        "selfplay" => {
+            let Ok(games) = input.parse::<usize>() else {
+                usage();
+            };
+            let limits = SearchLimits {
+                max_depth: SELF_PLAY_DEPTH,
+                time: Duration::from_secs(60),
+            };
+            OpeningBook::from_self_play(games, max_plies, limits, &mut rand::thread_rng(), |game| {
+                println!("Played game {game}/{games}")
+            })
+        }
+        _ => usage(),
+    };
+    book.save(path).unwrap();
+    println!("Saved {} book positions to {path}", book.len());
+}
+
+fn from_pdn_directory(directory: &str, max_plies: usize) -> OpeningBook {
+    let mut book = OpeningBook::default();
+    let mut paths = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "pdn"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        let text = fs::read_to_string(&path).unwrap();
+        let games = match PdnGame::parse_all(&text) {
+            Ok(games) => games,
+            Err(e) => {
+                eprintln!("Skipping {}: {e}", path.display());
+                continue;
+            }
+        };
+        let mut added = 0;
+        for (index, game) in games.iter().enumerate() {
+            match game.replay() {
+                Ok(replay) => {
+                    book.add_game(&replay, game.result, max_plies);
+                    added += 1;
+                }
+                Err(e) => eprintln!("Skipping game {} of {}: {e}", index + 1, path.display()),
+            }
+        }
+        println!("Added {added} games from {}", path.display());
+    }
+    book
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: book pdn <directory> [output_path] [max_plies]");
+    eprintln!("       book selfplay <games> [output_path] [max_plies]");
+    process::exit(1);
+}
@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    process,
+    time::{Duration, Instant},
+};
+
+use checkers::engine::{
+    Ai, Board, DxpMessage, DxpResult, Engine, Notation, NotationStyle, Outcome, SearchLimits, Turn,
+};
+
+const CLIENT_NAME: &str = "Checkers";
+const DEFAULT_DEPTH: u8 = 8;
+const MOVE_TIME: Duration = Duration::from_secs(1);
+const GAME_MINUTES: u16 = 10;
+const GAME_MOVES: u16 = 75;
+
+/// Usage: `dxp_client <host:port> [white|black] [depth]`
+///
+/// Connects to a DXP server, asks for a game where the built-in AI plays the given colour and
+/// plays it out, printing each move.
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let Some(address) = args.first() else {
+        usage();
+    };
+    let color = match args.get(1).map(String::as_str) {
+        Some("white") | None => Turn::White,
+        Some("black") => Turn::Black,
+        Some(_) => usage(),
+    };
+    let depth = match args.get(2).map(|arg| arg.parse::<u8>()) {
+        Some(Ok(depth)) => depth,
+        Some(Err(_)) => usage(),
+        None => DEFAULT_DEPTH,
+    };
+    let ai = Ai::new(SearchLimits {
+        max_depth: depth,
+        time: MOVE_TIME,
+    });
+    if let Err(e) = play(address, color, &ai) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
+
+fn play(address: &str, color: Turn, ai: &Ai) -> io::Result<()> {
+    let stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    send(
+        &mut writer,
+        &DxpMessage::GameRequest {
+            name: CLIENT_NAME.to_string(),
+            follower_color: color.next(),
+            minutes: GAME_MINUTES,
+            moves: GAME_MOVES,
+            position: None,
+        },
+    )?;
+    match receive(&mut reader)? {
+        DxpMessage::GameAccept { name, code: 0 } => println!("{name} accepted the game"),
+        DxpMessage::GameAccept { code, .. } => {
+            return Err(io::Error::other(format!("Game refused with code {code}")))
+        }
+        message => return Err(unexpected(&message)),
+    }
+    let mut board = Board::new();
+    let mut repetitions = HashMap::from([(board.hash(), 1)]);
+    loop {
+        let outcome = match repetitions[&board.hash()] >= 3 {
+            true => Some(Outcome::Draw),
+            false => Engine::get_outcome(&board),
+        };
+        if let Some(outcome) = outcome {
+            let result = match outcome {
+                Outcome::Draw => DxpResult::Draw,
+                _ if outcome == Outcome::win(color) => DxpResult::Win,
+                _ => DxpResult::Loss,
+            };
+            println!("Game over: {outcome}");
+            return send(&mut writer, &DxpMessage::GameEnd { result, stop: 1 });
+        }
+        let next_move = if board.turn == color {
+            let started = Instant::now();
+            let Some(next_move) = ai.choose_move(&board) else {
+                return Err(io::Error::other("No move found"));
+            };
+            let seconds = started.elapsed().as_secs() as u16;
+            send(
+                &mut writer,
+                &DxpMessage::from_move(&board, &next_move, seconds),
+            )?;
+            next_move
+        } else {
+            match receive(&mut reader)? {
+                message @ DxpMessage::Move { .. } => message.to_move(&board).ok_or_else(|| {
+                    io::Error::other(format!("Illegal move {}", message.encode()))
+                })?,
+                DxpMessage::GameEnd { result, .. } => {
+                    println!("Opponent ended the game: {result:?}");
+                    return send(
+                        &mut writer,
+                        &DxpMessage::GameEnd {
+                            result: DxpResult::Unknown,
+                            stop: 1,
+                        },
+                    );
+                }
+                DxpMessage::BackRequest { .. } => {
+                    send(&mut writer, &DxpMessage::BackAccept { code: 1 })?;
+                    continue;
+                }
+                _ => continue,
+            }
+        };
+        println!(
+            "{:?}: {}",
+            board.turn,
+            Notation::format_move(&next_move, NotationStyle::Numeric)
+        );
+        board = Engine::make_move(board, next_move.from, next_move.to);
+        *repetitions.entry(board.hash()).or_insert(0) += 1;
+    }
+}
+
+fn send(writer: &mut TcpStream, message: &DxpMessage) -> io::Result<()> {
+    writer.write_all(message.encode().as_bytes())?;
+    writer.write_all(&[0])?;
+    writer.flush()
+}
+
+fn receive(reader: &mut impl BufRead) -> io::Result<DxpMessage> {
+    let mut bytes = vec![];
+    if reader.read_until(0, &mut bytes)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Server closed the connection",
+        ));
+    }
+    DxpMessage::parse(&String::from_utf8_lossy(&bytes))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn unexpected(message: &DxpMessage) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Unexpected message {}", message.encode()),
+    )
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: dxp_client <host:port> [white|black] [depth]");
+    process::exit(1);
+}
@@ -0,0 +1,53 @@
+use std::{
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+use checkers::engine::{Ai, Board, HubEngine, SearchLimits};
+
+/// Minimal Hub-protocol engine backed by the built-in search, for exercising external seats.
+fn main() {
+    let mut board = Board::new();
+    let mut limits = SearchLimits {
+        max_depth: 4,
+        time: Duration::from_secs(1),
+    };
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let (command, values) = HubEngine::parse_line(&line.unwrap());
+        let reply = match command.as_str() {
+            "hub" => "id name=Stub version=1\nwait".to_string(),
+            "init" => "ready".to_string(),
+            "pos" => match values
+                .get("pos")
+                .map(|position| HubEngine::parse_position(position))
+            {
+                Some(Some(position)) => {
+                    board = position;
+                    continue;
+                }
+                Some(None) => "error message=\"Invalid position\"".to_string(),
+                None => "error message=\"Missing position\"".to_string(),
+            },
+            "level" => {
+                if let Some(Ok(depth)) = values.get("depth").map(|depth| depth.parse()) {
+                    limits.max_depth = depth;
+                }
+                if let Some(Ok(time)) = values.get("move-time").map(|time| time.parse()) {
+                    limits.time = Duration::from_secs_f32(time);
+                }
+                continue;
+            }
+            "go" => match Ai::new(limits).choose_move(&board) {
+                Some(best_move) => {
+                    format!("done move={}", HubEngine::format_move(&board, &best_move))
+                }
+                None => "error message=\"No legal moves\"".to_string(),
+            },
+            "quit" => return,
+            _ => continue,
+        };
+        writeln!(stdout, "{reply}").unwrap();
+        stdout.flush().unwrap();
+    }
+}
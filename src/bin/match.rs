@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    env, fs, process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use checkers::engine::{
+    Ai, Board, Engine, Mcts, Move, Network, OpeningBook, Outcome, Playouts, SearchLimits, Turn,
+    Weights,
+};
+
+const DEFAULT_GAMES: usize = 100;
+const DEFAULT_DEPTH: u8 = 4;
+/// Games still running after this many plies are scored as draws.
+const MAX_PLIES: usize = 200;
+/// Searches are bounded by depth, so the time limit only guards against runaway positions.
+const DEFAULT_TIME: Duration = Duration::from_secs(10);
+
+/// One side of the match, parsed from e.g. `depth=6,weights=data/weights.txt,book=data/book.txt`
+/// or `mcts=2000,playouts=random`.
+struct Player {
+    name: String,
+    kind: PlayerKind,
+}
+
+enum PlayerKind {
+    AlphaBeta(Ai),
+    Mcts(Mcts),
+}
+
+impl Player {
+    fn parse(name: &str, spec: &str) -> Result<Self, String> {
+        let mut limits = SearchLimits {
+            max_depth: DEFAULT_DEPTH,
+            time: DEFAULT_TIME,
+        };
+        let mut weights = Weights::default();
+        let mut book = None;
+        let mut network = None;
+        let mut mcts = None;
+        let mut playouts = Playouts::Heavy;
+        for option in spec.split(',').filter(|option| !option.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or(format!("Invalid option {option:?}"))?;
+            let invalid = |e: &dyn std::fmt::Display| format!("Invalid {key} {value:?}: {e}");
+            match key {
+                "depth" => limits.max_depth = value.parse().map_err(|e| invalid(&e))?,
+                "time_ms" => {
+                    limits.time = Duration::from_millis(value.parse().map_err(|e| invalid(&e))?)
+                }
+                "weights" => weights = Weights::load(value).map_err(|e| invalid(&e))?,
+                "book" => book = Some(Arc::new(OpeningBook::load(value).map_err(|e| invalid(&e))?)),
+                "network" => {
+                    network = Some(Arc::new(Network::load(value).map_err(|e| invalid(&e))?))
+                }
+                "mcts" => mcts = Some(value.parse().map_err(|e| invalid(&e))?),
+                "playouts" => {
+                    playouts = match value {
+                        "random" => Playouts::Random,
+                        "heavy" => Playouts::Heavy,
+                        _ => return Err(invalid(&"expected random or heavy")),
+                    }
+                }
+                _ => return Err(format!("Unknown option {key:?}")),
+            }
+        }
+        let kind = match mcts {
+            Some(simulations) => PlayerKind::Mcts(Mcts::new(simulations, playouts)),
+            None => PlayerKind::AlphaBeta(
+                Ai::new(limits)
+                    .with_weights(weights)
+                    .with_network(network)
+                    .with_book(book),
+            ),
+        };
+        Ok(Self {
+            name: format!("{name} ({spec})"),
+            kind,
+        })
+    }
+
+    fn choose_move(&self, board: &Board) -> Option<Move> {
+        match &self.kind {
+            PlayerKind::AlphaBeta(ai) => ai.choose_move(board),
+            PlayerKind::Mcts(mcts) => mcts.choose_move(board),
+        }
+    }
+}
+
+/// Result of one game from the first player's point of view: 1, 0.5 or 0.
+type Score = f64;
+
+/// Usage: `match <player_a> <player_b> [games] [threads] [openings_path]`
+///
+/// Players are comma-separated `key=value` options: `depth`, `time_ms`, `weights`, `network`
+/// and `book` for the alpha-beta engine, or `mcts` (simulations per move) and `playouts` for MCTS.
+/// Openings are FEN lines; by default every two-move opening from the initial position is used.
+/// Each opening is played twice with colours swapped.
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let (Some(spec_a), Some(spec_b)) = (args.first(), args.get(1)) else {
+        usage();
+    };
+    let parse_count = |index: usize, default: usize| match args.get(index).map(|arg| arg.parse()) {
+        Some(Ok(count)) if count > 0 => count,
+        Some(_) => usage(),
+        None => default,
+    };
+    let games = parse_count(2, DEFAULT_GAMES);
+    let threads = parse_count(3, thread::available_parallelism().map_or(1, |n| n.get()));
+    let openings = match args.get(4) {
+        Some(path) => load_openings(path),
+        None => default_openings(),
+    };
+    let players = match (Player::parse("A", spec_a), Player::parse("B", spec_b)) {
+        (Ok(a), Ok(b)) => [a, b],
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    };
+    println!("{} vs {}", players[0].name, players[1].name);
+    println!(
+        "{games} games on {threads} threads, {} openings",
+        openings.len()
+    );
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (players, openings, next_game) = (&players, &openings, &next_game);
+            scope.spawn(move || loop {
+                let game = next_game.fetch_add(1, Ordering::Relaxed);
+                if game >= games {
+                    return;
+                }
+                let opening = &openings[game / 2 % openings.len()];
+                let is_a_white = game % 2 == 0;
+                let (white, black) = match is_a_white {
+                    true => (&players[0], &players[1]),
+                    false => (&players[1], &players[0]),
+                };
+                let outcome = play_game(opening, white, black);
+                let score = match (outcome, is_a_white) {
+                    (Outcome::Draw, _) => 0.5,
+                    (Outcome::WhiteWins, true) | (Outcome::BlackWins, false) => 1.0,
+                    _ => 0.0,
+                };
+                sender.send(score).unwrap();
+            });
+        }
+        drop(sender);
+        let mut scores = vec![];
+        for score in receiver {
+            scores.push(score);
+            if scores.len() % 10 == 0 || scores.len() == games {
+                print_summary(&scores);
+            }
+        }
+    });
+}
+
+fn play_game(opening: &Board, white: &Player, black: &Player) -> Outcome {
+    let mut board = opening.clone();
+    let mut repetitions = HashMap::from([(board.hash(), 1)]);
+    for _ in 0..MAX_PLIES {
+        if let Some(outcome) = Engine::get_outcome(&board) {
+            return outcome;
+        }
+        let player = match board.turn {
+            Turn::White => white,
+            Turn::Black => black,
+        };
+        let Some(next_move) = player.choose_move(&board) else {
+            return Outcome::win(board.turn.next());
+        };
+        board = Engine::make_move(board, next_move.from, next_move.to);
+        let count = repetitions.entry(board.hash()).or_insert(0);
+        *count += 1;
+        if *count >= 3 {
+            return Outcome::Draw;
+        }
+    }
+    Outcome::Draw
+}
+
+fn print_summary(scores: &[Score]) {
+    let count = |score: Score| scores.iter().filter(|s| **s == score).count();
+    let (wins, draws, losses) = (count(1.0), count(0.5), count(0.0));
+    let games = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / games;
+    let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / games;
+    // 95% confidence interval of the mean score, mapped through the Elo curve.
+    let margin = 1.96 * (variance / games).sqrt();
+    let (low, high) = (elo(mean - margin), elo(mean + margin));
+    println!(
+        "{} games: +{wins} ={draws} -{losses}, score {:.1}%, Elo {} (+/- {})",
+        scores.len(),
+        mean * 100.0,
+        format_elo(elo(mean)),
+        format_elo((high - low) / 2.0),
+    );
+}
+
+fn elo(score: Score) -> f64 {
+    -400.0 * (1.0 / score.clamp(0.0, 1.0) - 1.0).log10()
+}
+
+fn format_elo(elo: f64) -> String {
+    match elo.is_finite() {
+        true => format!("{elo:.1}"),
+        false if elo < 0.0 => "-inf".to_string(),
+        false => "inf".to_string(),
+    }
+}
+
+fn default_openings() -> Vec<Board> {
+    let start = Board::new();
+    let mut openings = vec![];
+    for first in Engine::get_moves(&start) {
+        let board = Engine::make_move(start.clone(), first.from, first.to);
+        for second in Engine::get_moves(&board) {
+            openings.push(Engine::make_move(board.clone(), second.from, second.to));
+        }
+    }
+    openings
+}
+
+fn load_openings(path: &str) -> Vec<Board> {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Reading openings from {path} failed: {e}");
+        process::exit(1);
+    });
+    let openings = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|fen| match Board::from_fen(fen) {
+            Ok(board) => board,
+            Err(e) => {
+                eprintln!("Invalid opening {fen:?}: {e}");
+                process::exit(1);
+            }
+        })
+        .collect::<Vec<Board>>();
+    if openings.is_empty() {
+        eprintln!("No openings in {path}");
+        process::exit(1);
+    }
+    openings
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: match <player_a> <player_b> [games] [threads] [openings_path]");
+    eprintln!("Players are options like depth=6,time_ms=500,weights=PATH,book=PATH");
+    eprintln!("or mcts=2000,playouts=random|heavy");
+    process::exit(1);
+}
@@ -0,0 +1,36 @@
+use std::{env, process, time::Instant};
+
+use checkers::engine::Tablebase;
+
+const DEFAULT_MAX_PIECES: u8 = 3;
+const DEFAULT_PATH: &str = "data/tablebase.bin";
+
+/// Usage: `tablebase [max_pieces] [output_path]`
+fn main() {
+    let mut args = env::args().skip(1);
+    let max_pieces = match args.next().map(|arg| arg.parse::<u8>()) {
+        Some(Ok(max_pieces)) if max_pieces >= 2 => max_pieces,
+        Some(_) => {
+            eprintln!("max_pieces must be a number of at least 2");
+            process::exit(1);
+        }
+        None => DEFAULT_MAX_PIECES,
+    };
+    let path = args.next().unwrap_or(DEFAULT_PATH.to_string());
+    let started = Instant::now();
+    let generated = Tablebase::generate(max_pieces, |message| {
+        println!("[{:>6.1}s] {message}", started.elapsed().as_secs_f32())
+    });
+    let tablebase = match generated {
+        Ok(tablebase) => tablebase,
+        Err(e) => {
+            eprintln!("Generating the tablebase failed: {e}");
+            process::exit(1);
+        }
+    };
+    tablebase.save(&path).unwrap();
+    println!(
+        "Saved {} positions with up to {max_pieces} pieces to {path}",
+        tablebase.len()
+    );
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    process,
+    time::Duration,
+};
+
+use checkers::engine::{
+    Ai, Board, Engine, Network, Outcome, SearchLimits, NETWORK_HIDDEN, NETWORK_INPUTS,
+};
+use rand::seq::SliceRandom;
+
+const DEFAULT_DATASET: &str = "data/selfplay.txt";
+const DEFAULT_NETWORK: &str = "data/network.bin";
+const DEFAULT_DEPTH: u8 = 4;
+const DEFAULT_EPOCHS: usize = 20;
+/// Random opening plies, so self-play games do not all repeat the same line.
+const RANDOM_PLIES: usize = 6;
+/// Games still running after this many plies are scored as draws.
+const MAX_PLIES: usize = 200;
+const BATCH_SIZE: usize = 256;
+/// One position in this many is held out to measure the error on unseen positions.
+const VALIDATION_EVERY: usize = 10;
+const LEARNING_RATE: f32 = 0.001;
+const BETA1: f32 = 0.9;
+const BETA2: f32 = 0.999;
+const EPSILON: f32 = 1e-8;
+
+/// A position's active network inputs and the game's result for White: 1, 0.5 or 0.
+struct Sample {
+    inputs: Vec<usize>,
+    result: f32,
+}
+
+/// Usage:
+/// - `train selfplay <games> [dataset_path] [depth]` appends `<result> <fen>` lines from
+///   engine self-play to the dataset
+/// - `train fit <dataset> [network_path] [epochs]` trains the network on such a dataset,
+///   continuing from `network_path` when it exists
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let (Some(command), Some(input)) = (args.first(), args.get(1)) else {
+        usage();
+    };
+    match command.as_str() {
+        "selfplay" => {
+            let Ok(games) = input.parse::<usize>() else {
+                usage();
+            };
+            let path = args.get(2).map_or(DEFAULT_DATASET, String::as_str);
+            let depth = match args.get(3).map(|arg| arg.parse::<u8>()) {
+                Some(Ok(depth)) => depth,
+                Some(Err(_)) => usage(),
+                None => DEFAULT_DEPTH,
+            };
+            self_play(games, path, depth);
+        }
+        "fit" => {
+            let path = args.get(2).map_or(DEFAULT_NETWORK, String::as_str);
+            let epochs = match args.get(3).map(|arg| arg.parse::<usize>()) {
+                Some(Ok(epochs)) => epochs,
+                Some(Err(_)) => usage(),
+                None => DEFAULT_EPOCHS,
+            };
+            fit(input, path, epochs);
+        }
+        _ => usage(),
+    }
+}
+
+fn self_play(games: usize, path: &str, depth: u8) {
+    let ai = Ai::new(SearchLimits {
+        max_depth: depth,
+        time: Duration::from_secs(60),
+    });
+    let mut rng = rand::thread_rng();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    for game in 1..=games {
+        let mut board = Board::new();
+        let mut repetitions = HashMap::from([(board.hash(), 1)]);
+        let mut positions = vec![];
+        let mut ply = 0;
+        let outcome = loop {
+            if repetitions[&board.hash()] >= 3 || ply >= MAX_PLIES {
+                break Outcome::Draw;
+            }
+            if let Some(outcome) = Engine::get_outcome(&board) {
+                break outcome;
+            }
+            let next_move = match ply < RANDOM_PLIES {
+                true => Engine::get_moves(&board).choose(&mut rng).copied(),
+                false => {
+                    if is_quiet(&board) {
+                        positions.push(board.to_fen());
+                    }
+                    ai.choose_move(&board)
+                }
+            };
+            let Some(next_move) = next_move else {
+                break Outcome::win(board.turn.next());
+            };
+            board = Engine::make_move(board, next_move.from, next_move.to);
+            *repetitions.entry(board.hash()).or_insert(0) += 1;
+            ply += 1;
+        };
+        let result = match outcome {
+            Outcome::WhiteWins => "1-0",
+            Outcome::BlackWins => "0-1",
+            Outcome::Draw => "1/2-1/2",
+        };
+        let lines = positions
+            .iter()
+            .map(|fen| format!("{result} {fen}\n"))
+            .collect::<String>();
+        file.write_all(lines.as_bytes()).unwrap();
+        println!(
+            "Game {game}/{games}: {outcome} after {ply} plies, {} positions",
+            positions.len()
+        );
+    }
+}
+
+fn fit(dataset: &str, path: &str, epochs: usize) {
+    let mut rng = rand::thread_rng();
+    let mut samples = load_samples(dataset);
+    if samples.is_empty() {
+        eprintln!("No positions found in {dataset}");
+        process::exit(1);
+    }
+    samples.shuffle(&mut rng);
+    let validation = samples.split_off(samples.len() - samples.len() / VALIDATION_EVERY);
+    let mut training = samples;
+    let mut network = Network::load(path).unwrap_or_else(|_| Network::random(&mut rng));
+    println!(
+        "{NETWORK_INPUTS}x{NETWORK_HIDDEN} network, {} training and {} validation positions",
+        training.len(),
+        validation.len()
+    );
+    let mut m = vec![0.0; network.parameters.len()];
+    let mut v = vec![0.0; network.parameters.len()];
+    let mut step = 0;
+    for epoch in 1..=epochs {
+        training.shuffle(&mut rng);
+        for batch in training.chunks(BATCH_SIZE) {
+            let mut gradient = vec![0.0; network.parameters.len()];
+            for sample in batch {
+                let predicted = sigmoid(network.forward(&sample.inputs));
+                let slope = 2.0 * (predicted - sample.result) * predicted * (1.0 - predicted);
+                let slope = slope * 10f32.ln() / 400.0 / batch.len() as f32;
+                network.backward(&sample.inputs, slope, &mut gradient);
+            }
+            step += 1;
+            for (i, parameter) in network.parameters.iter_mut().enumerate() {
+                m[i] = BETA1 * m[i] + (1.0 - BETA1) * gradient[i];
+                v[i] = BETA2 * v[i] + (1.0 - BETA2) * gradient[i] * gradient[i];
+                let m_hat = m[i] / (1.0 - BETA1.powi(step));
+                let v_hat = v[i] / (1.0 - BETA2.powi(step));
+                *parameter -= LEARNING_RATE * m_hat / (v_hat.sqrt() + EPSILON);
+            }
+        }
+        println!(
+            "epoch {epoch}: training error {:.6}, validation error {:.6}",
+            error(&network, &training),
+            error(&network, &validation)
+        );
+    }
+    network.save(path).unwrap();
+    println!("Saved network to {path}");
+}
+
+/// Expected result for White of a score in hundredths of a man.
+fn sigmoid(score: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf(-score / 400.0))
+}
+
+fn error(network: &Network, samples: &[Sample]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let total = samples
+        .iter()
+        .map(|sample| (sample.result - sigmoid(network.forward(&sample.inputs))).powi(2))
+        .sum::<f32>();
+    total / samples.len() as f32
+}
+
+fn load_samples(path: &str) -> Vec<Sample> {
+    let text = fs::read_to_string(path).unwrap();
+    let mut samples = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line
+            .split_once(char::is_whitespace)
+            .and_then(|(result, fen)| {
+                let result = match result {
+                    "1-0" | "2-0" => 1.0,
+                    "0-1" | "0-2" => 0.0,
+                    "1/2-1/2" | "1-1" => 0.5,
+                    _ => return None,
+                };
+                Some((result, Board::from_fen(fen).ok()?))
+            });
+        match parsed {
+            Some((result, board)) => samples.push(Sample {
+                inputs: Network::inputs(&board),
+                result,
+            }),
+            None => eprintln!("Skipping line {} of {path}", number + 1),
+        }
+    }
+    samples
+}
+
+/// Positions with a capture pending are scored mid-exchange, so they are left out.
+fn is_quiet(board: &Board) -> bool {
+    !Engine::get_moves(board)
+        .iter()
+        .any(|legal_move| legal_move.is_capture)
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: train selfplay <games> [dataset_path] [depth]");
+    eprintln!("       train fit <dataset> [network_path] [epochs]");
+    process::exit(1);
+}
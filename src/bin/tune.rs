@@ -0,0 +1,199 @@
+use std::{env, fs, path::Path, process};
+
+use checkers::engine::{Board, Engine, Feature, Outcome, PdnGame, Weights, FEATURE_COUNT};
+
+const DEFAULT_PATH: &str = "data/weights.txt";
+const DEFAULT_ITERATIONS: usize = 1000;
+/// Opening positions mostly reflect the book rather than the evaluation.
+const SKIPPED_PLIES: usize = 8;
+const LEARNING_RATE: f32 = 0.5;
+const BETA1: f32 = 0.9;
+const BETA2: f32 = 0.999;
+const EPSILON: f32 = 1e-8;
+
+/// A position's features and the game's result for White: 1, 0.5 or 0.
+struct Sample {
+    features: [f32; FEATURE_COUNT],
+    result: f32,
+}
+
+/// Usage: `tune <dataset> [weights_path] [iterations]`
+///
+/// The dataset is a `.pdn` file, a directory of them, or a text file with one
+/// `<result> <fen>` line per position, e.g. `1-0 W:W21,22,K30:B1,2,3`.
+/// Weights are read from `weights_path` when it exists and the tuned ones are written back.
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let Some(dataset) = args.first() else {
+        usage();
+    };
+    let path = args.get(1).map_or(DEFAULT_PATH, String::as_str);
+    let iterations = match args.get(2).map(|arg| arg.parse::<usize>()) {
+        Some(Ok(iterations)) => iterations,
+        Some(Err(_)) => usage(),
+        None => DEFAULT_ITERATIONS,
+    };
+    let samples = load_samples(Path::new(dataset));
+    if samples.is_empty() {
+        eprintln!("No positions found in {dataset}");
+        process::exit(1);
+    }
+    let mut weights = Weights::load(path).unwrap_or_default();
+    let k = fit_scale(&weights, &samples);
+    println!(
+        "{} positions, scale {k:.3}, error {:.6}",
+        samples.len(),
+        error(&weights, &samples, k)
+    );
+    let mut m = [0.0; FEATURE_COUNT];
+    let mut v = [0.0; FEATURE_COUNT];
+    for iteration in 1..=iterations {
+        let gradient = gradient(&weights, &samples, k);
+        // Material stays fixed so the weights keep their hundredths-of-a-man scale.
+        for i in (0..FEATURE_COUNT).filter(|i| *i != Feature::Material as usize) {
+            m[i] = BETA1 * m[i] + (1.0 - BETA1) * gradient[i];
+            v[i] = BETA2 * v[i] + (1.0 - BETA2) * gradient[i] * gradient[i];
+            let m_hat = m[i] / (1.0 - BETA1.powi(iteration as i32));
+            let v_hat = v[i] / (1.0 - BETA2.powi(iteration as i32));
+            weights.values[i] -= LEARNING_RATE * m_hat / (v_hat.sqrt() + EPSILON);
+        }
+        if iteration % 100 == 0 || iteration == iterations {
+            println!(
+                "iteration {iteration}: error {:.6}",
+                error(&weights, &samples, k)
+            );
+        }
+    }
+    for feature in Feature::ALL {
+        println!("{:>16} {:8.2}", feature.name(), weights.get(feature));
+    }
+    weights.save(path).unwrap();
+    println!("Saved weights to {path}");
+}
+
+/// Expected result for White of a score in hundredths of a man.
+fn sigmoid(score: f32, k: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf(-k * score / 400.0))
+}
+
+fn error(weights: &Weights, samples: &[Sample], k: f32) -> f32 {
+    let total = samples
+        .iter()
+        .map(|sample| (sample.result - sigmoid(weights.score(&sample.features), k)).powi(2))
+        .sum::<f32>();
+    total / samples.len() as f32
+}
+
+fn gradient(weights: &Weights, samples: &[Sample], k: f32) -> [f32; FEATURE_COUNT] {
+    let mut gradient = [0.0; FEATURE_COUNT];
+    for sample in samples {
+        let predicted = sigmoid(weights.score(&sample.features), k);
+        let slope = -2.0 * (sample.result - predicted) * predicted * (1.0 - predicted);
+        let slope = slope * k * 10f32.ln() / 400.0;
+        for (value, feature) in gradient.iter_mut().zip(sample.features) {
+            *value += slope * feature;
+        }
+    }
+    gradient.map(|value| value / samples.len() as f32)
+}
+
+/// Scans for the scale that best maps the current weights to results before tuning them.
+fn fit_scale(weights: &Weights, samples: &[Sample]) -> f32 {
+    (1..=300)
+        .map(|step| step as f32 / 100.0)
+        .min_by(|a, b| error(weights, samples, *a).total_cmp(&error(weights, samples, *b)))
+        .unwrap()
+}
+
+fn load_samples(path: &Path) -> Vec<Sample> {
+    if path.is_dir() {
+        let mut paths = fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "pdn"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        return paths.iter().flat_map(|path| load_samples(path)).collect();
+    }
+    let text = fs::read_to_string(path).unwrap();
+    match path.extension().is_some_and(|extension| extension == "pdn") {
+        true => samples_from_pdn(&text, path),
+        false => samples_from_positions(&text, path),
+    }
+}
+
+fn samples_from_pdn(text: &str, path: &Path) -> Vec<Sample> {
+    let games = match PdnGame::parse_all(text) {
+        Ok(games) => games,
+        Err(e) => {
+            eprintln!("Skipping {}: {e}", path.display());
+            return vec![];
+        }
+    };
+    let mut samples = vec![];
+    for game in games {
+        let (Some(result), Ok(replay)) = (game.result.map(outcome_to_result), game.replay()) else {
+            continue;
+        };
+        let mut board = replay.start;
+        for (ply, legal_move) in replay.moves.iter().enumerate() {
+            if ply >= SKIPPED_PLIES && is_quiet(&board) {
+                samples.push(Sample {
+                    features: Weights::features(&board),
+                    result,
+                });
+            }
+            board = Engine::make_move(board, legal_move.from, legal_move.to);
+        }
+    }
+    samples
+}
+
+fn samples_from_positions(text: &str, path: &Path) -> Vec<Sample> {
+    let mut samples = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line
+            .split_once(char::is_whitespace)
+            .and_then(|(result, fen)| {
+                let result = match result {
+                    "1-0" | "2-0" => 1.0,
+                    "0-1" | "0-2" => 0.0,
+                    "1/2-1/2" | "1-1" => 0.5,
+                    _ => return None,
+                };
+                Some((result, Board::from_fen(fen).ok()?))
+            });
+        match parsed {
+            Some((result, board)) => samples.push(Sample {
+                features: Weights::features(&board),
+                result,
+            }),
+            None => eprintln!("Skipping line {} of {}", number + 1, path.display()),
+        }
+    }
+    samples
+}
+
+/// Positions with a capture pending are scored mid-exchange, so they are left out.
+fn is_quiet(board: &Board) -> bool {
+    !Engine::get_moves(board)
+        .iter()
+        .any(|legal_move| legal_move.is_capture)
+}
+
+fn outcome_to_result(outcome: Outcome) -> f32 {
+    match outcome {
+        Outcome::WhiteWins => 1.0,
+        Outcome::BlackWins => 0.0,
+        Outcome::Draw => 0.5,
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: tune <dataset> [weights_path] [iterations]");
+    process::exit(1);
+}
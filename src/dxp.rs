@@ -0,0 +1,284 @@
+use std::{io, sync::Arc};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
+    sync::{broadcast, mpsc},
+};
+use tracing::event;
+
+use crate::{
+    engine::{Board, DxpMessage, DxpResult, Engine, Outcome, Turn},
+    routes::GamesRouter,
+    utility::random_id,
+    AppState, Room, Seat,
+};
+
+const SERVER_NAME: &str = "Checkers";
+
+/// Accepts DXP connections; each accepted game request gets a new room where the connecting
+/// engine holds a `Seat::Dxp` and the built-in computer plays the follower's colour.
+pub struct DxpServer {}
+
+impl DxpServer {
+    pub async fn listen(state: Arc<AppState>, port: String) {
+        let listener = match TcpListener::bind(format!("0.0.0.0:{port}")).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                event!(
+                    tracing::Level::ERROR,
+                    "DXP could not listen on port {port}: {e}"
+                );
+                return;
+            }
+        };
+        event!(tracing::Level::INFO, "DXP listening on port {port}");
+        Self::serve(state, listener).await
+    }
+
+    /// Plays a game with every engine connecting to the listener.
+    async fn serve(state: Arc<AppState>, listener: TcpListener) {
+        loop {
+            let (stream, address) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    event!(tracing::Level::ERROR, "DXP accept failed: {e}");
+                    continue;
+                }
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = Self::handle_connection(state, stream).await {
+                    event!(
+                        tracing::Level::ERROR,
+                        "DXP connection {address} failed: {e}"
+                    );
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(state: Arc<AppState>, stream: TcpStream) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut messages = Self::read_messages(reader);
+        let Some(DxpMessage::GameRequest {
+            name,
+            follower_color,
+            position,
+            ..
+        }) = messages.recv().await
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Expected a DXP game request",
+            ));
+        };
+        let id = random_id(8);
+        let mut room = Room::new(id.clone(), position.unwrap_or_else(Board::new));
+        match follower_color {
+            Turn::White => (room.white, room.black) = (Seat::Computer, Seat::Dxp),
+            Turn::Black => (room.white, room.black) = (Seat::Dxp, Seat::Computer),
+        }
+        let handle = state.create_room(room.clone()).await?;
+        let (_, mut rx) = handle.join().await?;
+        event!(tracing::Level::INFO, "DXP engine {name} joined room {id}");
+        let accept = DxpMessage::GameAccept {
+            name: SERVER_NAME.to_string(),
+            code: 0,
+        };
+        Self::send(&mut writer, &accept).await?;
+        GamesRouter::schedule_ai_move(state.clone(), &room);
+        // Position the remote engine has been told about.
+        let mut board = room.board.clone();
+        let mut synced = 0;
+        loop {
+            tokio::select! {
+                message = messages.recv() => match message {
+                    Some(message @ DxpMessage::Move { .. }) => {
+                        let room = handle.get().await?;
+                        let hash = room.board.hash();
+                        let played = match message.to_move(&room.board) {
+                            Some(remote_move) => handle
+                                .play(remote_move.from, remote_move.to, Seat::Dxp, None, Some(hash))
+                                .await?,
+                            None => (room, None),
+                        };
+                        match played {
+                            (room, Some(_)) => GamesRouter::schedule_ai_move(state.clone(), &room),
+                            _ => {
+                                Self::send(&mut writer, &Self::abort()).await?;
+                                return Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("Illegal DXP move {}", message.encode()),
+                                ));
+                            }
+                        }
+                    }
+                    Some(DxpMessage::GameEnd { .. }) | None => {
+                        return Self::send(&mut writer, &Self::abort()).await;
+                    }
+                    Some(DxpMessage::BackRequest { .. }) => {
+                        Self::send(&mut writer, &DxpMessage::BackAccept { code: 1 }).await?;
+                    }
+                    Some(_) => {}
+                },
+                update = rx.recv() => {
+                    if let Err(broadcast::error::RecvError::Closed) = update {
+                        return Ok(());
+                    }
+                    let room = handle.get().await?;
+                    // The room was reset from the web page, which ends the DXP game.
+                    if room.history.len() < synced {
+                        return Self::send(&mut writer, &Self::abort()).await;
+                    }
+                    for played_move in &room.history[synced..] {
+                        if room.seat(board.turn) != Seat::Dxp {
+                            Self::send(&mut writer, &DxpMessage::from_move(&board, played_move, 0))
+                                .await?;
+                        }
+                        board = Engine::make_move(board, played_move.from, played_move.to);
+                    }
+                    synced = room.history.len();
+                    if let Some(outcome) = room.outcome() {
+                        let result = match outcome {
+                            Outcome::Draw => DxpResult::Draw,
+                            _ if outcome == Outcome::win(follower_color) => DxpResult::Win,
+                            _ => DxpResult::Loss,
+                        };
+                        return Self::send(&mut writer, &DxpMessage::GameEnd { result, stop: 1 })
+                            .await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Forwards parsed messages from a reader task, so reads are never cut short by `select!`.
+    fn read_messages(reader: tokio::net::tcp::OwnedReadHalf) -> mpsc::Receiver<DxpMessage> {
+        let (sender, receiver) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            loop {
+                let mut bytes = vec![];
+                match reader.read_until(0, &mut bytes).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {}
+                }
+                let text = String::from_utf8_lossy(&bytes);
+                match DxpMessage::parse(&text) {
+                    Ok(message) => {
+                        if sender.send(message).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => event!(tracing::Level::WARN, "{e}"),
+                }
+            }
+        });
+        receiver
+    }
+
+    fn abort() -> DxpMessage {
+        DxpMessage::GameEnd {
+            result: DxpResult::Unknown,
+            stop: 1,
+        }
+    }
+
+    async fn send(writer: &mut OwnedWriteHalf, message: &DxpMessage) -> io::Result<()> {
+        writer.write_all(message.encode().as_bytes()).await?;
+        writer.write_all(&[0]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{net::tcp::OwnedReadHalf, time::timeout};
+
+    use super::*;
+    use crate::{
+        engine::{Move, Weights},
+        matchmaking::Matchmaker,
+        room_actor::RoomRegistry,
+        store::MemoryStore,
+        utility::Point,
+    };
+
+    const REPLY_TIME: Duration = Duration::from_secs(30);
+
+    fn state() -> Arc<AppState> {
+        let store: Arc<dyn crate::store::Store> = Arc::new(MemoryStore::default());
+        Arc::new(AppState {
+            rooms: RoomRegistry::new(store.clone()).unwrap(),
+            store,
+            matchmaker: Matchmaker::default(),
+            tablebase: None,
+            book: None,
+            weights: Weights::default(),
+            network: None,
+            external_engine: None,
+            mcts_simulations: 100,
+            search_pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+            search_threads: 1,
+        })
+    }
+
+    async fn send(writer: &mut OwnedWriteHalf, message: &DxpMessage) {
+        writer.write_all(message.encode().as_bytes()).await.unwrap();
+        writer.write_all(&[0]).await.unwrap();
+    }
+
+    async fn receive(reader: &mut BufReader<OwnedReadHalf>) -> DxpMessage {
+        let mut bytes = vec![];
+        timeout(REPLY_TIME, reader.read_until(0, &mut bytes))
+            .await
+            .expect("the server answers in time")
+            .unwrap();
+        DxpMessage::parse(&String::from_utf8_lossy(&bytes)).unwrap()
+    }
+
+    /// An engine connecting as white plays a move, and the server's computer answers as
+    /// black.
+    #[tokio::test]
+    async fn engines_play_moves_with_the_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let state = state();
+        tokio::spawn(DxpServer::serve(state.clone(), listener));
+
+        let (reader, mut writer) = TcpStream::connect(address).await.unwrap().into_split();
+        let mut reader = BufReader::new(reader);
+        let request = DxpMessage::GameRequest {
+            name: "Tester".to_string(),
+            follower_color: Turn::Black,
+            minutes: 5,
+            moves: 50,
+            position: None,
+        };
+        send(&mut writer, &request).await;
+        let accept = receive(&mut reader).await;
+        assert!(
+            matches!(accept, DxpMessage::GameAccept { code: 0, .. }),
+            "{accept:?}"
+        );
+
+        let board = Board::new();
+        let opening = Move::new(Point::new(2, 5), Point::new(3, 4), false);
+        send(&mut writer, &DxpMessage::from_move(&board, &opening, 0)).await;
+        let board = Engine::make_move(board, opening.from, opening.to);
+        let answer = receive(&mut reader).await;
+        let reply = answer.to_move(&board);
+        assert!(reply.is_some(), "{answer:?}");
+
+        let summaries = state.rooms.summaries();
+        assert_eq!(summaries.len(), 1);
+        let room = state.get_room(&summaries[0].id).await.unwrap();
+        assert_eq!(room.history, [opening, reply.unwrap()]);
+        assert!(room.white == Seat::Dxp && room.black == Seat::Computer);
+    }
+}
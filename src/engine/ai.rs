@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use super::{Board, Move, Network, OpeningBook, Search, SearchLimits, Tablebase, Weights};
+
+const AI_TABLE_BITS: u32 = 18;
+
+/// Computer player: plays from the opening book while the position is in it, then searches.
+#[derive(Clone)]
+pub struct Ai {
+    limits: SearchLimits,
+    book: Option<Arc<OpeningBook>>,
+    tablebase: Option<Arc<Tablebase>>,
+    weights: Weights,
+    network: Option<Arc<Network>>,
+    threads: usize,
+}
+
+impl Ai {
+    pub fn new(limits: SearchLimits) -> Self {
+        Self {
+            limits,
+            book: None,
+            tablebase: None,
+            weights: Weights::default(),
+            network: None,
+            threads: 1,
+        }
+    }
+
+    pub fn with_book(mut self, book: Option<Arc<OpeningBook>>) -> Self {
+        self.book = book;
+        self
+    }
+
+    pub fn with_tablebase(mut self, tablebase: Option<Arc<Tablebase>>) -> Self {
+        self.tablebase = tablebase;
+        self
+    }
+
+    pub fn with_weights(mut self, weights: Weights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn with_network(mut self, network: Option<Arc<Network>>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Search threads per move; more than one runs on the caller's rayon pool.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn choose_move(&self, board: &Board) -> Option<Move> {
+        let book_move = self
+            .book
+            .as_ref()
+            .and_then(|book| book.choose(board, &mut rand::thread_rng()));
+        book_move.or_else(|| {
+            Search::new(AI_TABLE_BITS)
+                .with_tablebase(self.tablebase.clone())
+                .with_weights(self.weights)
+                .with_network(self.network.clone())
+                .with_threads(self.threads)
+                .run(board, self.limits)
+                .best_move()
+        })
+    }
+}
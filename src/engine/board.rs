@@ -1,14 +1,37 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-use super::{constants::BOARD_SIZE, Cell, Checker, Turn};
+use super::{constants::BOARD_SIZE, Cell, Checker, Turn, Zobrist};
 use crate::utility::Point;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(from = "BoardData")]
 pub struct Board {
     pub size: usize,
-    pub cells: Vec<Vec<Cell>>,
+    pub cells: [[Cell; BOARD_SIZE]; BOARD_SIZE],
     pub turn: Turn,
+    #[serde(skip)]
+    pieces_hash: u64,
+}
+
+#[derive(Deserialize)]
+struct BoardData {
+    size: usize,
+    cells: [[Cell; BOARD_SIZE]; BOARD_SIZE],
+    turn: Turn,
+}
+
+impl From<BoardData> for Board {
+    fn from(data: BoardData) -> Self {
+        let mut board = Board {
+            size: data.size,
+            cells: data.cells,
+            turn: data.turn,
+            pieces_hash: 0,
+        };
+        board.pieces_hash = Zobrist::hash(&board);
+        board
+    }
 }
 
 impl Board {
@@ -20,12 +43,10 @@ impl Board {
             for j in 0..board.size {
                 if (i + j) % 2 != 0 {
                     match i {
-                        _ if i < black_last_row => {
-                            board.cells[i][j] = Cell::Checker(Checker::Black)
-                        }
-                        _ if i > white_last_row => {
-                            board.cells[i][j] = Cell::Checker(Checker::White)
-                        }
+                        _ if i < black_last_row => board
+                            .set_cell(Point::new(j as i8, i as i8), Cell::Checker(Checker::Black)),
+                        _ if i > white_last_row => board
+                            .set_cell(Point::new(j as i8, i as i8), Cell::Checker(Checker::White)),
                         _ => continue,
                     }
                 }
@@ -34,7 +55,7 @@ impl Board {
         board
     }
 
-    pub fn iter(&self) -> BoardIterator {
+    pub fn iter(&self) -> BoardIterator<'_> {
         BoardIterator {
             board: self,
             x: 0,
@@ -58,9 +79,19 @@ impl Board {
     }
 
     pub fn set_cell(&mut self, point: Point, cell: Cell) {
+        self.pieces_hash ^=
+            Zobrist::cell(point, self.get_cell(point)) ^ Zobrist::cell(point, &cell);
         self.cells[point.y as usize][point.x as usize] = cell;
     }
 
+    /// Zobrist key of the pieces and the side to move.
+    pub fn hash(&self) -> u64 {
+        match self.turn {
+            Turn::White => self.pieces_hash,
+            Turn::Black => self.pieces_hash ^ Zobrist::black_to_move(),
+        }
+    }
+
     pub fn get_cell(&self, point: Point) -> &Cell {
         &self.cells[point.y as usize][point.x as usize]
     }
@@ -118,8 +149,9 @@ impl Default for Board {
     fn default() -> Self {
         Self {
             size: BOARD_SIZE,
-            cells: vec![vec![Cell::Empty; BOARD_SIZE]; BOARD_SIZE],
+            cells: [[Cell::Empty; BOARD_SIZE]; BOARD_SIZE],
             turn: Turn::White,
+            pieces_hash: 0,
         }
     }
 }
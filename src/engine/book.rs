@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use rand::Rng;
+
+use super::{
+    Board, Engine, Move, Notation, NotationStyle, Outcome, Replay, Search, SearchLimits, Turn,
+};
+
+const WIN_WEIGHT: u32 = 3;
+const DRAW_WEIGHT: u32 = 2;
+const LOSS_WEIGHT: u32 = 1;
+/// Self-play considers every root move within this margin of the best one.
+const SELF_PLAY_MARGIN: i32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookMove {
+    pub legal_move: Move,
+    pub weight: u32,
+}
+
+/// Weighted candidate moves keyed by the position's Zobrist hash.
+///
+/// On disk it is a text file with one `<hash in hex> <numeric move> <weight>` line per move,
+/// e.g. `5a1f0c3e9b2d4871 11-15 12`. Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn probe(&self, board: &Board) -> Option<&[BookMove]> {
+        self.entries.get(&board.hash()).map(Vec::as_slice)
+    }
+
+    /// Picks one of the legal book moves at random, proportionally to its weight.
+    pub fn choose(&self, board: &Board, rng: &mut impl Rng) -> Option<Move> {
+        let candidates = self
+            .probe(board)?
+            .iter()
+            .filter(|book_move| book_move.weight > 0)
+            .filter_map(|book_move| {
+                let Move { from, to, .. } = book_move.legal_move;
+                Some((Engine::find_move(board, from, to)?, book_move.weight))
+            })
+            .collect::<Vec<(Move, u32)>>();
+        let total = candidates.iter().map(|(_, weight)| weight).sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (legal_move, weight) in candidates {
+            if pick < weight {
+                return Some(legal_move);
+            }
+            pick -= weight;
+        }
+        None
+    }
+
+    pub fn add(&mut self, board: &Board, legal_move: Move, weight: u32) {
+        let moves = self.entries.entry(board.hash()).or_default();
+        match moves
+            .iter_mut()
+            .find(|book_move| book_move.legal_move == legal_move)
+        {
+            Some(book_move) => book_move.weight += weight,
+            None => moves.push(BookMove { legal_move, weight }),
+        }
+    }
+
+    /// Adds the first `max_plies` moves of a game, weighting them by the mover's result.
+    pub fn add_game(&mut self, replay: &Replay, result: Option<Outcome>, max_plies: usize) {
+        let mut board = replay.start.clone();
+        for legal_move in replay.moves.iter().take(max_plies) {
+            let weight = match result {
+                Some(outcome) if outcome == Outcome::win(board.turn) => WIN_WEIGHT,
+                Some(Outcome::Draw) | None => DRAW_WEIGHT,
+                Some(_) => LOSS_WEIGHT,
+            };
+            self.add(&board, *legal_move, weight);
+            board = Engine::make_move(board, legal_move.from, legal_move.to);
+        }
+    }
+
+    /// Plays `games` openings of `max_plies` moves from the initial position. Each move is
+    /// drawn at random among the root moves scoring within `SELF_PLAY_MARGIN` of the best.
+    pub fn from_self_play(
+        games: usize,
+        max_plies: usize,
+        limits: SearchLimits,
+        rng: &mut impl Rng,
+        mut progress: impl FnMut(usize),
+    ) -> Self {
+        let mut book = OpeningBook::default();
+        let mut search = Search::new(16);
+        for game in 0..games {
+            let mut board = Board::new();
+            for _ in 0..max_plies {
+                let scored = Engine::get_moves(&board)
+                    .into_iter()
+                    .map(|legal_move| {
+                        let child =
+                            Engine::make_move(board.clone(), legal_move.from, legal_move.to);
+                        let score = search.run(&child, limits).score;
+                        match board.turn {
+                            Turn::White => (legal_move, score),
+                            Turn::Black => (legal_move, -score),
+                        }
+                    })
+                    .collect::<Vec<(Move, i32)>>();
+                let Some(best) = scored.iter().map(|(_, score)| *score).max() else {
+                    break;
+                };
+                let candidates = scored
+                    .into_iter()
+                    .filter(|(_, score)| *score >= best - SELF_PLAY_MARGIN)
+                    .collect::<Vec<(Move, i32)>>();
+                let (legal_move, _) = candidates[rng.gen_range(0..candidates.len())];
+                book.add(&board, legal_move, 1);
+                board = Engine::make_move(board, legal_move.from, legal_move.to);
+            }
+            progress(game + 1);
+        }
+        book
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut keys = self.entries.keys().copied().collect::<Vec<u64>>();
+        keys.sort_unstable();
+        let mut text = String::new();
+        for key in keys {
+            for book_move in &self.entries[&key] {
+                let notation = Notation::format_move(&book_move.legal_move, NotationStyle::Numeric);
+                text.push_str(&format!("{key:016x} {notation} {}\n", book_move.weight));
+            }
+        }
+        fs::File::create(path)?.write_all(text.as_bytes())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut book = OpeningBook::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid book line {}: {line:?}", number + 1),
+                )
+            };
+            let mut fields = line.split_whitespace();
+            let key = fields
+                .next()
+                .and_then(|key| u64::from_str_radix(key, 16).ok())
+                .ok_or_else(invalid)?;
+            let notation = fields.next().ok_or_else(invalid)?;
+            let (from, to) = Notation::parse_move(notation).ok_or_else(invalid)?;
+            let weight = fields
+                .next()
+                .and_then(|weight| weight.parse::<u32>().ok())
+                .ok_or_else(invalid)?;
+            let legal_move = Move::new(from, to, notation.contains(['x', ':']));
+            let moves = book.entries.entry(key).or_default();
+            moves.push(BookMove { legal_move, weight });
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn book_move(notation: &str) -> Move {
+        let (from, to) = Notation::parse_move(notation).unwrap();
+        Move::new(from, to, notation.contains('x'))
+    }
+
+    #[test]
+    fn saved_books_load() {
+        let start = Board::new();
+        let after = Engine::make_move(
+            start.clone(),
+            book_move("22-18").from,
+            book_move("22-18").to,
+        );
+        let mut book = OpeningBook::default();
+        book.add(&start, book_move("22-18"), 5);
+        book.add(&start, book_move("23-19"), 2);
+        book.add(&start, book_move("22-18"), 1);
+        book.add(&after, book_move("11-15"), 3);
+        let path = env::temp_dir().join(format!("checkers-book-{}.txt", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = OpeningBook::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        let moves = [
+            BookMove {
+                legal_move: book_move("22-18"),
+                weight: 6,
+            },
+            BookMove {
+                legal_move: book_move("23-19"),
+                weight: 2,
+            },
+        ];
+        assert_eq!(loaded.probe(&start).unwrap(), moves);
+        assert_eq!(loaded.probe(&after), book.probe(&after));
+    }
+
+    #[test]
+    fn invalid_lines_name_their_number() {
+        let path = env::temp_dir().join(format!("checkers-bad-book-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "# comment\n\n5a1f0c3e9b2d4871 11-15 12\n5a1f0c3e9b2d4871 11-15\n",
+        )
+        .unwrap();
+        let error = OpeningBook::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 4"), "{error}");
+    }
+
+    #[test]
+    fn choices_follow_weights_among_legal_moves() {
+        let start = Board::new();
+        let mut book = OpeningBook::default();
+        // Neither may ever be chosen: one is no legal move, the other has no weight.
+        book.add(&start, book_move("22-15"), 100);
+        book.add(&start, book_move("23-19"), 0);
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(book.choose(&start, &mut rng), None);
+        book.add(&start, book_move("22-18"), 3);
+        book.add(&start, book_move("24-20"), 1);
+        let mut counts = HashMap::new();
+        for _ in 0..4000 {
+            let chosen = book.choose(&start, &mut rng).unwrap();
+            *counts
+                .entry(Notation::format_move(&chosen, NotationStyle::Numeric))
+                .or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 2, "{counts:?}");
+        assert!((2800..3200).contains(&counts["22-18"]), "{counts:?}");
+        assert_eq!(
+            book.choose(
+                &Engine::make_move(start, book_move("22-18").from, book_move("22-18").to),
+                &mut rng
+            ),
+            None
+        );
+    }
+}
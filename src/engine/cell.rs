@@ -2,9 +2,10 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
+use super::Turn;
 use crate::Checker;
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq)]
 pub enum Cell {
     #[default]
     Empty,
@@ -22,6 +23,13 @@ impl Cell {
         matches!(self, Cell::Checker(_))
     }
 
+    pub fn belongs_to(&self, turn: Turn) -> bool {
+        match self {
+            Cell::Checker(checker) => checker.is_white() == turn.is_white(),
+            _ => false,
+        }
+    }
+
     pub fn is_move(&self) -> bool {
         matches!(self, Cell::Move | Cell::Capture)
     }
@@ -0,0 +1,395 @@
+use std::{error::Error, fmt::Display};
+
+use super::{Board, Cell, Checker, Engine, Move, Notation, Turn};
+
+const VERSION: &str = "01";
+const NAME_LENGTH: usize = 32;
+const SQUARES: u8 = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DxpError {
+    Empty,
+    UnknownType(char),
+    InvalidField(&'static str, String),
+}
+
+impl Display for DxpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DxpError::Empty => write!(f, "Empty DXP message"),
+            DxpError::UnknownType(kind) => write!(f, "Unknown DXP message type {kind:?}"),
+            DxpError::InvalidField(field, message) => {
+                write!(f, "Invalid DXP {field} in {message:?}")
+            }
+        }
+    }
+}
+
+impl Error for DxpError {}
+
+/// Why a game ended, from the point of view of the side sending `GameEnd`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DxpResult {
+    Unknown,
+    Loss,
+    Draw,
+    Win,
+}
+
+/// A Dam eXchange Protocol message. On the wire each one is ASCII terminated by a NUL byte.
+///
+/// Squares use the 1-32 numbering, colours are `W` (white) and `Z` (black).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DxpMessage {
+    /// `R`: the initiator asks for a game where the follower plays `follower_color`.
+    GameRequest {
+        name: String,
+        follower_color: Turn,
+        minutes: u16,
+        moves: u16,
+        position: Option<Board>,
+    },
+    /// `A`: the follower answers a request; `code` 0 accepts it.
+    GameAccept { name: String, code: u8 },
+    /// `M`: a move with the squares of every captured piece.
+    Move {
+        seconds: u16,
+        from: u8,
+        to: u8,
+        captured: Vec<u8>,
+    },
+    /// `E`: the game is over; `stop` 1 means no further game is wanted.
+    GameEnd { result: DxpResult, stop: u8 },
+    /// `C`: free text.
+    Chat(String),
+    /// `B`: asks to take back moves up to `move_number` with `turn` to move.
+    BackRequest { move_number: u16, turn: Turn },
+    /// `K`: answers a take-back request; `code` 0 accepts it.
+    BackAccept { code: u8 },
+}
+
+impl DxpMessage {
+    pub fn parse(message: &str) -> Result<Self, DxpError> {
+        let message = message.trim_end_matches('\0');
+        let kind = message.chars().next().ok_or(DxpError::Empty)?;
+        let body = &message[kind.len_utf8()..];
+        let invalid = |field| DxpError::InvalidField(field, message.to_string());
+        let field = |start: usize, length: usize, name| {
+            body.get(start..start + length).ok_or_else(|| invalid(name))
+        };
+        let number = |start: usize, length: usize, name| {
+            field(start, length, name)?
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| invalid(name))
+        };
+        let square = |start: usize, name| {
+            number(start, 2, name).and_then(|square| match square {
+                1..=32 => Ok(square as u8),
+                _ => Err(invalid(name)),
+            })
+        };
+        match kind {
+            'R' => {
+                if field(0, 2, "version")? != VERSION {
+                    return Err(invalid("version"));
+                }
+                let offset = 2 + NAME_LENGTH;
+                let position = match field(offset + 7, 1, "start")? {
+                    "A" => None,
+                    "B" => Some(
+                        DxpMessage::parse_position(field(
+                            offset + 8,
+                            1 + SQUARES as usize,
+                            "position",
+                        )?)
+                        .ok_or_else(|| invalid("position"))?,
+                    ),
+                    _ => return Err(invalid("start")),
+                };
+                Ok(DxpMessage::GameRequest {
+                    name: field(2, NAME_LENGTH, "name")?.trim().to_string(),
+                    follower_color: DxpMessage::parse_color(field(offset, 1, "color")?)
+                        .ok_or_else(|| invalid("color"))?,
+                    minutes: number(offset + 1, 3, "time")?,
+                    moves: number(offset + 4, 3, "moves")?,
+                    position,
+                })
+            }
+            'A' => Ok(DxpMessage::GameAccept {
+                name: field(0, NAME_LENGTH, "name")?.trim().to_string(),
+                code: number(NAME_LENGTH, 1, "code")? as u8,
+            }),
+            'M' => {
+                let count = number(8, 2, "captures")? as usize;
+                let captured = (0..count)
+                    .map(|i| square(10 + i * 2, "captured square"))
+                    .collect::<Result<Vec<u8>, DxpError>>()?;
+                Ok(DxpMessage::Move {
+                    seconds: number(0, 4, "time")?,
+                    from: square(4, "from")?,
+                    to: square(6, "to")?,
+                    captured,
+                })
+            }
+            'E' => Ok(DxpMessage::GameEnd {
+                result: match number(0, 1, "reason")? {
+                    1 => DxpResult::Loss,
+                    2 => DxpResult::Draw,
+                    3 => DxpResult::Win,
+                    _ => DxpResult::Unknown,
+                },
+                stop: number(1, 1, "stop code")? as u8,
+            }),
+            'C' => Ok(DxpMessage::Chat(body.to_string())),
+            'B' => Ok(DxpMessage::BackRequest {
+                move_number: number(0, 3, "move number")?,
+                turn: DxpMessage::parse_color(field(3, 1, "color")?)
+                    .ok_or_else(|| invalid("color"))?,
+            }),
+            'K' => Ok(DxpMessage::BackAccept {
+                code: number(0, 1, "code")? as u8,
+            }),
+            _ => Err(DxpError::UnknownType(kind)),
+        }
+    }
+
+    /// Describes a legal move of `board`, listing the pieces it captures.
+    pub fn from_move(board: &Board, legal_move: &Move, seconds: u16) -> Self {
+        let captured = Engine::captured_pieces(board, legal_move)
+            .into_iter()
+            .filter_map(Notation::square)
+            .collect();
+        DxpMessage::Move {
+            seconds,
+            from: Notation::square(legal_move.from).unwrap_or_default(),
+            to: Notation::square(legal_move.to).unwrap_or_default(),
+            captured,
+        }
+    }
+
+    /// The legal move of `board` described by a `Move` message.
+    pub fn to_move(&self, board: &Board) -> Option<Move> {
+        let DxpMessage::Move { from, to, .. } = self else {
+            return None;
+        };
+        let from = Notation::from_square(*from)?;
+        let to = Notation::from_square(*to)?;
+        Engine::find_move(board, from, to)
+    }
+
+    /// Encodes the message without its NUL terminator.
+    pub fn encode(&self) -> String {
+        match self {
+            DxpMessage::GameRequest {
+                name,
+                follower_color,
+                minutes,
+                moves,
+                position,
+            } => {
+                let position = match position {
+                    Some(board) => format!("B{}", DxpMessage::format_position(board)),
+                    None => "A".to_string(),
+                };
+                format!(
+                    "R{VERSION}{}{}{minutes:03}{moves:03}{position}",
+                    DxpMessage::format_name(name),
+                    DxpMessage::format_color(*follower_color)
+                )
+            }
+            DxpMessage::GameAccept { name, code } => {
+                format!("A{}{code}", DxpMessage::format_name(name))
+            }
+            DxpMessage::Move {
+                seconds,
+                from,
+                to,
+                captured,
+            } => {
+                let captured = captured
+                    .iter()
+                    .map(|square| format!("{square:02}"))
+                    .collect::<String>();
+                format!(
+                    "M{seconds:04}{from:02}{to:02}{:02}{captured}",
+                    captured.len() / 2
+                )
+            }
+            DxpMessage::GameEnd { result, stop } => {
+                let reason = match result {
+                    DxpResult::Unknown => 0,
+                    DxpResult::Loss => 1,
+                    DxpResult::Draw => 2,
+                    DxpResult::Win => 3,
+                };
+                format!("E{reason}{stop}")
+            }
+            DxpMessage::Chat(text) => format!("C{text}"),
+            DxpMessage::BackRequest { move_number, turn } => {
+                format!("B{move_number:03}{}", DxpMessage::format_color(*turn))
+            }
+            DxpMessage::BackAccept { code } => format!("K{code}"),
+        }
+    }
+
+    fn format_name(name: &str) -> String {
+        let name = name.chars().take(NAME_LENGTH).collect::<String>();
+        format!("{name:<NAME_LENGTH$}")
+    }
+
+    fn format_color(turn: Turn) -> char {
+        match turn {
+            Turn::White => 'W',
+            Turn::Black => 'Z',
+        }
+    }
+
+    fn parse_color(color: &str) -> Option<Turn> {
+        match color {
+            "W" => Some(Turn::White),
+            "Z" => Some(Turn::Black),
+            _ => None,
+        }
+    }
+
+    /// Side to move followed by one `e`/`w`/`z`/`W`/`Z` character per square.
+    fn format_position(board: &Board) -> String {
+        let squares = (1..=SQUARES)
+            .filter_map(Notation::from_square)
+            .map(|point| match board.get_cell(point) {
+                Cell::Checker(Checker::White) => 'w',
+                Cell::Checker(Checker::Black) => 'z',
+                Cell::Checker(Checker::WhiteQueen) => 'W',
+                Cell::Checker(Checker::BlackQueen) => 'Z',
+                _ => 'e',
+            })
+            .collect::<String>();
+        format!("{}{squares}", DxpMessage::format_color(board.turn))
+    }
+
+    fn parse_position(position: &str) -> Option<Board> {
+        let turn = DxpMessage::parse_color(position.get(..1)?)?;
+        let mut board = Board::default();
+        for (square, piece) in (1..=SQUARES).zip(position[1..].chars()) {
+            let checker = match piece {
+                'w' => Checker::White,
+                'z' => Checker::Black,
+                'W' => Checker::WhiteQueen,
+                'Z' => Checker::BlackQueen,
+                'e' => continue,
+                _ => return None,
+            };
+            board.set_cell(Notation::from_square(square)?, Cell::Checker(checker));
+        }
+        board.turn = turn;
+        Some(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            DxpMessage::GameRequest {
+                name: "Initiator".to_string(),
+                follower_color: Turn::Black,
+                minutes: 5,
+                moves: 75,
+                position: None,
+            },
+            DxpMessage::GameRequest {
+                name: "Initiator".to_string(),
+                follower_color: Turn::White,
+                minutes: 120,
+                moves: 0,
+                position: Some(Board::from_fen("B:W18,K30:BK1,K2,14").unwrap()),
+            },
+            DxpMessage::GameAccept {
+                name: "Follower".to_string(),
+                code: 0,
+            },
+            DxpMessage::Move {
+                seconds: 12,
+                from: 22,
+                to: 18,
+                captured: vec![],
+            },
+            DxpMessage::Move {
+                seconds: 0,
+                from: 27,
+                to: 4,
+                captured: vec![24, 16, 8],
+            },
+            DxpMessage::GameEnd {
+                result: DxpResult::Draw,
+                stop: 1,
+            },
+            DxpMessage::Chat("Good game".to_string()),
+            DxpMessage::BackRequest {
+                move_number: 12,
+                turn: Turn::White,
+            },
+            DxpMessage::BackAccept { code: 1 },
+        ];
+        for message in messages {
+            let encoded = message.encode();
+            assert_eq!(DxpMessage::parse(&encoded), Ok(message), "{encoded}");
+        }
+    }
+
+    #[test]
+    fn messages_are_fixed_width() {
+        let accept = DxpMessage::GameAccept {
+            name: "Follower".to_string(),
+            code: 0,
+        };
+        assert_eq!(accept.encode(), format!("A{:<32}0", "Follower"));
+        let capture = DxpMessage::Move {
+            seconds: 3,
+            from: 27,
+            to: 18,
+            captured: vec![23],
+        };
+        assert_eq!(capture.encode(), "M000327180123");
+    }
+
+    #[test]
+    fn invalid_messages_name_the_field() {
+        assert_eq!(DxpMessage::parse(""), Err(DxpError::Empty));
+        assert_eq!(DxpMessage::parse("X"), Err(DxpError::UnknownType('X')));
+        assert!(matches!(
+            DxpMessage::parse("M0003331800"),
+            Err(DxpError::InvalidField("from", _))
+        ));
+        assert!(matches!(
+            DxpMessage::parse("R02Initiator"),
+            Err(DxpError::InvalidField("version", _))
+        ));
+    }
+
+    #[test]
+    fn moves_list_their_captures() {
+        let board = Board::from_fen("W:W22:B18,11").unwrap();
+        let capture = Engine::find_move(
+            &board,
+            Notation::from_square(22).unwrap(),
+            Notation::from_square(8).unwrap(),
+        )
+        .unwrap();
+        let message = DxpMessage::from_move(&board, &capture, 7);
+        assert_eq!(
+            message,
+            DxpMessage::Move {
+                seconds: 7,
+                from: 22,
+                to: 8,
+                captured: vec![11, 18],
+            }
+        );
+        assert_eq!(message.to_move(&board), Some(capture));
+    }
+}
@@ -0,0 +1,200 @@
+use std::{fmt::Display, fs, io, path::Path};
+
+use super::{constants::BOARD_SIZE, Board, Cell, Checker, Engine, Turn};
+use crate::utility::Point;
+
+pub const FEATURE_COUNT: usize = 7;
+
+/// Terms of the linear evaluation, each measured as White's count minus Black's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    /// Men on the board.
+    Material,
+    /// Kings on the board.
+    KingValue,
+    /// Rows the men have advanced.
+    Tempo,
+    /// Pieces on the central 4x4 squares.
+    CenterControl,
+    /// Men still guarding their own back rank.
+    BackRankGuard,
+    /// Men that no enemy piece can stop from crowning.
+    RunawayMen,
+    /// Non-capturing moves available.
+    Mobility,
+}
+
+impl Feature {
+    pub const ALL: [Feature; FEATURE_COUNT] = [
+        Feature::Material,
+        Feature::KingValue,
+        Feature::Tempo,
+        Feature::CenterControl,
+        Feature::BackRankGuard,
+        Feature::RunawayMen,
+        Feature::Mobility,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Material => "material",
+            Feature::KingValue => "king_value",
+            Feature::Tempo => "tempo",
+            Feature::CenterControl => "center_control",
+            Feature::BackRankGuard => "back_rank_guard",
+            Feature::RunawayMen => "runaway_men",
+            Feature::Mobility => "mobility",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Feature::ALL
+            .into_iter()
+            .find(|feature| feature.name() == name)
+    }
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Weight of every `Feature`, in hundredths of a man.
+///
+/// On disk it is a text file with one `<feature name> <weight>` line per feature, e.g.
+/// `runaway_men 30`. Missing features keep their default weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    pub values: [f32; FEATURE_COUNT],
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            values: [100.0, 300.0, 2.0, 5.0, 8.0, 30.0, 2.0],
+        }
+    }
+}
+
+impl Weights {
+    pub fn get(&self, feature: Feature) -> f32 {
+        self.values[feature as usize]
+    }
+
+    /// Score from the side to move's point of view.
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        let score = self.score(&Weights::features(board)).round() as i32;
+        match board.turn {
+            Turn::White => score,
+            Turn::Black => -score,
+        }
+    }
+
+    /// Score of precomputed features from White's point of view.
+    pub fn score(&self, features: &[f32; FEATURE_COUNT]) -> f32 {
+        self.values
+            .iter()
+            .zip(features)
+            .map(|(weight, feature)| weight * feature)
+            .sum()
+    }
+
+    /// Every feature of the position, from White's point of view.
+    pub fn features(board: &Board) -> [f32; FEATURE_COUNT] {
+        let mut features = [0.0; FEATURE_COUNT];
+        let last_row = BOARD_SIZE as i8 - 1;
+        for (point, cell) in board.iter() {
+            let Cell::Checker(checker) = cell else {
+                continue;
+            };
+            let sign = if checker.is_white() { 1.0 } else { -1.0 };
+            let mut add = |feature: Feature, value: f32| features[feature as usize] += sign * value;
+            if (2..=5).contains(&point.x) && (2..=5).contains(&point.y) {
+                add(Feature::CenterControl, 1.0);
+            }
+            if checker.is_queen() {
+                add(Feature::KingValue, 1.0);
+                continue;
+            }
+            add(Feature::Material, 1.0);
+            let advanced = match checker {
+                Checker::White => last_row - point.y,
+                _ => point.y,
+            };
+            add(Feature::Tempo, advanced as f32);
+            if advanced == 0 {
+                add(Feature::BackRankGuard, 1.0);
+            }
+            if Weights::is_runaway(board, point, checker) {
+                add(Feature::RunawayMen, 1.0);
+            }
+        }
+        features[Feature::Mobility as usize] =
+            Weights::mobility(board, Turn::White) - Weights::mobility(board, Turn::Black);
+        features
+    }
+
+    /// The man's forward cone up to the promotion row holds no enemy piece.
+    fn is_runaway(board: &Board, point: Point, checker: &Checker) -> bool {
+        let (direction, promotion_row) = match checker {
+            Checker::White => (-1, 0),
+            _ => (1, BOARD_SIZE as i8 - 1),
+        };
+        let mut row = point.y;
+        let mut distance = 0;
+        while row != promotion_row {
+            row += direction;
+            distance += 1;
+            for x in (point.x - distance).max(0)..=(point.x + distance).min(BOARD_SIZE as i8 - 1) {
+                if let Cell::Checker(other) = board.get_cell(Point::new(x, row)) {
+                    if checker.is_enemy(other) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn mobility(board: &Board, turn: Turn) -> f32 {
+        let mut board = board.clone();
+        board.turn = turn;
+        Engine::get_moves(&board)
+            .iter()
+            .filter(|legal_move| !legal_move.is_capture)
+            .count() as f32
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text = Feature::ALL
+            .iter()
+            .map(|feature| format!("{feature} {}\n", self.get(*feature)))
+            .collect::<String>();
+        fs::write(path, text)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut weights = Weights::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line
+                .split_once(char::is_whitespace)
+                .and_then(|(name, value)| {
+                    Some((Feature::from_name(name)?, value.trim().parse::<f32>().ok()?))
+                });
+            let Some((feature, value)) = parsed else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid weights line {}: {line:?}", number + 1),
+                ));
+            };
+            weights.values[feature as usize] = value;
+        }
+        Ok(weights)
+    }
+}
@@ -0,0 +1,179 @@
+use std::{collections::HashSet, error::Error, fmt::Display};
+
+use super::{constants::BOARD_SIZE, Board, Cell, Checker, Notation, Turn};
+use crate::utility::Point;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    MissingTurn,
+    InvalidTurn(String),
+    InvalidColor(String),
+    InvalidSquare(String),
+    DuplicateSquare(u8),
+    ManOnPromotionRow(u8),
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingTurn => write!(f, "FEN is missing the side to move"),
+            FenError::InvalidTurn(turn) => write!(f, "Invalid side to move: {turn:?}"),
+            FenError::InvalidColor(section) => write!(f, "Invalid piece section: {section:?}"),
+            FenError::InvalidSquare(square) => write!(f, "Invalid square: {square:?}"),
+            FenError::DuplicateSquare(square) => write!(f, "Square {square} is occupied twice"),
+            FenError::ManOnPromotionRow(square) => {
+                write!(f, "Uncrowned piece on promotion square {square}")
+            }
+        }
+    }
+}
+
+impl Error for FenError {}
+
+impl Board {
+    /// Parses a PDN FEN string, e.g. `W:W21,22,K30:B1,2,3`.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let fen = fen.trim().trim_matches('"').trim_end_matches('.');
+        let mut sections = fen.split(':').map(str::trim);
+        let turn = match sections.next() {
+            Some("W") => Turn::White,
+            Some("B") => Turn::Black,
+            Some("") | None => return Err(FenError::MissingTurn),
+            Some(turn) => return Err(FenError::InvalidTurn(turn.to_string())),
+        };
+        let mut board = Board::default();
+        let mut occupied = HashSet::new();
+        for section in sections.filter(|section| !section.is_empty()) {
+            let (man, king) = match section.chars().next() {
+                Some('W') => (Checker::White, Checker::WhiteQueen),
+                Some('B') => (Checker::Black, Checker::BlackQueen),
+                _ => return Err(FenError::InvalidColor(section.to_string())),
+            };
+            let pieces = section[1..].split(',').map(str::trim);
+            for piece in pieces.filter(|piece| !piece.is_empty()) {
+                let (checker, squares) = match piece.strip_prefix('K') {
+                    Some(squares) => (king, squares),
+                    None => (man, piece),
+                };
+                for square in parse_squares(squares)? {
+                    if !occupied.insert(square) {
+                        return Err(FenError::DuplicateSquare(square));
+                    }
+                    let point = Notation::from_square(square).unwrap();
+                    if is_promotion_row(point, checker) {
+                        return Err(FenError::ManOnPromotionRow(square));
+                    }
+                    board.set_cell(point, Cell::Checker(checker));
+                }
+            }
+        }
+        board.turn = turn;
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let turn = if self.turn.is_white() { "W" } else { "B" };
+        let white = self.fen_section(Checker::is_white);
+        let black = self.fen_section(Checker::is_black);
+        format!("{turn}:W{white}:B{black}")
+    }
+
+    fn fen_section(&self, is_side: fn(&Checker) -> bool) -> String {
+        let mut pieces = self
+            .iter()
+            .filter_map(|(point, cell)| match cell {
+                Cell::Checker(checker) if is_side(checker) => {
+                    Some((Notation::square(point)?, checker.is_queen()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<(u8, bool)>>();
+        pieces.sort();
+        pieces
+            .into_iter()
+            .map(|(square, is_queen)| match is_queen {
+                true => format!("K{square}"),
+                false => square.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+fn parse_squares(squares: &str) -> Result<Vec<u8>, FenError> {
+    let parse = |square: &str| {
+        square
+            .trim()
+            .parse::<u8>()
+            .ok()
+            .filter(|square| Notation::from_square(*square).is_some())
+            .ok_or_else(|| FenError::InvalidSquare(squares.to_string()))
+    };
+    match squares.split_once('-') {
+        Some((first, last)) => {
+            let (first, last) = (parse(first)?, parse(last)?);
+            if first > last {
+                return Err(FenError::InvalidSquare(squares.to_string()));
+            }
+            Ok((first..=last).collect())
+        }
+        None => Ok(vec![parse(squares)?]),
+    }
+}
+
+fn is_promotion_row(point: Point, checker: Checker) -> bool {
+    match checker {
+        Checker::White => point.y == 0,
+        Checker::Black => point.y == BOARD_SIZE as i8 - 1,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
+            "B:WK6,25:B3,12",
+            "W:W18,K30:BK1,K2,14",
+            "B:W:B5",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+        assert_eq!(
+            Board::from_fen(&Board::new().to_fen()).unwrap(),
+            Board::new()
+        );
+    }
+
+    #[test]
+    fn fen_accepts_ranges_quotes_and_any_order() {
+        let board = Board::from_fen("\"B:B1-4,K12:W K30, 29.\"").unwrap();
+        assert_eq!(board.to_fen(), "B:W29,K30:B1,2,3,4,K12");
+        assert_eq!(Board::from_fen("W:W21-32:B1-12").unwrap(), Board::new());
+    }
+
+    #[test]
+    fn fen_errors_name_the_problem() {
+        for (fen, error) in [
+            ("", FenError::MissingTurn),
+            (":W21:B1", FenError::MissingTurn),
+            ("X:W21:B1", FenError::InvalidTurn("X".to_string())),
+            ("W:R21:B1", FenError::InvalidColor("R21".to_string())),
+            ("W:W33:B1", FenError::InvalidSquare("33".to_string())),
+            ("W:W0:B1", FenError::InvalidSquare("0".to_string())),
+            ("W:Wx:B1", FenError::InvalidSquare("x".to_string())),
+            ("W:W9-5:B1", FenError::InvalidSquare("9-5".to_string())),
+            ("W:W21:B21", FenError::DuplicateSquare(21)),
+            ("W:W20-24:BK22", FenError::DuplicateSquare(22)),
+            ("W:W1:B12", FenError::ManOnPromotionRow(1)),
+            ("W:W21:B32", FenError::ManOnPromotionRow(32)),
+        ] {
+            assert_eq!(Board::from_fen(fen), Err(error), "{fen}");
+        }
+        assert!(Board::from_fen("W:WK1:BK32").is_ok());
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{Board, Cell, Checker, Engine, Move, Notation, Player, SearchLimits, Turn};
+
+/// How long an engine may take to answer, beyond the search time it was given.
+const REPLY_TIME: Duration = Duration::from_secs(10);
+const SQUARES: u8 = 32;
+
+/// Client for an external engine speaking the Hub protocol over stdin/stdout.
+///
+/// ```text
+/// > hub                          < id name=Stub version=1
+///                                < wait
+/// > init                         < ready
+/// > pos pos=Wbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww
+/// > level depth=8 move-time=1.0
+/// > go think                     < done move=22-18
+/// > quit
+/// ```
+///
+/// Positions are the side to move followed by one character per square, as written by
+/// `format_position`. Moves use square numbers, captures listing the squares of the pieces
+/// taken after the landing square, e.g. `22x8x11x18`. Values containing spaces are quoted.
+pub struct HubEngine {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines of the engine's output, read by a thread so waiting for them can time out.
+    lines: Receiver<io::Result<String>>,
+    limits: SearchLimits,
+    pub name: String,
+}
+
+impl HubEngine {
+    /// Starts `command`, split on whitespace into a program and its arguments, and
+    /// completes the `hub`/`init` handshake.
+    pub fn spawn(command: &str, limits: SearchLimits) -> io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        let mut engine = Self {
+            stdin: child.stdin.take().unwrap(),
+            lines,
+            child,
+            limits,
+            name: program.to_string(),
+        };
+        engine.send("hub")?;
+        let deadline = Instant::now() + REPLY_TIME;
+        loop {
+            let (command, values) = engine.receive(deadline)?;
+            match command.as_str() {
+                "id" => {
+                    if let Some(name) = values.get("name") {
+                        engine.name = name.clone();
+                    }
+                }
+                "wait" => break,
+                _ => {}
+            }
+        }
+        engine.send("init")?;
+        engine.expect("ready", REPLY_TIME)?;
+        Ok(engine)
+    }
+
+    /// Asks the engine for a move and checks it is legal in the position.
+    pub fn best_move(&mut self, board: &Board) -> io::Result<Move> {
+        self.send(&format!("pos pos={}", HubEngine::format_position(board)))?;
+        self.send(&format!(
+            "level depth={} move-time={:.3}",
+            self.limits.max_depth,
+            self.limits.time.as_secs_f32()
+        ))?;
+        self.send("go think")?;
+        let values = self.expect("done", self.limits.time + REPLY_TIME)?;
+        let notation = values.get("move").map_or("", String::as_str);
+        HubEngine::parse_move(board, notation).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} played an illegal move: {notation:?}", self.name),
+            )
+        })
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{line}")?;
+        self.stdin.flush()
+    }
+
+    /// Reads lines until one starts with `keyword`, ignoring anything else (e.g. `info`), for
+    /// at most `timeout`.
+    fn expect(&mut self, keyword: &str, timeout: Duration) -> io::Result<HashMap<String, String>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let (command, values) = self.receive(deadline)?;
+            if command == keyword {
+                return Ok(values);
+            }
+            if command == "error" {
+                let message = values.get("message").cloned().unwrap_or_default();
+                return Err(io::Error::other(message));
+            }
+        }
+    }
+
+    fn receive(&mut self, deadline: Instant) -> io::Result<(String, HashMap<String, String>)> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(HubEngine::parse_line(&line?)),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} did not answer in time", self.name),
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} closed its output", self.name),
+            )),
+        }
+    }
+
+    /// Splits `command key=value key="quoted value" flag` into the command and its values.
+    pub fn parse_line(line: &str) -> (String, HashMap<String, String>) {
+        let line = line.trim();
+        let (command, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut values = HashMap::new();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let key_end = rest.find(['=', ' ']).unwrap_or(rest.len());
+            let key = rest[..key_end].to_string();
+            rest = &rest[key_end..];
+            let value = match rest.strip_prefix('=') {
+                Some(value) if value.starts_with('"') => {
+                    let end = value[1..].find('"').map_or(value.len(), |end| end + 1);
+                    rest = value.get(end + 1..).unwrap_or("");
+                    value[1..end].to_string()
+                }
+                Some(value) => {
+                    let end = value.find(' ').unwrap_or(value.len());
+                    rest = &value[end..];
+                    value[..end].to_string()
+                }
+                None => String::new(),
+            };
+            values.insert(key, value);
+        }
+        (command.to_string(), values)
+    }
+
+    /// Formats a legal move of `board` the way `best_move` expects engines to answer.
+    pub fn format_move(board: &Board, legal_move: &Move) -> String {
+        let square = |point| Notation::square(point).unwrap_or_default().to_string();
+        if !legal_move.is_capture {
+            return format!("{}-{}", square(legal_move.from), square(legal_move.to));
+        }
+        let mut captured: Vec<u8> = Engine::captured_pieces(board, legal_move)
+            .into_iter()
+            .filter_map(Notation::square)
+            .collect();
+        captured.sort_unstable();
+        std::iter::once(square(legal_move.from))
+            .chain(std::iter::once(square(legal_move.to)))
+            .chain(captured.iter().map(u8::to_string))
+            .collect::<Vec<String>>()
+            .join("x")
+    }
+
+    /// The legal move of `board` written as `format_move` does. A capture must take exactly
+    /// the pieces it lists, in any order.
+    pub fn parse_move(board: &Board, notation: &str) -> Option<Move> {
+        let mut squares = notation
+            .split(['-', 'x'])
+            .map(|square| square.parse::<u8>().ok())
+            .collect::<Option<Vec<u8>>>()?;
+        if squares.len() < 2 {
+            return None;
+        }
+        let from = Notation::from_square(squares[0])?;
+        let to = Notation::from_square(squares[1])?;
+        let legal_move = Engine::find_move(board, from, to)?;
+        let listed = &mut squares[2..];
+        if legal_move.is_capture != notation.contains('x') {
+            return None;
+        }
+        if !listed.is_empty() {
+            listed.sort_unstable();
+            let mut captured: Vec<u8> = Engine::captured_pieces(board, &legal_move)
+                .into_iter()
+                .filter_map(Notation::square)
+                .collect();
+            captured.sort_unstable();
+            if listed != captured.as_slice() {
+                return None;
+            }
+        }
+        Some(legal_move)
+    }
+
+    /// The side to move, `W` or `B`, then each square from 1 to 32 as `w` or `b` for men, `W`
+    /// or `B` for kings and `e` when empty.
+    pub fn format_position(board: &Board) -> String {
+        let turn = match board.turn {
+            Turn::White => 'W',
+            Turn::Black => 'B',
+        };
+        let squares = (1..=SQUARES)
+            .filter_map(Notation::from_square)
+            .map(|point| match board.get_cell(point) {
+                Cell::Checker(Checker::White) => 'w',
+                Cell::Checker(Checker::Black) => 'b',
+                Cell::Checker(Checker::WhiteQueen) => 'W',
+                Cell::Checker(Checker::BlackQueen) => 'B',
+                _ => 'e',
+            });
+        std::iter::once(turn).chain(squares).collect()
+    }
+
+    pub fn parse_position(position: &str) -> Option<Board> {
+        let mut chars = position.chars();
+        let turn = match chars.next()? {
+            'W' => Turn::White,
+            'B' => Turn::Black,
+            _ => return None,
+        };
+        let pieces: Vec<char> = chars.collect();
+        if pieces.len() != SQUARES as usize {
+            return None;
+        }
+        let mut board = Board::default();
+        for (square, piece) in (1..=SQUARES).zip(pieces) {
+            let checker = match piece {
+                'w' => Checker::White,
+                'b' => Checker::Black,
+                'W' => Checker::WhiteQueen,
+                'B' => Checker::BlackQueen,
+                'e' => continue,
+                _ => return None,
+            };
+            board.set_cell(Notation::from_square(square)?, Cell::Checker(checker));
+        }
+        board.turn = turn;
+        Some(board)
+    }
+}
+
+impl Player for HubEngine {
+    fn choose_move(&mut self, board: &Board) -> io::Result<Option<Move>> {
+        match Engine::get_moves(board).is_empty() {
+            true => Ok(None),
+            false => self.best_move(board).map(Some),
+        }
+    }
+}
+
+impl Drop for HubEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,161 @@
+use std::io;
+
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Board, Engine, Move, Outcome, Player};
+
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+/// Playouts still running after this many plies are scored as draws.
+const MAX_PLAYOUT_PLIES: usize = 150;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Playouts {
+    /// Uniformly random legal moves.
+    Random,
+    /// Captures first, then random moves, which keeps material exchanges realistic.
+    Heavy,
+}
+
+struct Node {
+    board: Board,
+    parent: Option<usize>,
+    played_move: Option<Move>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    /// Sum of results for the side that played `played_move`: 1 a win, 0.5 a draw.
+    reward: f64,
+}
+
+impl Node {
+    fn new(board: Board, parent: Option<usize>, played_move: Option<Move>) -> Self {
+        let untried = match Engine::get_outcome(&board) {
+            Some(_) => vec![],
+            None => Engine::get_moves(&board),
+        };
+        Self {
+            board,
+            parent,
+            played_move,
+            children: vec![],
+            untried,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+/// Monte Carlo tree search with UCT selection over `Engine`'s legal moves.
+#[derive(Debug, Clone, Copy)]
+pub struct Mcts {
+    pub simulations: usize,
+    pub playouts: Playouts,
+}
+
+impl Mcts {
+    pub fn new(simulations: usize, playouts: Playouts) -> Self {
+        Self {
+            simulations,
+            playouts,
+        }
+    }
+
+    /// The root move visited most often after `simulations` playouts.
+    pub fn choose_move(&self, board: &Board) -> Option<Move> {
+        let mut rng = rand::thread_rng();
+        let mut nodes = vec![Node::new(board.clear_moves(), None, None)];
+        for _ in 0..self.simulations {
+            let mut index = 0;
+            while nodes[index].untried.is_empty() && !nodes[index].children.is_empty() {
+                index = Mcts::select_child(&nodes, index);
+            }
+            if !nodes[index].untried.is_empty() {
+                let untried = &mut nodes[index].untried;
+                let legal_move = untried.swap_remove(rng.gen_range(0..untried.len()));
+                let child =
+                    Engine::make_move(nodes[index].board.clone(), legal_move.from, legal_move.to);
+                nodes.push(Node::new(child, Some(index), Some(legal_move)));
+                let child_index = nodes.len() - 1;
+                nodes[index].children.push(child_index);
+                index = child_index;
+            }
+            let outcome = self.playout(nodes[index].board.clone(), &mut rng);
+            let mut current = Some(index);
+            while let Some(node_index) = current {
+                let node = &mut nodes[node_index];
+                node.visits += 1;
+                node.reward += match outcome {
+                    Outcome::Draw => 0.5,
+                    _ if outcome == Outcome::win(node.board.turn.next()) => 1.0,
+                    _ => 0.0,
+                };
+                current = node.parent;
+            }
+        }
+        nodes[0]
+            .children
+            .iter()
+            .max_by_key(|child| nodes[**child].visits)
+            .and_then(|child| nodes[*child].played_move)
+    }
+
+    fn select_child(nodes: &[Node], index: usize) -> usize {
+        let parent_visits = (nodes[index].visits.max(1) as f64).ln();
+        let uct = |child: usize| {
+            let node = &nodes[child];
+            let visits = node.visits.max(1) as f64;
+            node.reward / visits + EXPLORATION * (parent_visits / visits).sqrt()
+        };
+        *nodes[index]
+            .children
+            .iter()
+            .max_by(|a, b| uct(**a).total_cmp(&uct(**b)))
+            .unwrap()
+    }
+
+    fn playout(&self, mut board: Board, rng: &mut impl Rng) -> Outcome {
+        for _ in 0..MAX_PLAYOUT_PLIES {
+            let moves = Engine::get_moves(&board);
+            let Some(mut legal_move) = moves.choose(rng).copied() else {
+                return Outcome::win(board.turn.next());
+            };
+            if self.playouts == Playouts::Heavy {
+                let captures = moves
+                    .iter()
+                    .filter(|legal_move| legal_move.is_capture)
+                    .collect::<Vec<&Move>>();
+                if let Some(capture) = captures.choose(rng) {
+                    legal_move = **capture;
+                }
+            }
+            board = Engine::make_move(board, legal_move.from, legal_move.to);
+        }
+        Outcome::Draw
+    }
+}
+
+impl Player for Mcts {
+    fn choose_move(&mut self, board: &Board) -> io::Result<Option<Move>> {
+        Ok(Mcts::choose_move(self, board))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Notation;
+
+    #[test]
+    fn mcts_finds_a_win_in_one() {
+        // Taking Black's last piece wins at once; every other move lets it play on.
+        let board = Board::from_fen("W:W22,29,30,31:B18").unwrap();
+        for playouts in [Playouts::Random, Playouts::Heavy] {
+            let legal_move = Mcts::new(400, playouts).choose_move(&board).unwrap();
+            let squares = (
+                Notation::square(legal_move.from),
+                Notation::square(legal_move.to),
+            );
+            assert_eq!(squares, (Some(22), Some(15)), "{playouts:?}");
+        }
+    }
+}
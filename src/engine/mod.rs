@@ -1,15 +1,49 @@
+mod ai;
 mod board;
+mod book;
 mod cell;
 mod checker;
 mod constants;
+mod dxp;
+mod eval;
+mod fen;
+mod hub;
+mod mcts;
+mod moves;
+mod network;
+mod notation;
+mod outcome;
+mod pdn;
+mod player;
 mod route;
+mod search;
+mod tablebase;
+mod transposition;
 mod turn;
+mod zobrist;
 
+pub use ai::Ai;
 pub use board::Board;
+pub use book::{BookMove, OpeningBook};
 pub use cell::Cell;
 pub use checker::Checker;
+pub use dxp::{DxpError, DxpMessage, DxpResult};
+pub use eval::{Feature, Weights, FEATURE_COUNT};
+pub use fen::FenError;
+pub use hub::HubEngine;
+pub use mcts::{Mcts, Playouts};
+pub use moves::Move;
+pub use network::{Network, NETWORK_HIDDEN, NETWORK_INPUTS};
+pub use notation::{Notation, NotationStyle};
+pub use outcome::Outcome;
+pub use pdn::{PdnError, PdnGame, PdnMove, Replay};
+pub use player::Player;
 pub use route::Route;
+pub use search::{Analysis, Search, SearchLimits, WIN_SCORE};
+pub use tablebase::{Tablebase, TablebaseValue};
+pub use transposition::{Bound, Entry, TranspositionTable};
 pub use turn::Turn;
+pub use zobrist::Zobrist;
 
 use crate::utility::Point;
 
@@ -33,6 +67,111 @@ impl Engine {
         board.clear_moves()
     }
 
+    pub fn get_moves(board: &Board) -> Vec<Move> {
+        board
+            .iter()
+            .filter(|(_, cell)| cell.belongs_to(board.turn))
+            .flat_map(|(from, _)| Engine::get_piece_moves(board, from))
+            .collect()
+    }
+
+    /// The board `make_move` leaves after each of `get_moves`, finding each piece's captures
+    /// once rather than again for every move.
+    pub fn successors(board: &Board) -> Vec<Board> {
+        let mut successors = vec![];
+        for (from, cell) in board.iter() {
+            if !cell.belongs_to(board.turn) {
+                continue;
+            }
+            let marked = Engine::with_legal_moves(board.clone(), from);
+            let routes = Engine::get_captures(&marked, from);
+            for (to, cell) in marked.iter() {
+                let mut successor = match cell {
+                    Cell::Move => {
+                        let mut successor = marked.clone();
+                        successor.set_cell(from, Cell::Empty);
+                        successor.set_cell(to, *marked.get_cell(from));
+                        successor.check_promotion(to);
+                        successor
+                    }
+                    Cell::Capture => {
+                        let route = routes.iter().find(|route| *route.last().unwrap() == to);
+                        Engine::route_capture(&marked, route.unwrap())
+                    }
+                    _ => continue,
+                };
+                successor.turn = successor.turn.next();
+                successors.push(successor.clear_moves());
+            }
+        }
+        successors
+    }
+
+    pub fn find_move(board: &Board, from: Point, to: Point) -> Option<Move> {
+        Engine::get_piece_moves(board, from)
+            .into_iter()
+            .find(|legal_move| legal_move.to == to)
+    }
+
+    /// Every capture the piece can make, each route starting at `from` and listing the square
+    /// it lands on after each piece taken.
+    pub fn capture_routes(board: &Board, from: Point) -> Vec<Route> {
+        if !Engine::is_valid(board, &from) || !board.get_cell(from).belongs_to(board.turn) {
+            return vec![];
+        }
+        Engine::get_captures(board, from)
+            .into_iter()
+            .filter(|route| route.points.len() > 1)
+            .collect()
+    }
+
+    /// The route `make_move` takes for a capture from `from` to `to`, when there are several.
+    pub fn capture_route(board: &Board, from: Point, to: Point) -> Option<Route> {
+        Engine::capture_routes(board, from)
+            .into_iter()
+            .find(|route| *route.last().unwrap() == to)
+    }
+
+    /// Plays a capture along one of the piece's `capture_routes`.
+    pub fn make_capture(board: Board, route: &Route) -> Board {
+        let mut board = Engine::route_capture(&board.clear_moves(), route);
+        board.turn = board.turn.next();
+        board
+    }
+
+    /// The squares of the pieces a legal move of `board` takes.
+    pub fn captured_pieces(board: &Board, legal_move: &Move) -> Vec<Point> {
+        let after = Engine::make_move(board.clone(), legal_move.from, legal_move.to);
+        board
+            .iter()
+            .filter(|(point, cell)| {
+                cell.is_checker() && *point != legal_move.from && after.get_cell(*point).is_empty()
+            })
+            .map(|(point, _)| point)
+            .collect()
+    }
+
+    pub fn get_outcome(board: &Board) -> Option<Outcome> {
+        match Engine::get_moves(board).is_empty() {
+            true => Some(Outcome::win(board.turn.next())),
+            false => None,
+        }
+    }
+
+    fn get_piece_moves(board: &Board, from: Point) -> Vec<Move> {
+        if !Engine::is_valid(board, &from) || !board.get_cell(from).belongs_to(board.turn) {
+            return vec![];
+        }
+        Engine::with_legal_moves(board.clone(), from)
+            .iter()
+            .filter_map(|(to, cell)| match cell {
+                Cell::Move => Some(Move::new(from, to, false)),
+                Cell::Capture => Some(Move::new(from, to, true)),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn with_legal_moves(board: Board, from: Point) -> Board {
         let mut board = board.clear_moves();
         let cell = *board.get_cell(from);
@@ -49,10 +188,11 @@ impl Engine {
             .collect::<Vec<Point>>()
             .into_iter()
             .for_each(|point| board.set_cell(point, Cell::Move));
+        // A capture circling back to its start would mark the piece's own square.
         Engine::get_captures(&board, from)
             .into_iter()
             .map(|route| route.get_after_last())
-            .filter(|route| !route.is_empty())
+            .filter(|route| route.last().is_some_and(|to| *to != from))
             .for_each(|route| board.set_cell(*route.last().unwrap(), Cell::Capture));
         board
     }
@@ -62,13 +202,9 @@ impl Engine {
         let mut routes = vec![Route {
             points: vec![start],
         }];
-        let mut captures = vec![start];
-        while let Some(capture_point) = captures.pop() {
-            let route = routes
-                .iter()
-                .find(|route| *route.last().unwrap() == capture_point)
-                .unwrap()
-                .clone();
+        let mut captures = routes.clone();
+        while let Some(route) = captures.pop() {
+            let capture_point = *route.last().unwrap();
             let simulated_board = Engine::route_capture(&board, &route);
             let enemy_neighbours = Engine::get_enemy_neighbours(&simulated_board, capture_point);
             let mut valid_captures: Vec<Point> = Vec::new();
@@ -90,8 +226,9 @@ impl Engine {
             }
 
             valid_captures.into_iter().for_each(|point_behind_enemy| {
-                captures.push(point_behind_enemy);
-                routes.push(route.add_point(point_behind_enemy));
+                let capture_route = route.add_point(point_behind_enemy);
+                captures.push(capture_route.clone());
+                routes.push(capture_route);
             });
         }
         routes
@@ -175,7 +312,7 @@ impl Engine {
         }
     }
 
-    fn is_valid(board: &Board, point: &Point) -> bool {
+    pub fn is_valid(board: &Board, point: &Point) -> bool {
         point.x >= 0 && point.x < board.size as i8 && point.y >= 0 && point.y < board.size as i8
     }
 }
@@ -0,0 +1,29 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Notation, NotationStyle};
+use crate::utility::Point;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct Move {
+    pub from: Point,
+    pub to: Point,
+    pub is_capture: bool,
+}
+
+impl Move {
+    pub fn new(from: Point, to: Point, is_capture: bool) -> Self {
+        Self {
+            from,
+            to,
+            is_capture,
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Notation::format_move(self, NotationStyle::Numeric))
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use rand::Rng;
+
+use super::{Board, Cell, Checker, Notation, Turn};
+
+const MAGIC: &[u8; 4] = b"CKNN";
+const VERSION: u8 = 1;
+/// One input per square and piece kind: white man, black man, white king, black king.
+pub const NETWORK_INPUTS: usize = 32 * 4;
+pub const NETWORK_HIDDEN: usize = 32;
+/// Parameters in file order: hidden weights (input-major), hidden biases, output weights and
+/// the output bias.
+pub const NETWORK_PARAMETERS: usize = NETWORK_INPUTS * NETWORK_HIDDEN + NETWORK_HIDDEN * 2 + 1;
+const HIDDEN_BIASES: usize = NETWORK_INPUTS * NETWORK_HIDDEN;
+const OUTPUT_WEIGHTS: usize = HIDDEN_BIASES + NETWORK_HIDDEN;
+const OUTPUT_BIAS: usize = OUTPUT_WEIGHTS + NETWORK_HIDDEN;
+/// The raw output is measured in men; scores are in hundredths of a man like `Weights`.
+const OUTPUT_SCALE: f32 = 100.0;
+
+/// A tiny NNUE-style evaluation: sparse piece-square inputs, one clipped-ReLU hidden layer
+/// and a linear output giving White's score.
+///
+/// On disk it is `CKNN`, a version byte, the input and hidden sizes as little-endian `u16`s
+/// and then every parameter as a little-endian `f32`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    pub parameters: Vec<f32>,
+}
+
+impl Network {
+    /// Small random weights to start training from.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let hidden_range = (1.0 / 12.0f32).sqrt();
+        let output_range = (1.0 / NETWORK_HIDDEN as f32).sqrt();
+        let mut parameters = vec![0.0; NETWORK_PARAMETERS];
+        for parameter in &mut parameters[..HIDDEN_BIASES] {
+            *parameter = rng.gen_range(-hidden_range..hidden_range);
+        }
+        for parameter in &mut parameters[OUTPUT_WEIGHTS..OUTPUT_BIAS] {
+            *parameter = rng.gen_range(-output_range..output_range);
+        }
+        Self { parameters }
+    }
+
+    /// Indices of the inputs set by the pieces on the board.
+    pub fn inputs(board: &Board) -> Vec<usize> {
+        board
+            .iter()
+            .filter_map(|(point, cell)| {
+                let Cell::Checker(checker) = cell else {
+                    return None;
+                };
+                let kind = match checker {
+                    Checker::White => 0,
+                    Checker::Black => 1,
+                    Checker::WhiteQueen => 2,
+                    Checker::BlackQueen => 3,
+                };
+                Some((Notation::square(point)? as usize - 1) * 4 + kind)
+            })
+            .collect()
+    }
+
+    /// Hidden layer sums before activation.
+    pub fn accumulate(&self, inputs: &[usize]) -> [f32; NETWORK_HIDDEN] {
+        let mut hidden = [0.0; NETWORK_HIDDEN];
+        hidden.copy_from_slice(&self.parameters[HIDDEN_BIASES..OUTPUT_WEIGHTS]);
+        for input in inputs {
+            let weights = &self.parameters[input * NETWORK_HIDDEN..(input + 1) * NETWORK_HIDDEN];
+            for (sum, weight) in hidden.iter_mut().zip(weights) {
+                *sum += weight;
+            }
+        }
+        hidden
+    }
+
+    /// White's score in hundredths of a man for the given active inputs.
+    pub fn forward(&self, inputs: &[usize]) -> f32 {
+        let hidden = self.accumulate(inputs);
+        let output = self.parameters[OUTPUT_WEIGHTS..OUTPUT_BIAS]
+            .iter()
+            .zip(hidden)
+            .map(|(weight, sum)| weight * Network::activate(sum))
+            .sum::<f32>();
+        (output + self.parameters[OUTPUT_BIAS]) * OUTPUT_SCALE
+    }
+
+    /// Adds the gradient of the score with respect to every parameter, times `scale`, to
+    /// `gradient`.
+    pub fn backward(&self, inputs: &[usize], scale: f32, gradient: &mut [f32]) {
+        let scale = scale * OUTPUT_SCALE;
+        let hidden = self.accumulate(inputs);
+        gradient[OUTPUT_BIAS] += scale;
+        for (neuron, sum) in hidden.into_iter().enumerate() {
+            gradient[OUTPUT_WEIGHTS + neuron] += scale * Network::activate(sum);
+            if sum <= 0.0 || sum >= 1.0 {
+                continue;
+            }
+            let slope = scale * self.parameters[OUTPUT_WEIGHTS + neuron];
+            gradient[HIDDEN_BIASES + neuron] += slope;
+            for input in inputs {
+                gradient[input * NETWORK_HIDDEN + neuron] += slope;
+            }
+        }
+    }
+
+    /// Score from the side to move's point of view.
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        let score = self.forward(&Network::inputs(board)).round() as i32;
+        match board.turn {
+            Turn::White => score,
+            Turn::Black => -score,
+        }
+    }
+
+    fn activate(sum: f32) -> f32 {
+        sum.clamp(0.0, 1.0)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(9 + NETWORK_PARAMETERS * 4);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(NETWORK_INPUTS as u16).to_le_bytes());
+        bytes.extend_from_slice(&(NETWORK_HIDDEN as u16).to_le_bytes());
+        for parameter in &self.parameters {
+            bytes.extend_from_slice(&parameter.to_le_bytes());
+        }
+        fs::File::create(path)?.write_all(&bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut bytes = vec![];
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < 9 || &bytes[..4] != MAGIC {
+            return Err(invalid("Not a network file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("Unsupported network version"));
+        }
+        let inputs = u16::from_le_bytes(bytes[5..7].try_into().unwrap()) as usize;
+        let hidden = u16::from_le_bytes(bytes[7..9].try_into().unwrap()) as usize;
+        if inputs != NETWORK_INPUTS || hidden != NETWORK_HIDDEN {
+            return Err(invalid("Unsupported network shape"));
+        }
+        if bytes.len() != 9 + NETWORK_PARAMETERS * 4 {
+            return Err(invalid("Truncated network file"));
+        }
+        let parameters = bytes[9..]
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Ok(Self { parameters })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn network() -> Network {
+        Network::random(&mut StdRng::seed_from_u64(7))
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("checkers-{name}-{}.nn", std::process::id()))
+    }
+
+    #[test]
+    fn saved_networks_load() {
+        let network = network();
+        let path = temp_path("network");
+        network.save(&path).unwrap();
+        let loaded = Network::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, network);
+    }
+
+    #[test]
+    fn malformed_files_are_invalid_data() {
+        let path = temp_path("malformed-network");
+        network().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let mut wider = bytes.clone();
+        wider[7] += 1;
+        let mut unknown = bytes.clone();
+        unknown[4] += 1;
+        let cases = [
+            (b"CKNM".to_vec(), "Not a network file"),
+            (unknown, "Unsupported network version"),
+            (wider, "Unsupported network shape"),
+            (bytes[..bytes.len() - 1].to_vec(), "Truncated network file"),
+            ([bytes.as_slice(), &[0]].concat(), "Truncated network file"),
+        ];
+        for (bytes, message) in cases {
+            fs::write(&path, bytes).unwrap();
+            let error = Network::load(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{message}");
+            assert_eq!(error.to_string(), message);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn backward_matches_finite_differences() {
+        let network = network();
+        let inputs = Network::inputs(&Board::new());
+        let hidden = network.accumulate(&inputs);
+        let mut gradient = vec![0.0; NETWORK_PARAMETERS];
+        network.backward(&inputs, 0.5, &mut gradient);
+        // The score is linear in each parameter between the clipping points, so a step that
+        // keeps every hidden sum on the same side of them is exact up to rounding.
+        let step = 0.01;
+        let mut active = 0;
+        for (index, computed) in gradient.iter().enumerate() {
+            let neuron = match index {
+                _ if index < HIDDEN_BIASES => Some(index % NETWORK_HIDDEN),
+                _ if index < OUTPUT_WEIGHTS => Some(index - HIDDEN_BIASES),
+                _ => None,
+            };
+            if let Some(neuron) = neuron {
+                let sum = hidden[neuron];
+                if sum.abs() < 2.0 * step || (sum - 1.0).abs() < 2.0 * step {
+                    continue;
+                }
+            }
+            let score = |offset: f32| {
+                let mut network = network.clone();
+                network.parameters[index] += offset;
+                network.forward(&inputs)
+            };
+            let estimate = 0.5 * (score(step) - score(-step)) / (2.0 * step);
+            assert!(
+                (estimate - computed).abs() < 0.01 + 0.001 * estimate.abs(),
+                "parameter {index}: {estimate} estimated, {computed} computed"
+            );
+            active += usize::from(index < HIDDEN_BIASES && *computed != 0.0);
+        }
+        assert!(active > 0);
+    }
+}
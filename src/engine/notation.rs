@@ -0,0 +1,203 @@
+use super::{constants::BOARD_SIZE, Move};
+use crate::utility::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotationStyle {
+    /// Standard 1–32 square numbers, e.g. `22-18`.
+    Numeric,
+    /// Russian a1–h8 coordinates, e.g. `c3-d4`.
+    Algebraic,
+}
+
+pub struct Notation {}
+
+impl Notation {
+    /// Maps a dark square to its 1–32 number, counting from the top-left of the board.
+    pub fn square(point: Point) -> Option<u8> {
+        if !Notation::is_dark(point) {
+            return None;
+        }
+        Some((point.y * BOARD_SIZE as i8 / 2 + point.x / 2 + 1) as u8)
+    }
+
+    pub fn from_square(square: u8) -> Option<Point> {
+        let per_row = (BOARD_SIZE / 2) as u8;
+        if square == 0 || square > per_row * BOARD_SIZE as u8 {
+            return None;
+        }
+        let y = (square - 1) / per_row;
+        let x = (square - 1) % per_row * 2 + (y + 1) % 2;
+        Some(Point::new(x as i8, y as i8))
+    }
+
+    /// Maps a point to a1–h8 coordinates with a1 in White's bottom-left corner.
+    pub fn algebraic(point: Point) -> Option<String> {
+        if !Notation::is_on_board(point) {
+            return None;
+        }
+        let file = (b'a' + point.x as u8) as char;
+        let rank = BOARD_SIZE as i8 - point.y;
+        Some(format!("{file}{rank}"))
+    }
+
+    pub fn from_algebraic(coordinates: &str) -> Option<Point> {
+        let mut chars = coordinates.chars();
+        let file = chars.next()?.to_ascii_lowercase();
+        if !file.is_ascii_lowercase() {
+            return None;
+        }
+        let rank = chars.as_str().parse::<i8>().ok()?;
+        let x = (file as i8).checked_sub(b'a' as i8)?;
+        let point = Point::new(x, BOARD_SIZE as i8 - rank);
+        Notation::is_on_board(point).then_some(point)
+    }
+
+    /// Parses either a square number or algebraic coordinates.
+    pub fn parse_point(text: &str) -> Option<Point> {
+        match text.parse::<u8>() {
+            Ok(square) => Notation::from_square(square),
+            Err(_) => Notation::from_algebraic(text),
+        }
+    }
+
+    pub fn format_point(point: Point, style: NotationStyle) -> String {
+        match style {
+            NotationStyle::Numeric => Notation::square(point).map(|square| square.to_string()),
+            NotationStyle::Algebraic => Notation::algebraic(point),
+        }
+        .unwrap_or_else(|| point.to_string())
+    }
+
+    pub fn format_move(legal_move: &Move, style: NotationStyle) -> String {
+        let separator = match (legal_move.is_capture, style) {
+            (false, _) => "-",
+            (true, NotationStyle::Numeric) => "x",
+            (true, NotationStyle::Algebraic) => ":",
+        };
+        format!(
+            "{}{separator}{}",
+            Notation::format_point(legal_move.from, style),
+            Notation::format_point(legal_move.to, style)
+        )
+    }
+
+    /// Splits move text like `22-18`, `22x15x8`, `c3-d4` or `c3:e5:g3` into its points.
+    pub fn parse_points(text: &str) -> Option<Vec<Point>> {
+        text.trim()
+            .split(['-', 'x', ':'])
+            .map(Notation::parse_point)
+            .collect::<Option<Vec<Point>>>()
+            .filter(|points| points.len() >= 2)
+    }
+
+    /// Parses move text into its starting and landing points.
+    pub fn parse_move(text: &str) -> Option<(Point, Point)> {
+        let points = Notation::parse_points(text)?;
+        Some((*points.first()?, *points.last()?))
+    }
+
+    /// Formats a game's moves as numbered lines, e.g. `1. c3-d4 f6-e5`.
+    pub fn format_history(
+        moves: &[Move],
+        is_black_first: bool,
+        style: NotationStyle,
+    ) -> Vec<String> {
+        let mut lines: Vec<String> = vec![];
+        for (ply, legal_move) in moves.iter().enumerate() {
+            let ply = ply + is_black_first as usize;
+            let text = Notation::format_move(legal_move, style);
+            match lines.last_mut() {
+                Some(line) if ply % 2 == 1 => *line = format!("{line} {text}"),
+                _ if ply % 2 == 1 => lines.push(format!("1... {text}")),
+                _ => lines.push(format!("{}. {text}", ply / 2 + 1)),
+            }
+        }
+        lines
+    }
+
+    fn is_on_board(point: Point) -> bool {
+        let size = BOARD_SIZE as i8;
+        point.x >= 0 && point.y >= 0 && point.x < size && point.y < size
+    }
+
+    fn is_dark(point: Point) -> bool {
+        Notation::is_on_board(point) && (point.x + point.y) % 2 != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(square: u8) -> Point {
+        Notation::from_square(square).unwrap()
+    }
+
+    #[test]
+    fn squares_round_trip() {
+        for square in 1..=32 {
+            assert_eq!(Notation::square(point(square)), Some(square));
+        }
+        assert_eq!(point(1), Point::new(1, 0));
+        assert_eq!(point(5), Point::new(0, 1));
+        assert_eq!(point(32), Point::new(6, 7));
+        assert_eq!(Notation::from_square(0), None);
+        assert_eq!(Notation::from_square(33), None);
+        // Light and off-board squares have no number.
+        assert_eq!(Notation::square(Point::new(0, 0)), None);
+        assert_eq!(Notation::square(Point::new(-1, 0)), None);
+        assert_eq!(Notation::square(Point::new(8, 1)), None);
+    }
+
+    #[test]
+    fn algebraic_names_round_trip() {
+        for y in 0..8 {
+            for x in 0..8 {
+                let point = Point::new(x, y);
+                let name = Notation::algebraic(point).unwrap();
+                assert_eq!(Notation::from_algebraic(&name), Some(point), "{name}");
+            }
+        }
+        assert_eq!(Notation::from_algebraic("a1"), Some(Point::new(0, 7)));
+        assert_eq!(Notation::from_algebraic("h8"), Some(Point::new(7, 0)));
+        assert_eq!(Notation::from_algebraic("C3"), Some(point(22)));
+        for name in ["", "a", "a0", "a9", "i1", "1a", "c3x"] {
+            assert_eq!(Notation::from_algebraic(name), None, "{name}");
+        }
+        assert_eq!(Notation::algebraic(Point::new(8, 0)), None);
+    }
+
+    #[test]
+    fn moves_parse_in_either_notation() {
+        let capture = Some(vec![point(22), point(15), point(8)]);
+        assert_eq!(Notation::parse_points("22x15x8"), capture);
+        assert_eq!(Notation::parse_points(" c3:e5:g7 "), capture);
+        assert_eq!(Notation::parse_move("22x15x8"), Some((point(22), point(8))));
+        assert_eq!(Notation::parse_move("c3-d4"), Some((point(22), point(18))));
+        for text in ["22", "c3", "", "22-", "22-33", "22-i9", "j1-c3"] {
+            assert_eq!(Notation::parse_points(text), None, "{text}");
+            assert_eq!(Notation::parse_move(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn histories_number_full_moves() {
+        let moves = [
+            Move::new(point(22), point(18), false),
+            Move::new(point(11), point(15), false),
+            Move::new(point(18), point(11), true),
+        ];
+        assert_eq!(
+            Notation::format_history(&moves, false, NotationStyle::Numeric),
+            ["1. 22-18 11-15", "2. 18x11"]
+        );
+        assert_eq!(
+            Notation::format_history(&moves, true, NotationStyle::Numeric),
+            ["1... 22-18", "2. 11-15 18x11"]
+        );
+        assert_eq!(
+            Notation::format_history(&moves, false, NotationStyle::Algebraic),
+            ["1. c3-d4 f6-e5", "2. d4:f6"]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::Turn;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl Outcome {
+    pub fn win(turn: Turn) -> Self {
+        match turn {
+            Turn::White => Outcome::WhiteWins,
+            Turn::Black => Outcome::BlackWins,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::WhiteWins => write!(f, "White wins"),
+            Outcome::BlackWins => write!(f, "Black wins"),
+            Outcome::Draw => write!(f, "Draw"),
+        }
+    }
+}
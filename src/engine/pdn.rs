@@ -0,0 +1,472 @@
+use std::{error::Error, fmt::Display};
+
+use super::{Board, Engine, FenError, Move, Notation, Outcome, Route};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PdnError {
+    UnterminatedTag,
+    UnterminatedComment,
+    InvalidTag(String),
+    InvalidMove(String),
+    IllegalMove {
+        ply: usize,
+        notation: String,
+    },
+    /// A capture along another path than `Engine::make_move` takes between the same squares,
+    /// which rooms, keeping only a move's ends, cannot replay.
+    AmbiguousCapture {
+        ply: usize,
+        notation: String,
+    },
+    Fen(FenError),
+}
+
+impl Display for PdnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdnError::UnterminatedTag => write!(f, "Tag is not terminated with ']'"),
+            PdnError::UnterminatedComment => write!(f, "Comment is not terminated with '}}'"),
+            PdnError::InvalidTag(tag) => write!(f, "Invalid tag: {tag:?}"),
+            PdnError::InvalidMove(notation) => write!(f, "Invalid move: {notation:?}"),
+            PdnError::IllegalMove { ply, notation } => {
+                write!(f, "Illegal move {notation:?} at ply {ply}")
+            }
+            PdnError::AmbiguousCapture { ply, notation } => {
+                write!(
+                    f,
+                    "Capture {notation:?} at ply {ply} takes an unsupported path"
+                )
+            }
+            PdnError::Fen(e) => write!(f, "Invalid FEN tag: {e}"),
+        }
+    }
+}
+
+impl Error for PdnError {}
+
+impl From<FenError> for PdnError {
+    fn from(e: FenError) -> Self {
+        PdnError::Fen(e)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PdnMove {
+    pub squares: Vec<u8>,
+    pub is_capture: bool,
+    pub comment: Option<String>,
+}
+
+impl PdnMove {
+    pub fn parse(notation: &str) -> Result<Self, PdnError> {
+        let is_capture = notation.contains(['x', ':']);
+        let squares = Notation::parse_points(notation)
+            .and_then(|points| points.into_iter().map(Notation::square).collect())
+            .ok_or_else(|| PdnError::InvalidMove(notation.to_string()))?;
+        Ok(Self {
+            squares,
+            is_capture,
+            comment: None,
+        })
+    }
+
+    /// The move as played on the board, listing every square a capture lands on.
+    pub fn from_board(board: &Board, played: &Move) -> Self {
+        let route = played
+            .is_capture
+            .then(|| Engine::capture_route(board, played.from, played.to))
+            .flatten();
+        match route {
+            Some(route) => Self {
+                squares: route
+                    .iter()
+                    .map(|point| Notation::square(*point).unwrap_or_default())
+                    .collect(),
+                is_capture: true,
+                comment: None,
+            },
+            None => Self::from(played),
+        }
+    }
+
+    /// Whether the route lands on every square of the move, in order.
+    fn follows(&self, route: &Route) -> bool {
+        let mut squares = route.iter().filter_map(|point| Notation::square(*point));
+        self.squares
+            .iter()
+            .all(|square| squares.any(|landed| landed == *square))
+    }
+}
+
+impl From<&Move> for PdnMove {
+    fn from(value: &Move) -> Self {
+        Self {
+            squares: vec![
+                Notation::square(value.from).unwrap_or_default(),
+                Notation::square(value.to).unwrap_or_default(),
+            ],
+            is_capture: value.is_capture,
+            comment: None,
+        }
+    }
+}
+
+impl Display for PdnMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let separator = if self.is_capture { "x" } else { "-" };
+        let squares = self
+            .squares
+            .iter()
+            .map(|square| square.to_string())
+            .collect::<Vec<String>>();
+        write!(f, "{}", squares.join(separator))
+    }
+}
+
+pub struct Replay {
+    pub start: Board,
+    pub moves: Vec<Move>,
+    pub board: Board,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PdnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PdnMove>,
+    pub result: Option<Outcome>,
+}
+
+impl PdnGame {
+    pub fn from_moves(
+        tags: Vec<(String, String)>,
+        start: &Board,
+        moves: &[Move],
+        result: Option<Outcome>,
+    ) -> Self {
+        let mut board = start.clone();
+        let pdn_moves = moves
+            .iter()
+            .map(|played| {
+                let pdn_move = PdnMove::from_board(&board, played);
+                board = Engine::make_move(board.clone(), played.from, played.to);
+                pdn_move
+            })
+            .collect();
+        let mut game = Self {
+            tags,
+            moves: pdn_moves,
+            result,
+        };
+        game.set_tag("GameType", "25");
+        if *start != Board::new() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &start.to_fen());
+        }
+        game.set_tag("Result", result_to_str(result));
+        game
+    }
+
+    /// Parses the first game of a PDN text.
+    pub fn parse(text: &str) -> Result<Self, PdnError> {
+        Ok(Self::parse_all(text)?
+            .into_iter()
+            .next()
+            .unwrap_or_default())
+    }
+
+    /// Parses every game of a PDN text, e.g. a whole archive file.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, PdnError> {
+        let mut games = vec![];
+        let mut game = PdnGame::default();
+        let mut chars = text.chars().peekable();
+        while let Some(&char) = chars.peek() {
+            match char {
+                _ if char.is_whitespace() => {
+                    chars.next();
+                }
+                '[' => {
+                    chars.next();
+                    if !game.moves.is_empty() {
+                        games.push(std::mem::take(&mut game));
+                    }
+                    let tag = take_until(&mut chars, ']').ok_or(PdnError::UnterminatedTag)?;
+                    let (name, value) = parse_tag(&tag)?;
+                    if name == "Result" {
+                        game.result = str_to_result(&value);
+                    }
+                    game.tags.push((name, value));
+                }
+                '{' => {
+                    chars.next();
+                    let comment =
+                        take_until(&mut chars, '}').ok_or(PdnError::UnterminatedComment)?;
+                    if let Some(last) = game.moves.last_mut() {
+                        last.comment = Some(comment.trim().to_string());
+                    }
+                }
+                '(' => skip_variation(&mut chars),
+                '%' | ';' => {
+                    take_until(&mut chars, '\n');
+                }
+                _ => {
+                    let mut token = String::new();
+                    while let Some(&char) = chars.peek() {
+                        if char.is_whitespace() || "[{(".contains(char) {
+                            break;
+                        }
+                        token.push(char);
+                        chars.next();
+                    }
+                    if let Some(result) = parse_result_token(&token) {
+                        game.result = result;
+                        games.push(std::mem::take(&mut game));
+                        continue;
+                    }
+                    let notation = token
+                        .trim_start_matches(|c: char| c.is_ascii_digit())
+                        .trim_start_matches('.');
+                    let notation = match notation.len() < token.len() && token.contains('.') {
+                        true => notation,
+                        false => &token,
+                    };
+                    let notation = notation.trim_end_matches(['!', '?', '*', '+']);
+                    if notation.is_empty() || notation.starts_with('$') {
+                        continue;
+                    }
+                    game.moves.push(PdnMove::parse(notation)?);
+                }
+            }
+        }
+        if !game.tags.is_empty() || !game.moves.is_empty() {
+            games.push(game);
+        }
+        Ok(games)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn start(&self) -> Result<Board, PdnError> {
+        match self.tag("FEN") {
+            Some(fen) => Ok(Board::from_fen(fen)?),
+            None => Ok(Board::new()),
+        }
+    }
+
+    /// Plays the move text through `Engine`, rejecting illegal moves.
+    pub fn replay(&self) -> Result<Replay, PdnError> {
+        let start = self.start()?;
+        let mut board = start.clone();
+        let mut moves = vec![];
+        for (ply, pdn_move) in self.moves.iter().enumerate() {
+            let illegal = || PdnError::IllegalMove {
+                ply: ply + 1,
+                notation: pdn_move.to_string(),
+            };
+            let from = Notation::from_square(pdn_move.squares[0]).ok_or_else(illegal)?;
+            let to =
+                Notation::from_square(*pdn_move.squares.last().unwrap()).ok_or_else(illegal)?;
+            let legal_move = Engine::find_move(&board, from, to).ok_or_else(illegal)?;
+            if !legal_move.is_capture {
+                if pdn_move.squares.len() > 2 {
+                    return Err(illegal());
+                }
+                board = Engine::make_move(board, from, to);
+                moves.push(legal_move);
+                continue;
+            }
+            let route = Engine::capture_routes(&board, from)
+                .into_iter()
+                .find(|route| *route.last().unwrap() == to && pdn_move.follows(route))
+                .ok_or_else(illegal)?;
+            let captured = Engine::make_capture(board.clone(), &route);
+            if captured != Engine::make_move(board, from, to) {
+                return Err(PdnError::AmbiguousCapture {
+                    ply: ply + 1,
+                    notation: pdn_move.to_string(),
+                });
+            }
+            board = captured;
+            moves.push(legal_move);
+        }
+        Ok(Replay {
+            start,
+            moves,
+            board,
+        })
+    }
+}
+
+impl Display for PdnGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{name} \"{}\"]", value.replace('"', "'"))?;
+        }
+        writeln!(f)?;
+        let is_black_first = self.start().is_ok_and(|board| board.turn.is_black());
+        let mut line = String::new();
+        for (ply, pdn_move) in self.moves.iter().enumerate() {
+            let ply = ply + is_black_first as usize;
+            let mut text = match (ply % 2, ply) {
+                (0, _) => format!("{}. {pdn_move}", ply / 2 + 1),
+                (_, 1) if is_black_first => format!("1... {pdn_move}"),
+                _ => pdn_move.to_string(),
+            };
+            if let Some(comment) = &pdn_move.comment {
+                text = format!("{text} {{{}}}", comment.replace('}', ")"));
+            }
+            if !line.is_empty() && line.len() + text.len() >= 80 {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&text);
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        writeln!(f, "{line}{}", result_to_str(self.result))
+    }
+}
+
+fn take_until(chars: &mut impl Iterator<Item = char>, end: char) -> Option<String> {
+    let mut text = String::new();
+    let mut in_quotes = false;
+    for char in chars {
+        match char {
+            '"' if end == ']' => in_quotes = !in_quotes,
+            _ if char == end && !in_quotes => return Some(text),
+            _ => {}
+        }
+        text.push(char);
+    }
+    None
+}
+
+fn skip_variation(chars: &mut impl Iterator<Item = char>) {
+    let mut depth = 0;
+    for char in chars {
+        match char {
+            '(' => depth += 1,
+            ')' if depth == 1 => return,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), PdnError> {
+    let invalid = || PdnError::InvalidTag(tag.to_string());
+    let (name, value) = tag
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn parse_result_token(token: &str) -> Option<Option<Outcome>> {
+    match token {
+        "*" => Some(None),
+        _ => str_to_result(token).map(Some),
+    }
+}
+
+fn str_to_result(result: &str) -> Option<Outcome> {
+    match result {
+        "1-0" | "2-0" => Some(Outcome::WhiteWins),
+        "0-1" | "0-2" => Some(Outcome::BlackWins),
+        "1/2-1/2" | "1-1" => Some(Outcome::Draw),
+        _ => None,
+    }
+}
+
+fn result_to_str(result: Option<Outcome>) -> &'static str {
+    match result {
+        Some(Outcome::WhiteWins) => "1-0",
+        Some(Outcome::BlackWins) => "0-1",
+        Some(Outcome::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(fen: &str, moves: &str) -> PdnGame {
+        PdnGame::parse(&format!("[FEN \"{fen}\"]\n{moves} *")).unwrap()
+    }
+
+    #[test]
+    fn moves_parse_every_square_of_a_capture() {
+        let capture = PdnMove::parse("22x15x8").unwrap();
+        assert_eq!(capture.squares, [22, 15, 8]);
+        assert!(capture.is_capture);
+        assert_eq!(capture.to_string(), "22x15x8");
+        let quiet = PdnMove::parse("9-13").unwrap();
+        assert_eq!((quiet.squares, quiet.is_capture), (vec![9, 13], false));
+        assert!(PdnMove::parse("9-x").is_err());
+    }
+
+    #[test]
+    fn replay_follows_capture_paths() {
+        for moves in ["1. 22x15x8", "1. 22x8"] {
+            let replay = game("W:W22:B18,11", moves).replay().unwrap();
+            assert_eq!(replay.board.to_fen(), "B:W8:B", "{moves}");
+            assert_eq!(replay.moves.len(), 1);
+        }
+        let error = game("W:W22:B18,11", "1. 22x13x8").replay().err().unwrap();
+        assert!(matches!(error, PdnError::IllegalMove { ply: 1, .. }));
+        let error = game("W:W22:B18,11", "1. 22-18-15").replay().err().unwrap();
+        assert!(matches!(error, PdnError::IllegalMove { ply: 1, .. }));
+    }
+
+    /// From 22 a white man reaches 15 by taking 18 alone, or 17, 9 and 10 around it.
+    #[test]
+    fn replay_refuses_captures_rooms_cannot_record() {
+        let fen = "W:W22:B9,10,17,18";
+        let replay = game(fen, "1. 22x15").replay().unwrap();
+        assert_eq!(replay.board.to_fen(), "B:W15:B9,10,17");
+        let error = game(fen, "1. 22x13x6x15").replay().err().unwrap();
+        assert!(matches!(error, PdnError::AmbiguousCapture { ply: 1, .. }));
+    }
+
+    #[test]
+    fn exports_replay_to_the_same_game() {
+        let start = Board::from_fen("W:W22,31:B18,11,5").unwrap();
+        let mut board = start.clone();
+        let mut moves = vec![];
+        for (from, to) in [(22, 8), (5, 9), (8, 3)] {
+            let from = Notation::from_square(from).unwrap();
+            let to = Notation::from_square(to).unwrap();
+            moves.push(Engine::find_move(&board, from, to).unwrap());
+            board = Engine::make_move(board, from, to);
+        }
+        let exported = PdnGame::from_moves(vec![], &start, &moves, None);
+        let text = exported.to_string();
+        assert!(text.contains("1. 22x15x8 5-9 2. 8-3 *"), "{text}");
+        let parsed = PdnGame::parse(&text).unwrap();
+        assert_eq!(parsed, exported);
+        let replay = parsed.replay().unwrap();
+        assert_eq!((replay.start, replay.moves), (start, moves));
+        assert_eq!(replay.board, board);
+    }
+}
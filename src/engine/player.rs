@@ -0,0 +1,15 @@
+use std::io;
+
+use super::{Ai, Board, Move};
+
+/// Anything that can pick a move for the side to move, e.g. a room's non-human seat.
+pub trait Player {
+    /// `Ok(None)` means the player has no move to offer, e.g. in a finished game.
+    fn choose_move(&mut self, board: &Board) -> io::Result<Option<Move>>;
+}
+
+impl Player for Ai {
+    fn choose_move(&mut self, board: &Board) -> io::Result<Option<Move>> {
+        Ok(Ai::choose_move(self, board))
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{
+    Board, Bound, Engine, Entry, Move, Network, Tablebase, TablebaseValue, TranspositionTable,
+    Turn, Weights,
+};
+
+pub const WIN_SCORE: i32 = 100_000;
+/// Scores beyond this bound are forced wins or losses.
+const DECISIVE_SCORE: i32 = WIN_SCORE - 10_000;
+const MAX_PLY: usize = 128;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    pub max_depth: u8,
+    pub time: Duration,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            time: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// Centipawn-like score from White's point of view.
+    pub score: i32,
+    pub depth: u8,
+    pub principal_variation: Vec<Move>,
+    pub nodes: u64,
+    pub tablebase: Option<TablebaseValue>,
+}
+
+impl Analysis {
+    pub fn best_move(&self) -> Option<Move> {
+        self.principal_variation.first().copied()
+    }
+
+    /// Plies to a forced win (positive) or loss (negative) for White, if the score is decisive.
+    pub fn mate_in(&self) -> Option<i32> {
+        match self.score.abs() > DECISIVE_SCORE {
+            true => Some(self.score.signum() * (WIN_SCORE - self.score.abs())),
+            false => None,
+        }
+    }
+}
+
+/// Iterative deepening negamax alpha-beta search over `Engine`'s legal moves.
+///
+/// With more than one thread it runs Lazy SMP: helper searches on the current rayon pool
+/// share the transposition table with the main one and stop as soon as it finishes.
+pub struct Search {
+    table: Arc<TranspositionTable>,
+    threads: usize,
+    nodes: u64,
+    deadline: Instant,
+    is_stopped: bool,
+    stop: Arc<AtomicBool>,
+    path: Vec<u64>,
+    tablebase: Option<Arc<Tablebase>>,
+    weights: Weights,
+    network: Option<Arc<Network>>,
+}
+
+impl Search {
+    pub fn new(table_bits: u32) -> Self {
+        Self {
+            table: Arc::new(TranspositionTable::new(table_bits)),
+            threads: 1,
+            nodes: 0,
+            deadline: Instant::now(),
+            is_stopped: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            path: vec![],
+            tablebase: None,
+            weights: Weights::default(),
+            network: None,
+        }
+    }
+
+    pub fn with_weights(mut self, weights: Weights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Evaluates with the network instead of the handcrafted weights when one is given.
+    pub fn with_network(mut self, network: Option<Arc<Network>>) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn with_tablebase(mut self, tablebase: Option<Arc<Tablebase>>) -> Self {
+        self.tablebase = tablebase;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn run(&mut self, board: &Board, limits: SearchLimits) -> Analysis {
+        let board = board.clear_moves();
+        self.deadline = Instant::now() + limits.time;
+        self.stop = Arc::new(AtomicBool::new(false));
+        if self.threads == 1 {
+            return self.iterate(&board, limits, 1);
+        }
+        let mut helpers = (1..self.threads)
+            .map(|_| self.helper())
+            .collect::<Vec<Search>>();
+        let mut analysis = Analysis::default();
+        rayon::scope(|scope| {
+            for (index, helper) in helpers.iter_mut().enumerate() {
+                let board = &board;
+                // Odd helpers start one ply deeper, so the threads spread over more depths.
+                let start_depth = 1 + (index % 2) as u8;
+                scope.spawn(move |_| {
+                    helper.iterate(board, limits, start_depth);
+                });
+            }
+            analysis = self.iterate(&board, limits, 1);
+            self.stop.store(true, Ordering::Relaxed);
+        });
+        analysis.nodes += helpers.iter().map(|helper| helper.nodes).sum::<u64>();
+        analysis
+    }
+
+    /// A single-threaded search sharing this one's table, deadline and stop flag.
+    fn helper(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            threads: 1,
+            nodes: 0,
+            deadline: self.deadline,
+            is_stopped: false,
+            stop: self.stop.clone(),
+            path: vec![],
+            tablebase: self.tablebase.clone(),
+            weights: self.weights,
+            network: self.network.clone(),
+        }
+    }
+
+    fn iterate(&mut self, board: &Board, limits: SearchLimits, start_depth: u8) -> Analysis {
+        self.nodes = 0;
+        self.is_stopped = false;
+        let mut analysis = Analysis::default();
+        for depth in start_depth.min(limits.max_depth)..=limits.max_depth {
+            let score = self.alpha_beta(board, depth, 0, -WIN_SCORE, WIN_SCORE);
+            if self.is_stopped && depth > start_depth {
+                break;
+            }
+            analysis = Analysis {
+                score: match board.turn {
+                    Turn::White => score,
+                    Turn::Black => -score,
+                },
+                depth,
+                principal_variation: self.principal_variation(board, depth),
+                nodes: self.nodes,
+                tablebase: self.probe(board),
+            };
+            if self.is_stopped || analysis.mate_in().is_some() {
+                break;
+            }
+        }
+        analysis.nodes = self.nodes;
+        analysis
+    }
+
+    fn alpha_beta(&mut self, board: &Board, depth: u8, ply: usize, alpha: i32, beta: i32) -> i32 {
+        self.visit();
+        if self.is_stopped {
+            return 0;
+        }
+        let hash = board.hash();
+        if ply > 0 && self.path.contains(&hash) {
+            return 0;
+        }
+        if ply > 0 {
+            match self.probe(board) {
+                Some(TablebaseValue::Win(distance)) => {
+                    return WIN_SCORE - ply as i32 - distance as i32
+                }
+                Some(TablebaseValue::Loss(distance)) => {
+                    return -WIN_SCORE + ply as i32 + distance as i32
+                }
+                Some(TablebaseValue::Draw) => return 0,
+                None => {}
+            }
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+        let entry = self.table.probe(hash);
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth && ply > 0) {
+            let score = Search::score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+        let moves = Search::order_moves(Engine::get_moves(board), entry.and_then(|e| e.best_move));
+        if moves.is_empty() {
+            return -WIN_SCORE + ply as i32;
+        }
+        let original_alpha = alpha;
+        let mut alpha = alpha;
+        let mut best_score = -WIN_SCORE;
+        let mut best_move = None;
+        self.path.push(hash);
+        for legal_move in moves {
+            let child = Engine::make_move(board.clone(), legal_move.from, legal_move.to);
+            let score = -self.alpha_beta(&child, depth - 1, ply + 1, -beta, -alpha);
+            if score > best_score {
+                best_score = score;
+                best_move = Some(legal_move);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta || self.is_stopped {
+                break;
+            }
+        }
+        self.path.pop();
+        if !self.is_stopped {
+            let bound = match best_score {
+                _ if best_score >= beta => Bound::Lower,
+                _ if best_score > original_alpha => Bound::Exact,
+                _ => Bound::Upper,
+            };
+            self.table.store(Entry {
+                key: hash,
+                depth,
+                score: Search::score_to_table(best_score, ply),
+                bound,
+                best_move,
+            });
+        }
+        best_score
+    }
+
+    /// Resolves pending captures so the static evaluation is not taken mid-exchange. Captures
+    /// are not forced, so the side to move may stand pat on the evaluation instead of taking,
+    /// and is lost only when it has no moves at all.
+    fn quiescence(&mut self, board: &Board, ply: usize, alpha: i32, beta: i32) -> i32 {
+        let moves = Engine::get_moves(board);
+        if moves.is_empty() {
+            return -WIN_SCORE + ply as i32;
+        }
+        let stand_pat = match &self.network {
+            Some(network) => network.evaluate(board),
+            None => self.weights.evaluate(board),
+        };
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        let mut alpha = alpha.max(stand_pat);
+        let mut best_score = stand_pat;
+        for capture in moves.into_iter().filter(|legal_move| legal_move.is_capture) {
+            self.visit();
+            if self.is_stopped {
+                return best_score.max(alpha);
+            }
+            let child = Engine::make_move(board.clone(), capture.from, capture.to);
+            let score = -self.quiescence(&child, ply + 1, -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+        }
+        best_score
+    }
+
+    fn probe(&self, board: &Board) -> Option<TablebaseValue> {
+        self.tablebase.as_ref()?.probe(board)
+    }
+
+    fn visit(&mut self) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024)
+            && (Instant::now() >= self.deadline || self.stop.load(Ordering::Relaxed))
+        {
+            self.is_stopped = true;
+        }
+    }
+
+    fn principal_variation(&self, board: &Board, depth: u8) -> Vec<Move> {
+        let mut board = board.clone();
+        let mut hashes = vec![];
+        let mut variation = vec![];
+        while let Some(best_move) = self.table.probe(board.hash()).and_then(|e| e.best_move) {
+            if variation.len() >= depth as usize || hashes.contains(&board.hash()) {
+                break;
+            }
+            if Engine::find_move(&board, best_move.from, best_move.to).is_none() {
+                break;
+            }
+            hashes.push(board.hash());
+            board = Engine::make_move(board, best_move.from, best_move.to);
+            variation.push(best_move);
+        }
+        variation
+    }
+
+    /// Stores decisive scores relative to the node, so they stay valid at any ply.
+    fn score_to_table(score: i32, ply: usize) -> i32 {
+        match score {
+            _ if score > DECISIVE_SCORE => score + ply as i32,
+            _ if score < -DECISIVE_SCORE => score - ply as i32,
+            _ => score,
+        }
+    }
+
+    fn score_from_table(score: i32, ply: usize) -> i32 {
+        match score {
+            _ if score > DECISIVE_SCORE => score - ply as i32,
+            _ if score < -DECISIVE_SCORE => score + ply as i32,
+            _ => score,
+        }
+    }
+
+    fn order_moves(mut moves: Vec<Move>, best_move: Option<Move>) -> Vec<Move> {
+        moves.sort_by_key(|legal_move| match legal_move {
+            _ if Some(*legal_move) == best_move => 0,
+            _ if legal_move.is_capture => 1,
+            _ => 2,
+        });
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Notation;
+
+    fn analyse(fen: &str, max_depth: u8) -> Analysis {
+        let limits = SearchLimits {
+            max_depth,
+            time: Duration::from_secs(10),
+        };
+        Search::new(16).run(&Board::from_fen(fen).unwrap(), limits)
+    }
+
+    fn squares(legal_move: Move) -> (u8, u8) {
+        let square = |point| Notation::square(point).unwrap();
+        (square(legal_move.from), square(legal_move.to))
+    }
+
+    #[test]
+    fn search_finds_a_win_in_one() {
+        // Taking Black's last piece leaves it without moves.
+        let analysis = analyse("W:W22,31:B18", 4);
+        assert_eq!(analysis.best_move().map(squares), Some((22, 15)));
+        assert_eq!(analysis.mate_in(), Some(1));
+        assert!(analysis.score > DECISIVE_SCORE);
+    }
+
+    #[test]
+    fn search_scores_a_side_without_moves_as_lost() {
+        // White's only man is blocked by Black's.
+        let analysis = analyse("W:W29:B25,22", 2);
+        assert_eq!(analysis.best_move(), None);
+        assert!(analysis.score < -DECISIVE_SCORE);
+    }
+
+    #[test]
+    fn search_takes_a_free_piece() {
+        let analysis = analyse("W:W22,27,31:B18,2,3", 4);
+        assert_eq!(analysis.best_move().map(squares), Some((22, 15)));
+        assert!(analysis.score > 0);
+    }
+
+    #[test]
+    fn quiescence_resolves_captures_before_evaluating() {
+        let mut search = Search::new(16);
+        search.deadline = Instant::now() + Duration::from_secs(10);
+        let mut quiescence =
+            |fen| search.quiescence(&Board::from_fen(fen).unwrap(), 0, -WIN_SCORE, WIN_SCORE);
+        // Level material, but White takes a man for free.
+        let board = Board::from_fen("W:W22,31:B18,1").unwrap();
+        assert!(quiescence("W:W22,31:B18,1") > Weights::default().evaluate(&board) + 50);
+        // Capturing Black's last piece wins outright.
+        assert!(quiescence("W:W22:B18") > DECISIVE_SCORE);
+        assert!(quiescence("W:W29:B25,22") < -DECISIVE_SCORE);
+        // Taking on 18 loses White's last man to 10x19, so White keeps its man instead.
+        let board = Board::from_fen("W:W22:B18,10,6").unwrap();
+        assert_eq!(
+            quiescence("W:W22:B18,10,6"),
+            Weights::default().evaluate(&board)
+        );
+    }
+}
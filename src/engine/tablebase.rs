@@ -0,0 +1,685 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use rayon::prelude::*;
+
+use super::{Board, Cell, Checker, Engine, Notation, Turn};
+
+const MAGIC: &[u8; 4] = b"CKTB";
+const VERSION: u8 = 2;
+const WIN: u16 = 0b01 << 14;
+const LOSS: u16 = 0b10 << 14;
+const DISTANCE_MASK: u16 = (1 << 14) - 1;
+const SQUARES: usize = 32;
+/// Squares a man may stand on: all but its promotion row.
+const MAN_SQUARES: usize = 28;
+/// `BINOMIALS[n][k]` is n choose k.
+const BINOMIALS: [[u64; SQUARES + 1]; SQUARES + 1] = binomials();
+
+const fn binomials() -> [[u64; SQUARES + 1]; SQUARES + 1] {
+    let mut table = [[0; SQUARES + 1]; SQUARES + 1];
+    let mut n = 0;
+    while n <= SQUARES {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+/// Game-theoretic value for the side to move, with the distance in plies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TablebaseValue {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+
+impl TablebaseValue {
+    fn encode(&self) -> u16 {
+        match self {
+            TablebaseValue::Win(distance) => WIN | distance,
+            TablebaseValue::Loss(distance) => LOSS | distance,
+            TablebaseValue::Draw => 0,
+        }
+    }
+
+    fn decode(value: u16) -> Self {
+        match value & !DISTANCE_MASK {
+            WIN => TablebaseValue::Win(value & DISTANCE_MASK),
+            LOSS => TablebaseValue::Loss(value & DISTANCE_MASK),
+            _ => TablebaseValue::Draw,
+        }
+    }
+}
+
+/// The pieces of each kind on the board. Every material with both sides on the board is one
+/// slice of the tablebase, whose positions are numbered by `index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Material {
+    white_men: u8,
+    black_men: u8,
+    white_kings: u8,
+    black_kings: u8,
+}
+
+impl Material {
+    fn of(board: &Board) -> Self {
+        let mut material = Material {
+            white_men: 0,
+            black_men: 0,
+            white_kings: 0,
+            black_kings: 0,
+        };
+        for (_, cell) in board.iter() {
+            match cell {
+                Cell::Checker(Checker::White) => material.white_men += 1,
+                Cell::Checker(Checker::Black) => material.black_men += 1,
+                Cell::Checker(Checker::WhiteQueen) => material.white_kings += 1,
+                Cell::Checker(Checker::BlackQueen) => material.black_kings += 1,
+                _ => {}
+            }
+        }
+        material
+    }
+
+    /// Every material of 2..=`max_pieces` pieces with both sides on the board, in an order
+    /// where captures and promotions only lead to earlier ones.
+    fn all(max_pieces: u8) -> Vec<Self> {
+        let mut materials = vec![];
+        for pieces in 2..=max_pieces {
+            for men in 0..=pieces {
+                for white_men in 0..=men {
+                    for white_kings in 0..=pieces - men {
+                        let material = Material {
+                            white_men,
+                            black_men: men - white_men,
+                            white_kings,
+                            black_kings: pieces - men - white_kings,
+                        };
+                        if material.has_side(Turn::White) && material.has_side(Turn::Black) {
+                            materials.push(material);
+                        }
+                    }
+                }
+            }
+        }
+        materials
+    }
+
+    fn pieces(&self) -> u8 {
+        self.white_men + self.black_men + self.white_kings + self.black_kings
+    }
+
+    fn has_side(&self, turn: Turn) -> bool {
+        match turn {
+            Turn::White => self.white_men + self.white_kings > 0,
+            Turn::Black => self.black_men + self.black_kings > 0,
+        }
+    }
+
+    /// Placements of each group of pieces: men among their 28 squares, then kings among the
+    /// squares the men leave.
+    fn placements(&self) -> [u64; 4] {
+        let men = (self.white_men + self.black_men) as usize;
+        let white_kings = self.white_kings as usize;
+        [
+            BINOMIALS[MAN_SQUARES][self.white_men as usize],
+            BINOMIALS[MAN_SQUARES][self.black_men as usize],
+            BINOMIALS[SQUARES - men][white_kings],
+            BINOMIALS[SQUARES - men - white_kings][self.black_kings as usize],
+        ]
+    }
+
+    /// Positions in the slice, including those numbers where both sides' men would share a
+    /// square.
+    fn len(&self) -> usize {
+        self.placements().iter().product::<u64>() as usize * 2
+    }
+
+    /// The position's number within its slice; both sides' placements are ranked as
+    /// combinations, and the side to move is the lowest digit. Men on their promotion row,
+    /// which only set-up positions have, are in no slice.
+    fn index(&self, board: &Board) -> Option<usize> {
+        let mut groups: [Vec<usize>; 4] = Default::default();
+        let mut free = 0;
+        for square in 1..=SQUARES as u8 {
+            let point = Notation::from_square(square).unwrap();
+            let square = square as usize - 1;
+            match board.get_cell(point) {
+                Cell::Checker(Checker::White) => {
+                    groups[0].push(square.checked_sub(SQUARES - MAN_SQUARES)?)
+                }
+                Cell::Checker(Checker::Black) if square >= MAN_SQUARES => return None,
+                Cell::Checker(Checker::Black) => groups[1].push(square),
+                Cell::Checker(Checker::WhiteQueen) => groups[2].push(free),
+                // Black kings are ranked among the squares the white kings leave.
+                Cell::Checker(Checker::BlackQueen) => groups[3].push(free - groups[2].len()),
+                _ => {}
+            }
+            if !board.get_cell(point).is_checker() || board.get_cell(point).is_queen() {
+                free += 1;
+            }
+        }
+        let mut index = 0;
+        for (group, placements) in groups.iter().zip(self.placements()) {
+            index = index * placements + rank(group);
+        }
+        Some(index as usize * 2 + board.turn.is_black() as usize)
+    }
+
+    /// The position numbered `index`, unless it puts two men on one square.
+    fn board(&self, index: usize) -> Option<Board> {
+        let mut board = Board::default();
+        board.turn = match index % 2 {
+            0 => Turn::White,
+            _ => Turn::Black,
+        };
+        let placements = self.placements();
+        let mut ranks = [0; 4];
+        let mut rest = index as u64 / 2;
+        for (group, placements) in placements.iter().enumerate().rev() {
+            ranks[group] = rest % placements;
+            rest /= placements;
+        }
+        let counts = [
+            self.white_men,
+            self.black_men,
+            self.white_kings,
+            self.black_kings,
+        ];
+        let mut occupied = [false; SQUARES];
+        let men = [(Checker::White, SQUARES - MAN_SQUARES), (Checker::Black, 0)];
+        for (group, (checker, first_square)) in men.into_iter().enumerate() {
+            for offset in unrank(ranks[group], counts[group] as usize) {
+                let square = first_square + offset;
+                if occupied[square] {
+                    return None;
+                }
+                occupied[square] = true;
+                let point = Notation::from_square(square as u8 + 1)?;
+                board.set_cell(point, Cell::Checker(checker));
+            }
+        }
+        for (group, checker) in [(2, Checker::WhiteQueen), (3, Checker::BlackQueen)] {
+            let free = (0..SQUARES)
+                .filter(|square| !occupied[*square])
+                .collect::<Vec<_>>();
+            for offset in unrank(ranks[group], counts[group] as usize) {
+                let square = free[offset];
+                occupied[square] = true;
+                let point = Notation::from_square(square as u8 + 1)?;
+                board.set_cell(point, Cell::Checker(checker));
+            }
+        }
+        Some(board)
+    }
+}
+
+/// The combinatorial number of a set of distinct values in increasing order.
+fn rank(values: &[usize]) -> u64 {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| BINOMIALS[*value][i + 1])
+        .sum()
+}
+
+/// The `count` values in increasing order whose `rank` is `rank`.
+fn unrank(mut rank: u64, count: usize) -> Vec<usize> {
+    let mut values = vec![0; count];
+    for i in (1..=count).rev() {
+        let mut value = i - 1;
+        while BINOMIALS[value + 1][i] <= rank {
+            value += 1;
+        }
+        rank -= BINOMIALS[value][i];
+        values[i - 1] = value;
+    }
+    values
+}
+
+/// What is known of the positions a position's moves lead to.
+#[derive(Clone, Copy)]
+struct Children {
+    /// The fastest loss among them, which the position wins by.
+    fastest_loss: Option<u16>,
+    /// The slowest win among them, while every one is a win.
+    slowest_win: Option<u16>,
+    has_moves: bool,
+}
+
+impl Children {
+    const NONE: Children = Children {
+        fastest_loss: None,
+        slowest_win: Some(0),
+        has_moves: false,
+    };
+
+    /// Adds a child, `None` while its value is unknown.
+    fn add(&mut self, value: Option<TablebaseValue>) {
+        self.has_moves = true;
+        match value {
+            Some(TablebaseValue::Loss(distance)) => {
+                self.fastest_loss = Some(self.fastest_loss.map_or(distance, |d| d.min(distance)));
+            }
+            Some(TablebaseValue::Win(distance)) => {
+                self.slowest_win = self.slowest_win.map(|d| d.max(distance));
+            }
+            _ => self.slowest_win = None,
+        }
+    }
+
+    /// The position's value in round `round`, if it is known by then. A win waits for the
+    /// round of its distance, as a faster one could still appear until then.
+    fn resolve(&self, round: u16) -> Option<TablebaseValue> {
+        match (self.fastest_loss, self.slowest_win) {
+            (Some(distance), _) if distance < round => Some(TablebaseValue::Win(distance + 1)),
+            (Some(_), _) => None,
+            (None, Some(_)) if !self.has_moves => Some(TablebaseValue::Loss(0)),
+            (None, Some(distance)) => Some(TablebaseValue::Loss(distance + 1)),
+            (None, None) => None,
+        }
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.fastest_loss.is_some()
+    }
+}
+
+/// Win/loss/draw tables for every position with at most `max_pieces` pieces.
+///
+/// On disk it is a `CKTB` header followed by each slice: its counts of white men, black men,
+/// white kings and black kings, its length, and one value per position numbered by
+/// `Material::index`. Draws, and numbers that are no position, are 0.
+pub struct Tablebase {
+    max_pieces: u8,
+    slices: HashMap<Material, Vec<u16>>,
+}
+
+impl Tablebase {
+    pub fn max_pieces(&self) -> u8 {
+        self.max_pieces
+    }
+
+    /// Positions covered, over every slice.
+    pub fn len(&self) -> usize {
+        self.slices.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slices.is_empty()
+    }
+
+    pub fn probe(&self, board: &Board) -> Option<TablebaseValue> {
+        let material = Material::of(board);
+        if material.pieces() > self.max_pieces {
+            return None;
+        }
+        let values = self.slices.get(&material)?;
+        Some(TablebaseValue::decode(values[material.index(board)?]))
+    }
+
+    pub fn count_pieces(board: &Board) -> usize {
+        board.iter().filter(|(_, cell)| cell.is_checker()).count()
+    }
+
+    /// Retrograde analysis of one material slice after another, each resolving its captures
+    /// and promotions from the slices before it.
+    pub fn generate(max_pieces: u8, progress: impl FnMut(&str)) -> io::Result<Self> {
+        Self::generate_materials(max_pieces, Material::all(max_pieces), progress)
+    }
+
+    fn generate_materials(
+        max_pieces: u8,
+        materials: impl IntoIterator<Item = Material>,
+        mut progress: impl FnMut(&str),
+    ) -> io::Result<Self> {
+        let mut tablebase = Self {
+            max_pieces,
+            slices: HashMap::new(),
+        };
+        for material in materials {
+            let values = tablebase.generate_slice(material)?;
+            let decisive = values.iter().filter(|value| **value != 0).count();
+            progress(&format!(
+                "{material:?}: {} positions, {decisive} decisive",
+                values.len()
+            ));
+            tablebase.slices.insert(material, values);
+        }
+        Ok(tablebase)
+    }
+
+    /// Positions without moves are lost. Then each round `r` resolves the positions that win
+    /// in `r` plies or whose moves all lead to known wins, until a round changes nothing and
+    /// no win is left waiting for its round; the rest are draws. Values are final once set,
+    /// so each round can use those set earlier in it.
+    fn generate_slice(&self, material: Material) -> io::Result<Vec<u16>> {
+        let positions = (0..material.len())
+            .into_par_iter()
+            .filter_map(|index| {
+                let board = material.board(index)?;
+                Some(
+                    self.children(material, &board)
+                        .map(|children| (index, children)),
+                )
+            })
+            .collect::<io::Result<Vec<(usize, (Children, Vec<u32>))>>>()?;
+        // Moves staying in the slice, flattened: position `p`'s are `moves[ends[p - 1]..ends[p]]`.
+        let mut indices = Vec::with_capacity(positions.len());
+        let mut outside = Vec::with_capacity(positions.len());
+        let mut ends = Vec::with_capacity(positions.len());
+        let mut moves = vec![];
+        for (index, (children, in_slice)) in positions {
+            indices.push(index);
+            outside.push(children);
+            moves.extend(in_slice);
+            ends.push(moves.len());
+        }
+        let mut values: Vec<Option<TablebaseValue>> = vec![None; material.len()];
+        let mut pending = (0..indices.len()).collect::<Vec<usize>>();
+        for round in 0.. {
+            let mut changed = false;
+            let mut waiting = false;
+            pending.retain(|&position| {
+                let start = position.checked_sub(1).map_or(0, |previous| ends[previous]);
+                let mut children = outside[position];
+                for &child in &moves[start..ends[position]] {
+                    children.add(values[child as usize]);
+                }
+                match children.resolve(round) {
+                    Some(value) => {
+                        values[indices[position]] = Some(value);
+                        changed = true;
+                        false
+                    }
+                    None => {
+                        waiting |= children.is_waiting();
+                        true
+                    }
+                }
+            });
+            if !changed && !waiting {
+                break;
+            }
+        }
+        Ok(values
+            .into_iter()
+            .map(|value| value.map_or(0, |value| value.encode()))
+            .collect())
+    }
+
+    /// What is known of the position's moves leaving the slice, and the indices of those
+    /// staying in it.
+    fn children(&self, material: Material, board: &Board) -> io::Result<(Children, Vec<u32>)> {
+        let mut outside = Children::NONE;
+        let mut in_slice = vec![];
+        for child in Engine::successors(board) {
+            match self.child_value(material, &child)? {
+                Ok(value) => outside.add(Some(value)),
+                Err(index) => in_slice.push(index as u32),
+            }
+        }
+        Ok((outside, in_slice))
+    }
+
+    /// The value of a position reached from the slice when it is in a slice generated before,
+    /// or else its index in the slice itself.
+    fn child_value(
+        &self,
+        material: Material,
+        child: &Board,
+    ) -> io::Result<Result<TablebaseValue, usize>> {
+        let child_material = Material::of(child);
+        // Taking the opponent's last piece leaves them without moves.
+        if !child_material.has_side(child.turn) {
+            return Ok(Ok(TablebaseValue::Loss(0)));
+        }
+        let slice = match child_material == material {
+            true => None,
+            false => self.slices.get(&child_material),
+        };
+        match (child_material.index(child), slice) {
+            (Some(index), _) if child_material == material => Ok(Err(index)),
+            (Some(index), Some(slice)) => Ok(Ok(TablebaseValue::decode(slice[index]))),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} is not in a slice generated before {material:?}",
+                    child.to_fen()
+                ),
+            )),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(10 + self.len() * 2 + self.slices.len() * 12);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.max_pieces);
+        bytes.extend_from_slice(&(self.slices.len() as u32).to_le_bytes());
+        for material in Material::all(self.max_pieces) {
+            let Some(values) = self.slices.get(&material) else {
+                continue;
+            };
+            bytes.extend_from_slice(&[
+                material.white_men,
+                material.black_men,
+                material.white_kings,
+                material.black_kings,
+            ]);
+            bytes.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            for value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        fs::File::create(path)?.write_all(&bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut bytes = vec![];
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < 10 || &bytes[..4] != MAGIC {
+            return Err(invalid("Not a tablebase file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("Unsupported tablebase version"));
+        }
+        let max_pieces = bytes[5];
+        let count = u32::from_le_bytes(bytes[6..10].try_into().unwrap());
+        let mut rest = &bytes[10..];
+        let mut slices = HashMap::new();
+        for _ in 0..count {
+            let Some((header, values)) = rest.split_at_checked(12) else {
+                return Err(invalid("Truncated tablebase file"));
+            };
+            let material = Material {
+                white_men: header[0],
+                black_men: header[1],
+                white_kings: header[2],
+                black_kings: header[3],
+            };
+            let len = u64::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+            if !Material::all(max_pieces).contains(&material) || len != material.len() {
+                return Err(invalid("Invalid tablebase slice"));
+            }
+            let Some((values, after)) = values.split_at_checked(len * 2) else {
+                return Err(invalid("Truncated tablebase file"));
+            };
+            let values = values
+                .chunks_exact(2)
+                .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+                .collect();
+            slices.insert(material, values);
+            rest = after;
+        }
+        if !rest.is_empty() {
+            return Err(invalid("Trailing bytes in tablebase file"));
+        }
+        Ok(Self { max_pieces, slices })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::OnceLock};
+
+    use super::*;
+
+    const KINGS: Material = Material {
+        white_men: 0,
+        black_men: 0,
+        white_kings: 1,
+        black_kings: 1,
+    };
+    const TWO_KINGS: Material = Material {
+        white_men: 0,
+        black_men: 0,
+        white_kings: 2,
+        black_kings: 1,
+    };
+
+    /// The king against king slice and the two kings against one it resolves captures from.
+    fn kings() -> &'static Tablebase {
+        static KINGS_TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+        KINGS_TABLEBASE
+            .get_or_init(|| Tablebase::generate_materials(3, [KINGS, TWO_KINGS], |_| {}).unwrap())
+    }
+
+    fn probe(fen: &str) -> Option<TablebaseValue> {
+        kings().probe(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn lone_kings_draw_unless_one_is_taken() {
+        assert_eq!(probe("W:WK1:BK32"), Some(TablebaseValue::Draw));
+        assert_eq!(probe("B:WK1:BK32"), Some(TablebaseValue::Draw));
+        assert_eq!(probe("W:WK22:BK18"), Some(TablebaseValue::Win(1)));
+        // The cornered king can only step along the diagonal into the other's capture.
+        assert_eq!(probe("W:WK29:BK4"), Some(TablebaseValue::Loss(2)));
+    }
+
+    #[test]
+    fn two_kings_beat_a_cornered_king() {
+        assert_eq!(probe("W:WK6,K10:BK1"), Some(TablebaseValue::Win(9)));
+        let tablebase = kings();
+        for index in 0..TWO_KINGS.len() {
+            let Some(board) = TWO_KINGS.board(index) else {
+                continue;
+            };
+            let children = Engine::get_moves(&board)
+                .into_iter()
+                .map(|legal_move| {
+                    let child = Engine::make_move(board.clone(), legal_move.from, legal_move.to);
+                    match tablebase.child_value(TWO_KINGS, &child).unwrap() {
+                        Ok(value) => value,
+                        Err(_) => tablebase.probe(&child).unwrap(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let value = tablebase.probe(&board).unwrap();
+            let fen = board.to_fen();
+            match value {
+                TablebaseValue::Win(distance) => {
+                    assert!(
+                        children.contains(&TablebaseValue::Loss(distance - 1)),
+                        "{fen}"
+                    );
+                    assert!(
+                        children.iter().all(|child| match child {
+                            TablebaseValue::Loss(loss) => *loss >= distance - 1,
+                            _ => true,
+                        }),
+                        "{fen}"
+                    );
+                }
+                TablebaseValue::Loss(distance) => {
+                    let wins = children.iter().map(|child| match child {
+                        TablebaseValue::Win(win) => Some(*win),
+                        _ => None,
+                    });
+                    let slowest = wins.collect::<Option<Vec<_>>>().expect(&fen);
+                    assert_eq!(
+                        slowest.into_iter().max().unwrap_or(0) + 1,
+                        distance.max(1),
+                        "{fen}"
+                    );
+                }
+                TablebaseValue::Draw => {
+                    assert!(children.contains(&TablebaseValue::Draw), "{fen}");
+                    assert!(
+                        !children
+                            .iter()
+                            .any(|child| matches!(child, TablebaseValue::Loss(_))),
+                        "{fen}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn successors_are_the_boards_moves_lead_to() {
+        for fen in [
+            "W:W22:B9,10,17,18",
+            "B:W9,18,27:B23",
+            "W:WK6,K10:BK1",
+            "W:W6,K27:B2,14",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let played = Engine::get_moves(&board)
+                .into_iter()
+                .map(|legal_move| Engine::make_move(board.clone(), legal_move.from, legal_move.to))
+                .collect::<Vec<_>>();
+            assert_eq!(Engine::successors(&board), played, "{fen}");
+        }
+    }
+
+    #[test]
+    fn slices_need_the_slices_they_capture_into() {
+        let error = Tablebase::generate_materials(3, [TWO_KINGS], |_| {})
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn positions_are_numbered_once() {
+        let material = Material {
+            white_men: 1,
+            black_men: 1,
+            white_kings: 1,
+            black_kings: 0,
+        };
+        let mut positions = 0;
+        for index in 0..material.len() {
+            if let Some(board) = material.board(index) {
+                assert_eq!(Material::of(&board), material);
+                assert_eq!(material.index(&board), Some(index));
+                positions += 1;
+            }
+        }
+        assert_eq!(positions, 2 * (28 * 28 - 24) * 30);
+    }
+
+    #[test]
+    fn saved_tablebases_load() {
+        let path = env::temp_dir().join(format!("checkers-tablebase-{}.bin", std::process::id()));
+        let tablebase = kings();
+        tablebase.save(&path).unwrap();
+        let loaded = Tablebase::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.max_pieces(), 3);
+        assert_eq!(loaded.slices, tablebase.slices);
+    }
+}
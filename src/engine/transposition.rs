@@ -0,0 +1,191 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{Move, Notation};
+
+const VALID: u64 = 1 << 63;
+const HAS_MOVE: u64 = 1 << 42;
+const IS_CAPTURE: u64 = 1 << 53;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+impl Entry {
+    /// Packs everything but the key: score in bits 0-31, depth 32-39, bound 40-41,
+    /// then the move's presence, squares and capture flag, and a valid bit on top.
+    fn encode(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let best_move = self.best_move.map_or(0, |best_move| {
+            let square = |point| Notation::square(point).unwrap_or(1) as u64 - 1;
+            HAS_MOVE
+                | square(best_move.from) << 43
+                | square(best_move.to) << 48
+                | if best_move.is_capture { IS_CAPTURE } else { 0 }
+        });
+        VALID | (self.score as u32 as u64) | (self.depth as u64) << 32 | bound << 40 | best_move
+    }
+
+    fn decode(key: u64, data: u64) -> Option<Self> {
+        let square = |shift: u64| Notation::from_square((data >> shift & 0b11111) as u8 + 1);
+        let best_move = match data & HAS_MOVE {
+            0 => None,
+            _ => Some(Move::new(square(43)?, square(48)?, data & IS_CAPTURE != 0)),
+        };
+        Some(Self {
+            key,
+            depth: (data >> 32) as u8,
+            score: data as u32 as i32,
+            bound: match data >> 40 & 0b11 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+            best_move,
+        })
+    }
+}
+
+/// Fixed-size hash table of searched positions indexed by Zobrist key, shared between
+/// search threads without locks.
+///
+/// Each slot stores `key ^ data` next to `data`, so an entry torn by a concurrent write no
+/// longer matches its key and is ignored.
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    /// Allocates `2^bits` slots.
+    pub fn new(bits: u32) -> Self {
+        Self {
+            slots: (0..1u64 << bits)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let (check, data) = self.load(key);
+        match data & VALID != 0 && check ^ data == key {
+            true => Entry::decode(key, data),
+            false => None,
+        }
+    }
+
+    /// Depth-preferred replacement: a slot holding another position is only overwritten by
+    /// an entry searched at least as deep.
+    pub fn store(&self, entry: Entry) {
+        let (check, data) = self.load(entry.key);
+        let old_depth = (data >> 32) as u8;
+        if data & VALID != 0 && check ^ data != entry.key && old_depth > entry.depth {
+            return;
+        }
+        let data = entry.encode();
+        let [check, slot_data] = &self.slots[self.index(entry.key)];
+        check.store(entry.key ^ data, Ordering::Relaxed);
+        slot_data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for [check, data] in &self.slots {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn load(&self, key: u64) -> (u64, u64) {
+        let [check, data] = &self.slots[self.index(key)];
+        (check.load(Ordering::Relaxed), data.load(Ordering::Relaxed))
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key as usize) & (self.slots.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: u64, depth: u8, score: i32, bound: Bound, best_move: Option<Move>) -> Entry {
+        Entry {
+            key,
+            depth,
+            score,
+            bound,
+            best_move,
+        }
+    }
+
+    fn square_move(from: u8, to: u8, is_capture: bool) -> Move {
+        let point = |square| Notation::from_square(square).unwrap();
+        Move::new(point(from), point(to), is_capture)
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let table = TranspositionTable::new(8);
+        let moves = [
+            None,
+            Some(square_move(1, 32, true)),
+            Some(square_move(32, 1, false)),
+            Some(square_move(22, 18, false)),
+        ];
+        let scores = [i32::MIN, -99_999, -1, 0, 1, 12_345, i32::MAX];
+        let bounds = [Bound::Exact, Bound::Lower, Bound::Upper];
+        let mut key = 0u64;
+        for best_move in moves {
+            for score in scores {
+                for bound in bounds {
+                    key = key.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                    let depth = (key >> 56) as u8;
+                    // An entry already in the slot could be deeper and keep it.
+                    table.clear();
+                    table.store(entry(key, depth, score, bound, best_move));
+                    let stored = table.probe(key).unwrap();
+                    assert_eq!(stored.key, key);
+                    assert_eq!(stored.depth, depth);
+                    assert_eq!(stored.score, score);
+                    assert_eq!(stored.bound, bound);
+                    assert_eq!(stored.best_move, best_move);
+                }
+            }
+        }
+        assert!(table.probe(key ^ 1).is_none());
+    }
+
+    #[test]
+    fn deeper_entries_keep_their_slot() {
+        let table = TranspositionTable::new(4);
+        // Both keys fall in slot 5.
+        let (first, second) = (5, 5 + 16);
+        table.store(entry(first, 6, 10, Bound::Exact, None));
+        table.store(entry(second, 3, 20, Bound::Exact, None));
+        assert_eq!(table.probe(first).unwrap().score, 10);
+        assert!(table.probe(second).is_none());
+        table.store(entry(second, 6, 20, Bound::Exact, None));
+        assert!(table.probe(first).is_none());
+        assert_eq!(table.probe(second).unwrap().score, 20);
+        // The same position is always replaced, even by a shallower search.
+        table.store(entry(second, 1, 30, Bound::Lower, None));
+        assert_eq!(table.probe(second).unwrap().depth, 1);
+        table.clear();
+        assert!(table.probe(second).is_none());
+    }
+}
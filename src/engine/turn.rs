@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default, Copy)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, Copy, PartialEq)]
 pub enum Turn {
     #[default]
     White,
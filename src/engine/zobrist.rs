@@ -0,0 +1,121 @@
+use super::{constants::BOARD_SIZE, Board, Cell, Checker, Notation};
+use crate::utility::Point;
+
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE / 2;
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Random keys generated at compile time, so hashes stay stable across builds and can be
+/// stored on disk.
+const KEYS: Zobrist = Zobrist::generate();
+
+pub struct Zobrist {
+    pieces: [[u64; SQUARES]; 4],
+    black_to_move: u64,
+}
+
+impl Zobrist {
+    const fn generate() -> Self {
+        let mut state = SEED;
+        let mut pieces = [[0; SQUARES]; 4];
+        let mut piece = 0;
+        while piece < 4 {
+            let mut square = 0;
+            while square < SQUARES {
+                state = state.wrapping_add(SEED);
+                pieces[piece][square] = Zobrist::mix(state);
+                square += 1;
+            }
+            piece += 1;
+        }
+        state = state.wrapping_add(SEED);
+        Self {
+            pieces,
+            black_to_move: Zobrist::mix(state),
+        }
+    }
+
+    /// SplitMix64 finalizer.
+    const fn mix(mut value: u64) -> u64 {
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    pub fn cell(point: Point, cell: &Cell) -> u64 {
+        let Cell::Checker(checker) = cell else {
+            return 0;
+        };
+        let piece = match checker {
+            Checker::White => 0,
+            Checker::Black => 1,
+            Checker::WhiteQueen => 2,
+            Checker::BlackQueen => 3,
+        };
+        Notation::square(point).map_or(0, |square| KEYS.pieces[piece][square as usize - 1])
+    }
+
+    pub fn black_to_move() -> u64 {
+        KEYS.black_to_move
+    }
+
+    /// Computes the key from scratch; `Board::hash` keeps it up to date incrementally.
+    pub fn hash(board: &Board) -> u64 {
+        board
+            .iter()
+            .fold(0, |hash, (point, cell)| hash ^ Zobrist::cell(point, cell))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Engine, Turn};
+
+    /// The key `Board::hash` should hold, computed from scratch.
+    fn full_hash(board: &Board) -> u64 {
+        match board.turn {
+            Turn::White => Zobrist::hash(board),
+            Turn::Black => Zobrist::hash(board) ^ Zobrist::black_to_move(),
+        }
+    }
+
+    #[test]
+    fn hashes_survive_a_fen_round_trip() {
+        for fen in ["W:W21-32:B1-12", "B:WK6,25:B3,12", "W:W18,K30:BK1,K2,14"] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.hash(), full_hash(&board), "{fen}");
+            assert_eq!(
+                Board::from_fen(&board.to_fen()).unwrap().hash(),
+                board.hash(),
+                "{fen}"
+            );
+        }
+        let white = Board::from_fen("W:W21:B1").unwrap();
+        let black = Board::from_fen("B:W21:B1").unwrap();
+        assert_eq!(white.hash() ^ black.hash(), Zobrist::black_to_move());
+    }
+
+    #[test]
+    fn hashes_follow_every_change_incrementally() {
+        let mut board = Board::new();
+        let start = board.hash();
+        let (point, cell) = board
+            .iter()
+            .find(|(_, cell)| matches!(cell, Cell::Checker(_)))
+            .map(|(point, cell)| (point, *cell))
+            .unwrap();
+        board.set_cell(point, Cell::Empty);
+        assert_ne!(board.hash(), start);
+        board.set_cell(point, cell);
+        assert_eq!(board.hash(), start);
+        // Plays a game picking moves by a fixed rule, captures and crownings included.
+        for ply in 0..120 {
+            let moves = Engine::get_moves(&board);
+            let Some(played_move) = moves.get(ply * 7 % moves.len().max(1)) else {
+                break;
+            };
+            board = Engine::make_move(board, played_move.from, played_move.to);
+            assert_eq!(board.hash(), full_hash(&board), "after ply {ply}");
+        }
+    }
+}
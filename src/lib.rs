@@ -0,0 +1,4 @@
+pub mod engine;
+pub mod utility;
+
+pub use engine::{Cell, Checker};
@@ -1,49 +1,193 @@
-use axum::{response::IntoResponse, routing::get, Router};
-use engine::Board;
+use accounts::{User, SESSION_LIFETIME};
+use axum::{extract::State, response::IntoResponse, routing::get, Router};
+use checkers::{engine, utility};
+use dxp::DxpServer;
+use engine::{Board, Engine, Move, Network, OpeningBook, Outcome, Tablebase, Turn, Weights};
+use matchmaking::Matchmaker;
+use room_actor::{Expiry, RoomHandle, RoomRegistry};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, env, sync::Arc};
+use std::{env, fmt::Display, io, str::FromStr, sync::Arc, thread, time::Duration};
 use store::Store;
-use tokio::sync::{broadcast, Mutex};
+use tokio::{sync::oneshot, time};
 use tracing::event;
+use utility::random_id;
 
-mod engine;
+mod accounts;
+mod dxp;
+mod matchmaking;
+mod ratings;
+mod room_actor;
 mod routes;
 mod store;
 mod templates;
-mod utility;
 
 pub use engine::{Cell, Checker};
-use routes::{GamesRouter, RoomsRouter, WSRouter};
+use routes::{
+    AccountsRouter, ApiRouter, CurrentUser, GamesRouter, MatchmakingRouter, RoomsRouter, WSRouter,
+};
 use templates::{IndexTemplate, RoomHrefTemplate};
 use tower_http::{services::ServeDir, trace::TraceLayer};
 
+const DEFAULT_MCTS_SIMULATIONS: usize = 2000;
+const DEFAULT_ROOM_IDLE_MINUTES: u64 = 7 * 24 * 60;
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const MATCH_INTERVAL: Duration = Duration::from_secs(1);
+const SESSION_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 pub struct AppState {
-    rooms: Mutex<HashMap<String, broadcast::Sender<String>>>,
+    /// Actors of the rooms in use, which own their room's state.
+    rooms: RoomRegistry,
+    store: Arc<dyn Store>,
+    /// Players waiting to be paired for a game.
+    matchmaker: Matchmaker,
+    tablebase: Option<Arc<Tablebase>>,
+    book: Option<Arc<OpeningBook>>,
+    weights: Weights,
+    /// Evaluation of network seats, from `NETWORK_PATH`.
+    network: Option<Arc<Network>>,
+    /// Command line of the Hub engine played by external seats, from `EXTERNAL_ENGINE`.
+    external_engine: Option<String>,
+    /// Playouts per move of MCTS seats, from `MCTS_SIMULATIONS`.
+    mcts_simulations: usize,
+    /// Dedicated pool for engine searches, so they never block the tokio runtime.
+    search_pool: rayon::ThreadPool,
+    /// Lazy SMP threads per search, from `SEARCH_THREADS`; also the size of `search_pool`.
+    search_threads: usize,
+}
+
+impl AppState {
+    pub async fn room(&self, id: &str) -> io::Result<RoomHandle> {
+        self.rooms.get(id).await
+    }
+
+    /// A snapshot of the room's current state.
+    pub async fn get_room(&self, id: &str) -> io::Result<Room> {
+        self.room(id).await?.get().await
+    }
+
+    /// The user holding the side's human seat.
+    pub fn player(&self, room: &Room, turn: Turn) -> Option<User> {
+        self.user(room.player(turn)?)
+    }
+
+    fn user(&self, id: &str) -> Option<User> {
+        self.store.get_user(id).unwrap_or_else(|e| {
+            event!(tracing::Level::ERROR, "Loading user {id} failed: {e}");
+            None
+        })
+    }
+
+    /// Who plays the side: the user's name for a taken human seat, else the seat's label.
+    pub fn player_name(&self, room: &Room, turn: Turn) -> String {
+        match self.player(room, turn) {
+            Some(user) => user.name,
+            None => room.seat(turn).to_string(),
+        }
+    }
+
+    /// Who plays the side as listed with the room: the user's name and rating, else the
+    /// seat's label.
+    pub fn player_label(&self, room: &RoomSummary, turn: Turn) -> String {
+        match room.player(turn).and_then(|id| self.user(id)) {
+            Some(user) => self.rated_name(&user, room.variant),
+            None => room.seat(turn).to_string(),
+        }
+    }
+
+    /// The user's name followed by their rating in the room's variant, e.g. `alice 1500?`;
+    /// guests have no rating.
+    pub fn rated_name(&self, user: &User, variant: Variant) -> String {
+        if user.is_guest() {
+            return user.name.clone();
+        }
+        match self.store.rating(&user.id, variant) {
+            Ok(rating) => format!("{} {rating}", user.name),
+            Err(e) => {
+                event!(
+                    tracing::Level::ERROR,
+                    "Loading rating of {} failed: {e}",
+                    user.id
+                );
+                user.name.clone()
+            }
+        }
+    }
+
+    /// Saves a new room and spawns its actor.
+    pub async fn create_room(&self, room: Room) -> io::Result<RoomHandle> {
+        self.rooms.create(room).await
+    }
+
+    /// Runs a CPU-bound engine task on the search pool and waits for its result.
+    pub async fn spawn_search<T: Send + 'static>(
+        &self,
+        task: impl FnOnce() -> T + Send + 'static,
+    ) -> T {
+        let (tx, rx) = oneshot::channel();
+        self.search_pool.spawn(move || {
+            let _ = tx.send(task());
+        });
+        rx.await.unwrap()
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .init();
     let port = env::var("PORT").unwrap_or("3000".to_string());
     let public = ServeDir::new("public");
-    let mut rooms_senders = HashMap::new();
-    for id in Store::get_rooms().unwrap().keys() {
-        let (tx, _rx) = broadcast::channel(100);
-        rooms_senders.insert(id.clone(), tx);
-    }
+    let store = store::open_store().unwrap();
+    let search_threads = env::var("SEARCH_THREADS")
+        .ok()
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()))
+        .max(1);
+    let search_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(search_threads)
+        .thread_name(|index| format!("search-{index}"))
+        .build()
+        .unwrap();
+    event!(
+        tracing::Level::INFO,
+        "Searching with {search_threads} threads"
+    );
     let app_state = Arc::new(AppState {
-        rooms: Mutex::new(rooms_senders),
+        rooms: RoomRegistry::new(store.clone()).unwrap(),
+        store,
+        matchmaker: Matchmaker::default(),
+        tablebase: load_tablebase(),
+        book: load_book(),
+        weights: load_weights(),
+        network: load_network(),
+        external_engine: env::var("EXTERNAL_ENGINE").ok(),
+        mcts_simulations: env::var("MCTS_SIMULATIONS")
+            .ok()
+            .and_then(|simulations| simulations.parse().ok())
+            .unwrap_or(DEFAULT_MCTS_SIMULATIONS),
+        search_pool,
+        search_threads,
     });
+    if let Some((idle, expiry)) = room_expiry() {
+        tokio::spawn(expire_rooms(app_state.clone(), idle, expiry));
+    }
+    tokio::spawn(match_seeks(app_state.clone()));
+    tokio::spawn(prune_sessions(app_state.clone()));
+    if let Ok(dxp_port) = env::var("DXP_PORT") {
+        tokio::spawn(DxpServer::listen(app_state.clone(), dxp_port));
+    }
     let app = Router::new()
         .route("/", get(index))
         .nest("/ws", WSRouter::get())
         .nest("/rooms", RoomsRouter::get())
         .nest("/games", GamesRouter::get())
+        .nest("/api", ApiRouter::get())
+        .merge(AccountsRouter::get())
+        .merge(MatchmakingRouter::get())
         .with_state(app_state)
         .nest_service("/assets", public)
         .layer(TraceLayer::new_for_http());
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
-        .init();
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
         .await
         .unwrap();
@@ -51,19 +195,173 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
-async fn index() -> impl IntoResponse {
-    let rooms = Store::get_rooms().unwrap();
-    let mut room_hrefs = rooms
-        .values()
-        .map(|r| RoomHrefTemplate {
-            id: r.id.clone(),
-            title: format!("Room {}", r.id.clone()),
-        })
-        .collect::<Vec<RoomHrefTemplate>>();
-    room_hrefs.sort_by_key(|key| key.id.clone());
+fn load_tablebase() -> Option<Arc<Tablebase>> {
+    let path = env::var("TABLEBASE_PATH").unwrap_or("data/tablebase.bin".to_string());
+    match Tablebase::load(&path) {
+        Ok(tablebase) => {
+            event!(
+                tracing::Level::INFO,
+                "Loaded {}-piece tablebase from {path}",
+                tablebase.max_pieces()
+            );
+            Some(Arc::new(tablebase))
+        }
+        Err(e) => {
+            event!(tracing::Level::WARN, "Tablebase {path} not loaded: {e}");
+            None
+        }
+    }
+}
+
+fn load_book() -> Option<Arc<OpeningBook>> {
+    let path = env::var("BOOK_PATH").unwrap_or("data/book.txt".to_string());
+    match OpeningBook::load(&path) {
+        Ok(book) => {
+            event!(
+                tracing::Level::INFO,
+                "Loaded opening book with {} positions from {path}",
+                book.len()
+            );
+            Some(Arc::new(book))
+        }
+        Err(e) => {
+            event!(tracing::Level::WARN, "Opening book {path} not loaded: {e}");
+            None
+        }
+    }
+}
+
+fn load_weights() -> Weights {
+    let path = env::var("WEIGHTS_PATH").unwrap_or("data/weights.txt".to_string());
+    match Weights::load(&path) {
+        Ok(weights) => {
+            event!(
+                tracing::Level::INFO,
+                "Loaded evaluation weights from {path}"
+            );
+            weights
+        }
+        Err(e) => {
+            event!(
+                tracing::Level::WARN,
+                "Evaluation weights {path} not loaded: {e}"
+            );
+            Weights::default()
+        }
+    }
+}
+
+/// Rooms idle for `ROOM_IDLE_MINUTES` (a week by default, 0 to keep them forever) are
+/// archived, or deleted when `ROOM_EXPIRY` is `delete`.
+fn room_expiry() -> Option<(Duration, Expiry)> {
+    let minutes = env::var("ROOM_IDLE_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(DEFAULT_ROOM_IDLE_MINUTES);
+    let expiry = match env::var("ROOM_EXPIRY").as_deref() {
+        Ok("delete") => Expiry::Delete,
+        Ok("archive") | Err(_) => Expiry::Archive,
+        Ok(other) => {
+            event!(
+                tracing::Level::WARN,
+                "Unknown ROOM_EXPIRY {other:?}, archiving idle rooms"
+            );
+            Expiry::Archive
+        }
+    };
+    (minutes > 0).then_some((Duration::from_secs(minutes * 60), expiry))
+}
+
+async fn expire_rooms(state: Arc<AppState>, idle: Duration, expiry: Expiry) {
+    let mut interval = time::interval(EXPIRY_INTERVAL.min(idle));
+    loop {
+        interval.tick().await;
+        match state.rooms.expire(idle, expiry).await {
+            Ok(0) => {}
+            Ok(expired) => event!(tracing::Level::INFO, "Expired {expired} idle rooms"),
+            Err(e) => event!(tracing::Level::ERROR, "Expiring idle rooms failed: {e}"),
+        }
+    }
+}
+
+/// Forgets expired sessions every `SESSION_PRUNE_INTERVAL`, which no cookie signs in with.
+async fn prune_sessions(state: Arc<AppState>) {
+    let mut interval = time::interval(SESSION_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let created_before = store::now().saturating_sub(SESSION_LIFETIME);
+        match state.store.delete_sessions_before(created_before) {
+            Ok(0) => {}
+            Ok(pruned) => event!(tracing::Level::INFO, "Pruned {pruned} expired sessions"),
+            Err(e) => event!(tracing::Level::ERROR, "Pruning sessions failed: {e}"),
+        }
+    }
+}
+
+/// Pairs the open seeks every `MATCH_INTERVAL`, creating each pair's room and sending both
+/// players there.
+async fn match_seeks(state: Arc<AppState>) {
+    let mut interval = time::interval(MATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        for (first, second) in state.matchmaker.pairs() {
+            let room = Matchmaker::room(&first, &second);
+            let id = room.id.clone();
+            match state.create_room(room).await {
+                Ok(_) => {
+                    event!(
+                        tracing::Level::INFO,
+                        "Matched {} and {} in room {id}",
+                        first.user.name,
+                        second.user.name
+                    );
+                    first.start(&id);
+                    second.start(&id);
+                }
+                // Dropping the seeks tells both players to seek again.
+                Err(e) => event!(tracing::Level::ERROR, "Creating matched room failed: {e}"),
+            }
+        }
+    }
+}
+
+fn load_network() -> Option<Arc<Network>> {
+    let path = env::var("NETWORK_PATH").unwrap_or("data/network.bin".to_string());
+    match Network::load(&path) {
+        Ok(network) => {
+            event!(
+                tracing::Level::INFO,
+                "Loaded evaluation network from {path}"
+            );
+            Some(Arc::new(network))
+        }
+        Err(e) => {
+            event!(
+                tracing::Level::WARN,
+                "Evaluation network {path} not loaded: {e}"
+            );
+            None
+        }
+    }
+}
+
+async fn index(State(state): State<Arc<AppState>>, user: CurrentUser) -> impl IntoResponse {
+    let mut listed: Vec<RoomSummary> = state
+        .rooms
+        .summaries()
+        .into_iter()
+        .filter(|room| room.public && !room.archived)
+        .collect();
+    listed.sort_by_key(|room| std::cmp::Reverse(room.last_activity));
     IndexTemplate {
         title: "Checkers".to_string(),
-        rooms: room_hrefs,
+        rooms: listed
+            .iter()
+            .map(|room| RoomHrefTemplate::new(room, &state))
+            .collect(),
+        has_external_engine: state.external_engine.is_some(),
+        has_network: state.network.is_some(),
+        user: user.0,
     }
 }
 
@@ -71,4 +369,394 @@ async fn index() -> impl IntoResponse {
 pub struct Room {
     id: String,
     board: Board,
+    #[serde(default = "Board::new")]
+    start: Board,
+    #[serde(default)]
+    history: Vec<Move>,
+    /// The Zobrist keys of `start` and of the position after each move in `history`, for
+    /// spotting repetitions; rooms saved before it was kept have none.
+    #[serde(default)]
+    positions: Vec<u64>,
+    #[serde(default = "enabled")]
+    hints: bool,
+    #[serde(default)]
+    rated: bool,
+    #[serde(default)]
+    white: Seat,
+    #[serde(default)]
+    black: Seat,
+    /// The ids of the users holding the human seats; a human seat nobody holds goes to the
+    /// first user to move for it.
+    #[serde(default)]
+    white_player: Option<String>,
+    #[serde(default)]
+    black_player: Option<String>,
+    /// How the game ended when it did not end on the board: a resignation or an agreed draw.
+    #[serde(default)]
+    result: Option<Outcome>,
+    /// The side whose draw offer is standing.
+    #[serde(default)]
+    draw_offer: Option<Turn>,
+    /// The number of events in the room's log that built this state.
+    #[serde(default)]
+    version: usize,
+    /// Chosen when the room was created; empty for rooms created by visiting their address.
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    variant: Variant,
+    #[serde(default)]
+    time_control: Option<TimeControl>,
+    /// Listed on the index page; rooms created private are unlisted and need their `invite`.
+    #[serde(default = "enabled")]
+    public: bool,
+    /// Seconds since the Unix epoch of the room's creation and of its latest event.
+    #[serde(default)]
+    created_at: u64,
+    #[serde(default)]
+    last_activity: u64,
+    /// Idle for too long: unlisted and closed to moves until it is reset.
+    #[serde(default)]
+    archived: bool,
+    /// The tokens guarding a private room; anyone may use rooms without one.
+    #[serde(default)]
+    invite: Option<Invite>,
+}
+
+/// Who plays a side of a room.
+#[derive(Clone, Copy, Deserialize, Serialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Seat {
+    #[default]
+    Human,
+    Computer,
+    /// The Monte Carlo tree search, a less tactical style for casual games.
+    Mcts,
+    /// The alpha-beta computer evaluating with the trained network.
+    Network,
+    /// A Hub-protocol engine run as a child process.
+    External,
+    /// An engine connected over DXP, which sends its own moves.
+    Dxp,
+}
+
+impl Seat {
+    /// The server picks this seat's moves itself.
+    pub fn is_automatic(&self) -> bool {
+        matches!(
+            self,
+            Seat::Computer | Seat::Mcts | Seat::Network | Seat::External
+        )
+    }
+}
+
+impl Display for Seat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Seat::Human => write!(f, "Human"),
+            Seat::Computer => write!(f, "Computer"),
+            Seat::Mcts => write!(f, "Computer (MCTS)"),
+            Seat::Network => write!(f, "Computer (neural network)"),
+            Seat::External => write!(f, "External engine"),
+            Seat::Dxp => write!(f, "DXP engine"),
+        }
+    }
+}
+
+/// The rules a room plays.
+#[derive(Clone, Copy, Deserialize, Serialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    #[default]
+    Russian,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 1] = [Variant::Russian];
+
+    /// The variant's name in forms and stored data, e.g. `russian`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Variant::Russian => "russian",
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variant::Russian => write!(f, "Russian draughts"),
+        }
+    }
+}
+
+/// Minutes per side plus seconds added after each move, written `10+5`. Announced with the
+/// room; clocks are not kept yet.
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct TimeControl {
+    minutes: u32,
+    increment: u32,
+}
+
+impl TimeControl {
+    /// The time control chosen in a form: empty for untimed games, else e.g. `10+5`.
+    pub fn from_form(time_control: &str) -> Result<Option<Self>, String> {
+        match time_control.trim() {
+            "" => Ok(None),
+            time_control => time_control.parse().map(Some),
+        }
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(time_control: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time control {time_control:?}, expected e.g. 10+5");
+        let (minutes, increment) = time_control.split_once('+').ok_or_else(invalid)?;
+        Ok(TimeControl {
+            minutes: minutes.trim().parse().map_err(|_| invalid())?,
+            increment: increment.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}+{}", self.minutes, self.increment)
+    }
+}
+
+/// What room listings show of a room. Rooms keep theirs up to date as they change, so
+/// listings never load a room's log.
+#[derive(Clone)]
+pub struct RoomSummary {
+    pub id: String,
+    pub title: String,
+    pub variant: Variant,
+    pub time_control: Option<TimeControl>,
+    pub rated: bool,
+    pub public: bool,
+    pub archived: bool,
+    pub status: RoomStatus,
+    pub white: Seat,
+    pub black: Seat,
+    pub white_player: Option<String>,
+    pub black_player: Option<String>,
+    pub created_at: u64,
+    pub last_activity: u64,
+}
+
+impl RoomSummary {
+    pub fn seat(&self, turn: Turn) -> Seat {
+        match turn {
+            Turn::White => self.white,
+            Turn::Black => self.black,
+        }
+    }
+
+    pub fn player(&self, turn: Turn) -> Option<&str> {
+        match turn {
+            Turn::White => self.white_player.as_deref(),
+            Turn::Black => self.black_player.as_deref(),
+        }
+    }
+}
+
+/// Where a room's game stands, as listed on the index page.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RoomStatus {
+    /// No move played yet.
+    Waiting,
+    Playing,
+    Finished,
+    Archived,
+}
+
+impl Display for RoomStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoomStatus::Waiting => write!(f, "Waiting"),
+            RoomStatus::Playing => write!(f, "Playing"),
+            RoomStatus::Finished => write!(f, "Finished"),
+            RoomStatus::Archived => write!(f, "Archived"),
+        }
+    }
+}
+
+/// The secrets of a private room, which only requests carrying its invite token may view or
+/// play in.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Invite {
+    /// Shared in the room's invite link; regenerating it locks out everyone holding the old one.
+    token: String,
+    /// Held by the room's creator alone, who may regenerate `token`.
+    creator: String,
+}
+
+impl Invite {
+    const TOKEN_LENGTH: usize = 24;
+
+    pub fn generate() -> Self {
+        Self {
+            token: random_id(Invite::TOKEN_LENGTH),
+            creator: random_id(Invite::TOKEN_LENGTH),
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Room {
+    pub fn new(id: String, board: Board) -> Self {
+        Self {
+            id,
+            start: board.clone(),
+            positions: vec![board.hash()],
+            board,
+            history: vec![],
+            hints: true,
+            rated: false,
+            white: Seat::Human,
+            black: Seat::Human,
+            white_player: None,
+            black_player: None,
+            result: None,
+            draw_offer: None,
+            version: 0,
+            name: String::new(),
+            variant: Variant::Russian,
+            time_control: None,
+            public: true,
+            created_at: 0,
+            last_activity: 0,
+            archived: false,
+            invite: None,
+        }
+    }
+
+    /// The room's name, or its id for rooms without one.
+    pub fn title(&self) -> String {
+        match self.name.is_empty() {
+            true => format!("Room {}", self.id),
+            false => self.name.clone(),
+        }
+    }
+
+    pub fn summary(&self) -> RoomSummary {
+        RoomSummary {
+            id: self.id.clone(),
+            title: self.title(),
+            variant: self.variant,
+            time_control: self.time_control,
+            rated: self.rated,
+            public: self.public,
+            archived: self.archived,
+            status: self.status(),
+            white: self.white,
+            black: self.black,
+            white_player: self.white_player.clone(),
+            black_player: self.black_player.clone(),
+            created_at: self.created_at,
+            last_activity: self.last_activity,
+        }
+    }
+
+    pub fn status(&self) -> RoomStatus {
+        match self.outcome() {
+            _ if self.archived => RoomStatus::Archived,
+            Some(_) => RoomStatus::Finished,
+            None if self.history.is_empty() => RoomStatus::Waiting,
+            None => RoomStatus::Playing,
+        }
+    }
+
+    pub fn seat(&self, turn: Turn) -> Seat {
+        match turn {
+            Turn::White => self.white,
+            Turn::Black => self.black,
+        }
+    }
+
+    /// The computer or an external engine is to move in an unfinished game.
+    pub fn is_computer_to_move(&self) -> bool {
+        self.seat(self.board.turn).is_automatic() && self.outcome().is_none() && !self.archived
+    }
+
+    /// Engine hints are a training aid and never allowed in rated games.
+    pub fn can_hint(&self) -> bool {
+        self.hints && !self.rated
+    }
+
+    /// Analysis would help a player during a rated game, so it waits for the game to end.
+    pub fn can_analyse(&self) -> bool {
+        !self.rated || self.outcome().is_some()
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.result
+            .or_else(|| Engine::get_outcome(&self.board))
+            .or(self.is_repetition_draw().then_some(Outcome::Draw))
+    }
+
+    /// The id of the user holding the side's seat.
+    pub fn player(&self, turn: Turn) -> Option<&str> {
+        match turn {
+            Turn::White => self.white_player.as_deref(),
+            Turn::Black => self.black_player.as_deref(),
+        }
+    }
+
+    /// Whether the user, or an anonymous request for `None`, may act for the side: a human
+    /// plays it and its seat is free or theirs.
+    pub fn may_play(&self, turn: Turn, user: Option<&str>) -> bool {
+        self.seat(turn) == Seat::Human && self.player(turn).is_none_or(|id| Some(id) == user)
+    }
+
+    /// Whether the user may change the seats and hints or reset the game: anyone until a
+    /// player holds a seat, then only the players and the room's creator.
+    pub fn may_manage(&self, user: Option<&str>, creator: bool) -> bool {
+        let unclaimed = self.white_player.is_none() && self.black_player.is_none();
+        unclaimed || creator || self.is_player(user)
+    }
+
+    /// Whether the user holds either of the room's seats.
+    pub fn is_player(&self, user: Option<&str>) -> bool {
+        user.is_some_and(|id| {
+            [Turn::White, Turn::Black]
+                .map(|turn| self.player(turn))
+                .contains(&Some(id))
+        })
+    }
+
+    /// The side the user acts for: the side to move when they may play it, otherwise the
+    /// other side when they may play that.
+    pub fn human_turn(&self, user: Option<&str>) -> Option<Turn> {
+        let turn = self.board.turn;
+        [turn, turn.next()]
+            .into_iter()
+            .find(|turn| self.may_play(*turn, user))
+    }
+
+    /// Threefold repetition of the current position, compared by Zobrist key.
+    pub fn is_repetition_draw(&self) -> bool {
+        let hash = self.board.hash();
+        self.positions.iter().filter(|key| **key == hash).count() >= 3
+    }
+
+    /// Rebuilds `positions` by replaying the moves when they do not match the history, as
+    /// for rooms loaded from older snapshots.
+    pub fn replay_positions(&mut self) {
+        if self.positions.len() == self.history.len() + 1 {
+            return;
+        }
+        let mut board = self.start.clone();
+        self.positions = vec![board.hash()];
+        for played_move in &self.history {
+            board = Engine::make_move(board, played_move.from, played_move.to);
+            self.positions.push(board.hash());
+        }
+    }
 }
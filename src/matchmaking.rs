@@ -0,0 +1,207 @@
+//! Pairing of players seeking a game: each seek waits for an opponent of the same variant and
+//! time control whose rating is close enough, the accepted gap widening the longer it waits.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::oneshot;
+
+use crate::{
+    accounts::User, engine::Board, ratings::Rating, utility::random_id, Room, TimeControl, Variant,
+};
+
+/// The rating gap a seek accepts straight away.
+const INITIAL_WINDOW: f64 = 100.0;
+/// The gap grows by `WINDOW_GROWTH` for every `WINDOW_STEP` spent waiting.
+const WINDOW_GROWTH: f64 = 50.0;
+const WINDOW_STEP: Duration = Duration::from_secs(5);
+
+/// A player waiting for an opponent, told the id of the room made for their game.
+pub struct Seek {
+    pub user: User,
+    /// The user's rating in `variant` when they started seeking.
+    pub rating: Rating,
+    pub variant: Variant,
+    pub time_control: Option<TimeControl>,
+    since: Instant,
+    matched: oneshot::Sender<String>,
+}
+
+impl Seek {
+    /// A seek, and the receiver of its room's id. Dropping the receiver withdraws the seek.
+    pub fn new(
+        user: User,
+        rating: Rating,
+        variant: Variant,
+        time_control: Option<TimeControl>,
+    ) -> (Self, oneshot::Receiver<String>) {
+        let (matched, receiver) = oneshot::channel();
+        let seek = Self {
+            user,
+            rating,
+            variant,
+            time_control,
+            since: Instant::now(),
+            matched,
+        };
+        (seek, receiver)
+    }
+
+    /// The rating gap accepted after waiting until `now`.
+    fn window(&self, now: Instant) -> f64 {
+        let steps = now.saturating_duration_since(self.since).as_secs() / WINDOW_STEP.as_secs();
+        INITIAL_WINDOW + WINDOW_GROWTH * steps as f64
+    }
+
+    /// Whether this seek would play the other: another user seeking the same game, rated
+    /// within this seek's window at `now`.
+    fn accepts(&self, other: &Seek, now: Instant) -> bool {
+        self.user.id != other.user.id
+            && self.variant == other.variant
+            && self.time_control == other.time_control
+            && (self.rating.rating - other.rating.rating).abs() <= self.window(now)
+    }
+
+    /// Tells the seeker their game's room.
+    pub fn start(self, room_id: &str) {
+        let _ = self.matched.send(room_id.to_string());
+    }
+}
+
+/// The open seeks, paired by the background matcher.
+#[derive(Default)]
+pub struct Matchmaker {
+    seeks: Mutex<Vec<Seek>>,
+}
+
+impl Matchmaker {
+    /// Adds the seek, replacing any other seek of its user.
+    pub fn join(&self, seek: Seek) {
+        let mut seeks = self.seeks.lock().unwrap();
+        seeks.retain(|other| other.user.id != seek.user.id);
+        seeks.push(seek);
+    }
+
+    /// Takes out the seeks that can be paired now, each with the closest-rated opponent that
+    /// both sides accept, the longest waiting seeks choosing first. Withdrawn seeks are
+    /// dropped.
+    pub fn pairs(&self) -> Vec<(Seek, Seek)> {
+        self.pairs_at(Instant::now())
+    }
+
+    fn pairs_at(&self, now: Instant) -> Vec<(Seek, Seek)> {
+        let mut seeks = self.seeks.lock().unwrap();
+        seeks.retain(|seek| !seek.matched.is_closed());
+        seeks.sort_by_key(|seek| seek.since);
+        let mut pairs = vec![];
+        let mut waiting: Vec<Seek> = vec![];
+        for seek in seeks.drain(..) {
+            let opponent = waiting
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| other.accepts(&seek, now) && seek.accepts(other, now))
+                .min_by(|(_, a), (_, b)| {
+                    let gap = |other: &Seek| (other.rating.rating - seek.rating.rating).abs();
+                    gap(a).total_cmp(&gap(b))
+                })
+                .map(|(index, _)| index);
+            match opponent {
+                Some(index) => pairs.push((waiting.remove(index), seek)),
+                None => waiting.push(seek),
+            }
+        }
+        *seeks = waiting;
+        pairs
+    }
+
+    /// The room for a pair's game, with colours drawn at random. The game is rated when both
+    /// players are registered.
+    pub fn room(first: &Seek, second: &Seek) -> Room {
+        let (white, black) = match rand::random() {
+            true => (first, second),
+            false => (second, first),
+        };
+        Room {
+            name: format!("{} vs {}", white.user.name, black.user.name),
+            variant: first.variant,
+            time_control: first.time_control,
+            rated: !white.user.is_guest() && !black.user.is_guest(),
+            white_player: Some(white.user.id.clone()),
+            black_player: Some(black.user.id.clone()),
+            ..Room::new(random_id(8), Board::new())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A guest's seek of an untimed game, rated `rating`, made `after` the start.
+    fn seek(start: Instant, rating: f64, after: u64) -> (Seek, oneshot::Receiver<String>) {
+        let rating = Rating {
+            rating,
+            ..Rating::default()
+        };
+        let (mut seek, matched) = Seek::new(User::guest(), rating, Variant::Russian, None);
+        seek.since = start + Duration::from_secs(after);
+        (seek, matched)
+    }
+
+    fn ratings(pairs: &[(Seek, Seek)]) -> Vec<(f64, f64)> {
+        pairs
+            .iter()
+            .map(|(first, second)| (first.rating.rating, second.rating.rating))
+            .collect()
+    }
+
+    #[test]
+    fn seeks_pair_with_the_closest_rating_both_accept() {
+        let start = Instant::now();
+        let matchmaker = Matchmaker::default();
+        let (first, _first) = seek(start, 1500.0, 0);
+        let (second, _second) = seek(start, 1620.0, 1);
+        let (third, _third) = seek(start, 1570.0, 2);
+        [first, second, third]
+            .into_iter()
+            .for_each(|seek| matchmaker.join(seek));
+        let pairs = matchmaker.pairs_at(start + Duration::from_secs(2));
+        assert_eq!(ratings(&pairs), vec![(1620.0, 1570.0)]);
+        assert_eq!(matchmaker.seeks.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn windows_widen_while_seeks_wait() {
+        let start = Instant::now();
+        let matchmaker = Matchmaker::default();
+        let (first, _first) = seek(start, 1500.0, 0);
+        let (second, _second) = seek(start, 1720.0, 0);
+        matchmaker.join(first);
+        matchmaker.join(second);
+        // 220 points apart: the windows reach 200 after 10 seconds and 250 after 15.
+        assert!(matchmaker.pairs_at(start).is_empty());
+        assert!(matchmaker
+            .pairs_at(start + Duration::from_secs(14))
+            .is_empty());
+        let pairs = matchmaker.pairs_at(start + Duration::from_secs(15));
+        assert_eq!(ratings(&pairs), vec![(1500.0, 1720.0)]);
+    }
+
+    #[test]
+    fn withdrawn_and_different_seeks_stay_unpaired() {
+        let start = Instant::now();
+        let matchmaker = Matchmaker::default();
+        let (first, _first) = seek(start, 1500.0, 0);
+        let (mut timed, _timed) = seek(start, 1500.0, 0);
+        timed.time_control = Some("5+0".parse().unwrap());
+        let (withdrawn, matched) = seek(start, 1500.0, 0);
+        drop(matched);
+        [first, timed, withdrawn]
+            .into_iter()
+            .for_each(|seek| matchmaker.join(seek));
+        assert!(matchmaker.pairs_at(start).is_empty());
+        assert_eq!(matchmaker.seeks.lock().unwrap().len(), 2);
+    }
+}
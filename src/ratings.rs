@@ -0,0 +1,228 @@
+//! Glicko-2 ratings of registered users, kept per variant and updated after every rated game,
+//! each game counting as a rating period of its own.
+//!
+//! See Glickman, "Example of the Glicko-2 system" (2013), whose steps and example the code
+//! follows.
+
+use std::{f64::consts::PI, fmt::Display, io};
+
+use serde::{Deserialize, Serialize};
+use tracing::event;
+
+use crate::{
+    engine::{Outcome, Turn},
+    store::{now, Store},
+    Room, Variant,
+};
+
+/// Converts ratings and deviations between the Glicko scale and Glicko-2's.
+const SCALE: f64 = 173.7178;
+/// Constrains how fast volatility changes.
+const TAU: f64 = 0.5;
+/// Tolerance of the volatility iteration.
+const CONVERGENCE: f64 = 0.000_001;
+/// Ratings with a larger deviation are shown as provisional.
+const PROVISIONAL_DEVIATION: f64 = 110.0;
+
+/// A player's strength on the Glicko scale.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    /// How uncertain `rating` is; high for new players and falling with every game.
+    pub deviation: f64,
+    /// How erratic the player's results are.
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    /// The rating of a player with no rated games.
+    fn default() -> Self {
+        Self {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+        }
+    }
+}
+
+impl Rating {
+    /// Still too uncertain to trust, as for players with few rated games.
+    pub fn is_provisional(&self) -> bool {
+        self.deviation > PROVISIONAL_DEVIATION
+    }
+
+    /// The rating after a period of games against opponents of the given ratings, scored 1 for
+    /// a win, 0.5 for a draw and 0 for a loss.
+    pub fn update(&self, results: &[(Rating, f64)]) -> Rating {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+        if results.is_empty() {
+            return Rating {
+                deviation: (phi.powi(2) + self.volatility.powi(2)).sqrt() * SCALE,
+                ..*self
+            };
+        }
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi.powi(2) / PI.powi(2)).sqrt();
+        let games: Vec<(f64, f64, f64)> = results
+            .iter()
+            .map(|(opponent, score)| {
+                let g = g(opponent.deviation / SCALE);
+                let expected = 1.0 / (1.0 + (-g * (mu - (opponent.rating - 1500.0) / SCALE)).exp());
+                (g, expected, *score)
+            })
+            .collect();
+        let variance = 1.0
+            / games
+                .iter()
+                .map(|(g, expected, _)| g.powi(2) * expected * (1.0 - expected))
+                .sum::<f64>();
+        let improvement: f64 = games
+            .iter()
+            .map(|(g, expected, score)| g * (score - expected))
+            .sum();
+        let delta = variance * improvement;
+        let volatility = self.volatility(phi, variance, delta);
+        let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+        let new_phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / variance).sqrt();
+        Rating {
+            rating: (mu + new_phi.powi(2) * improvement) * SCALE + 1500.0,
+            deviation: new_phi * SCALE,
+            volatility,
+        }
+    }
+
+    /// The new volatility, found by the Illinois algorithm.
+    fn volatility(&self, phi: f64, variance: f64, delta: f64) -> f64 {
+        let a = self.volatility.powi(2).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta.powi(2) - phi.powi(2) - variance - ex)
+                / (2.0 * (phi.powi(2) + variance + ex).powi(2))
+                - (x - a) / TAU.powi(2)
+        };
+        let mut low = a;
+        let mut high = match delta.powi(2) > phi.powi(2) + variance {
+            true => (delta.powi(2) - phi.powi(2) - variance).ln(),
+            false => {
+                let mut k = 1.0;
+                while f(a - k * TAU) < 0.0 {
+                    k += 1.0;
+                }
+                a - k * TAU
+            }
+        };
+        let (mut f_low, mut f_high) = (f(low), f(high));
+        while (high - low).abs() > CONVERGENCE {
+            let next = low + (low - high) * f_low / (f_high - f_low);
+            let f_next = f(next);
+            if f_next * f_high <= 0.0 {
+                (low, f_low) = (high, f_high);
+            } else {
+                f_low /= 2.0;
+            }
+            (high, f_high) = (next, f_next);
+        }
+        (low / 2.0).exp()
+    }
+}
+
+impl Display for Rating {
+    /// Rounded, with a `?` when provisional, e.g. `1623` or `1500?`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.0}", self.rating)?;
+        if self.is_provisional() {
+            write!(f, "?")?;
+        }
+        Ok(())
+    }
+}
+
+/// A user's rating in a variant after a rated game; a user's changes in order are their
+/// rating history.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RatingChange {
+    pub user_id: String,
+    pub variant: Variant,
+    /// The room of the game that changed the rating.
+    pub room_id: String,
+    pub rating: Rating,
+    pub at: u64,
+}
+
+/// Records both players' new ratings after the room's rated game ended. Games are only rated
+/// between two different registered users holding the human seats; others are skipped.
+pub fn rate_game(store: &dyn Store, room: &Room) -> io::Result<()> {
+    let Some(outcome) = room.outcome() else {
+        return Ok(());
+    };
+    let (Some(white), Some(black)) = (room.player(Turn::White), room.player(Turn::Black)) else {
+        event!(
+            tracing::Level::INFO,
+            "Room {} unrated: a seat has no player",
+            room.id
+        );
+        return Ok(());
+    };
+    let registered = |id| {
+        let user = store.get_user(id)?;
+        io::Result::Ok(user.is_some_and(|user| !user.is_guest()))
+    };
+    if white == black || !registered(white)? || !registered(black)? {
+        event!(
+            tracing::Level::INFO,
+            "Room {} unrated: not two accounts",
+            room.id
+        );
+        return Ok(());
+    }
+    let white_score = match outcome {
+        Outcome::WhiteWins => 1.0,
+        Outcome::BlackWins => 0.0,
+        Outcome::Draw => 0.5,
+    };
+    let white_rating = store.rating(white, room.variant)?;
+    let black_rating = store.rating(black, room.variant)?;
+    let at = now();
+    let change = |user_id: &str, rating| RatingChange {
+        user_id: user_id.to_string(),
+        variant: room.variant,
+        room_id: room.id.clone(),
+        rating,
+        at,
+    };
+    store.add_ratings(&[
+        change(white, white_rating.update(&[(black_rating, white_score)])),
+        change(
+            black,
+            black_rating.update(&[(white_rating, 1.0 - white_score)]),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratings_follow_the_glicko_2_example() {
+        let player = Rating {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+        };
+        let opponent = |rating, deviation| Rating {
+            rating,
+            deviation,
+            ..Rating::default()
+        };
+        let updated = player.update(&[
+            (opponent(1400.0, 30.0), 1.0),
+            (opponent(1550.0, 100.0), 0.0),
+            (opponent(1700.0, 300.0), 0.0),
+        ]);
+        assert!((updated.rating - 1464.06).abs() < 0.01);
+        assert!((updated.deviation - 151.52).abs() < 0.01);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001);
+        assert!(updated.is_provisional() && updated.to_string() == "1464?");
+    }
+}
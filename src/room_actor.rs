@@ -0,0 +1,541 @@
+use std::{collections::HashMap, io, sync::Arc, time::Duration};
+
+use askama::Template;
+use tokio::{
+    sync::{broadcast, mpsc, oneshot, Mutex},
+    time,
+};
+use tracing::event;
+
+use crate::{
+    engine::{Board, Engine, Move},
+    ratings::rate_game,
+    store::{now, LoggedEvent, RoomEvent, Store},
+    templates::BoardTemplate,
+    utility::Point,
+    Room, RoomSummary, Seat,
+};
+
+/// An actor with no joined clients stops after this long without commands.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Board updates kept for clients that fall behind.
+const BROADCAST_CAPACITY: usize = 100;
+
+/// The summaries of every room, by id, shared by the registry and the rooms' actors.
+type Summaries = Arc<std::sync::Mutex<HashMap<String, RoomSummary>>>;
+
+/// Picks the events to record for the room's current state.
+type Update = dyn FnOnce(&Room) -> Vec<RoomEvent> + Send;
+
+/// Something a room's actor is asked to do; each one answers on its `reply` channel.
+enum RoomCommand {
+    Get {
+        reply: oneshot::Sender<Room>,
+    },
+    /// The board with the legal moves of the piece on `from` highlighted. A point off the
+    /// board is an `InvalidInput` error.
+    Select {
+        from: Point,
+        reply: oneshot::Sender<io::Result<(Room, Board)>>,
+    },
+    /// Plays the move if `seat` is to move, `hash` (when given) is still the position's key
+    /// and the move is legal. Human moves are made for `user`, who takes the side's seat if
+    /// nobody holds it and must hold it otherwise.
+    Move {
+        from: Point,
+        to: Point,
+        seat: Seat,
+        user: Option<String>,
+        hash: Option<u64>,
+        reply: oneshot::Sender<io::Result<(Room, Option<Move>)>>,
+    },
+    /// Starts a new game from the start position, keeping the room's settings and seats.
+    Reset {
+        reply: oneshot::Sender<io::Result<Room>>,
+    },
+    /// Records the events `update` picks for the room's current state, e.g. a seat change or
+    /// a resignation.
+    Update {
+        update: Box<Update>,
+        reply: oneshot::Sender<io::Result<Room>>,
+    },
+    /// Subscribes to the room's board updates.
+    Join {
+        reply: oneshot::Sender<(Room, broadcast::Receiver<String>)>,
+    },
+    /// Stops the actor, which disconnects every joined client.
+    Close,
+}
+
+/// Owns one room: commands are handled one at a time, so concurrent moves can never
+/// overwrite each other, and every change is recorded as events, then broadcast, in order.
+struct RoomActor {
+    room: Room,
+    store: Arc<dyn Store>,
+    summaries: Summaries,
+    sender: broadcast::Sender<String>,
+    commands: mpsc::Receiver<RoomCommand>,
+}
+
+impl RoomActor {
+    async fn run(mut self) {
+        loop {
+            let command = match time::timeout(IDLE_TIMEOUT, self.commands.recv()).await {
+                Ok(Some(RoomCommand::Close) | None) => return,
+                Ok(Some(command)) => command,
+                Err(_) if self.sender.receiver_count() > 0 => continue,
+                Err(_) => break,
+            };
+            self.handle(command);
+        }
+        // Idle: refuse new commands but answer the ones already queued.
+        self.commands.close();
+        while let Some(command) = self.commands.recv().await {
+            self.handle(command);
+        }
+    }
+
+    fn handle(&mut self, command: RoomCommand) {
+        match command {
+            RoomCommand::Close => {}
+            RoomCommand::Get { reply } => {
+                let _ = reply.send(self.room.clone());
+            }
+            RoomCommand::Select { from, reply } => {
+                let selected = match Engine::is_valid(&self.room.board, &from) {
+                    true => Ok((
+                        self.room.clone(),
+                        Engine::with_legal_moves(self.room.board.clone(), from),
+                    )),
+                    false => Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{from} is not on the board"),
+                    )),
+                };
+                let _ = reply.send(selected);
+            }
+            RoomCommand::Move {
+                from,
+                to,
+                seat,
+                user,
+                hash,
+                reply,
+            } => {
+                let played = self
+                    .play(from, to, seat, user, hash)
+                    .map(|legal_move| (self.room.clone(), legal_move));
+                let _ = reply.send(played);
+            }
+            RoomCommand::Reset { reply } => {
+                let reset = self.record(vec![RoomEvent::Reset]);
+                let _ = reply.send(reset.map(|_| self.room.clone()));
+            }
+            RoomCommand::Update { update, reply } => {
+                let events = update(&self.room);
+                let updated = self.record(events);
+                let _ = reply.send(updated.map(|_| self.room.clone()));
+            }
+            RoomCommand::Join { reply } => {
+                let _ = reply.send((self.room.clone(), self.sender.subscribe()));
+            }
+        }
+    }
+
+    /// The move played, or `None` when it is not `seat`'s turn, the position has changed or
+    /// the move is not legal. A human side held by another user is a `PermissionDenied`
+    /// error.
+    fn play(
+        &mut self,
+        from: Point,
+        to: Point,
+        seat: Seat,
+        user: Option<String>,
+        hash: Option<u64>,
+    ) -> io::Result<Option<Move>> {
+        let room = &self.room;
+        let turn = room.board.turn;
+        if room.seat(turn) != seat || hash.is_some_and(|hash| hash != room.board.hash()) {
+            return Ok(None);
+        }
+        if seat == Seat::Human && !room.may_play(turn, user.as_deref()) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{turn:?} is played by someone else"),
+            ));
+        }
+        let mut events = vec![];
+        if let (Seat::Human, None, Some(user)) = (seat, room.player(turn), user) {
+            events.push(RoomEvent::PlayerJoined { turn, user });
+        }
+        events.push(RoomEvent::Move { from, to });
+        let events: Vec<LoggedEvent> = events.into_iter().map(LoggedEvent::now).collect();
+        let mut played = room.clone();
+        for logged in &events {
+            if played.apply(&logged.event, logged.at).is_err() {
+                return Ok(None);
+            }
+        }
+        self.commit(played, &events)?;
+        Ok(self.room.history.last().copied())
+    }
+
+    /// Applies the events in order and records them, changing nothing if any is invalid.
+    fn record(&mut self, events: Vec<RoomEvent>) -> io::Result<()> {
+        let events: Vec<LoggedEvent> = events.into_iter().map(LoggedEvent::now).collect();
+        let mut room = self.room.clone();
+        for logged in &events {
+            room.apply(&logged.event, logged.at)?;
+        }
+        self.commit(room, &events)
+    }
+
+    /// Saves the events that turned the room into `room`, then makes it current and
+    /// broadcasts it. Rates the game if they ended a rated one, and disconnects every client
+    /// if they replaced the invite token the clients joined with.
+    fn commit(&mut self, room: Room, events: &[LoggedEvent]) -> io::Result<()> {
+        if let Err(e) = self.store.save(&room, events) {
+            event!(tracing::Level::ERROR, "Saving room {} failed: {e}", room.id);
+            return Err(e);
+        }
+        let ended = self.room.outcome().is_none() && room.outcome().is_some();
+        self.room = room;
+        let summary = self.room.summary();
+        self.summaries
+            .lock()
+            .unwrap()
+            .insert(summary.id.clone(), summary);
+        if ended && self.room.rated {
+            // The game itself is saved, so a failed rating does not undo it.
+            if let Err(e) = rate_game(self.store.as_ref(), &self.room) {
+                event!(
+                    tracing::Level::ERROR,
+                    "Rating room {} failed: {e}",
+                    self.room.id
+                );
+            }
+        }
+        let regenerated = events
+            .iter()
+            .any(|logged| matches!(logged.event, RoomEvent::InviteRegenerated { .. }));
+        if regenerated {
+            // Dropping the old sender ends every joined socket; clients that still hold a
+            // valid token reconnect.
+            self.sender = broadcast::channel(BROADCAST_CAPACITY).0;
+        }
+        self.broadcast();
+        Ok(())
+    }
+
+    /// Sends the board to every joined client; nobody listening is not an error.
+    fn broadcast(&self) {
+        let board =
+            BoardTemplate::new(&self.room.board, self.room.id.clone(), None).with_room(&self.room);
+        let _ = self.sender.send(board.render().unwrap());
+    }
+}
+
+/// Cheap, cloneable address of a room's actor.
+#[derive(Clone)]
+pub struct RoomHandle {
+    commands: mpsc::Sender<RoomCommand>,
+}
+
+impl RoomHandle {
+    /// Spawns the actor owning `room`, which keeps its entry of `summaries` current.
+    fn spawn(room: Room, store: Arc<dyn Store>, summaries: Summaries) -> Self {
+        let (commands, receiver) = mpsc::channel(32);
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        let actor = RoomActor {
+            room,
+            store,
+            summaries,
+            sender,
+            commands: receiver,
+        };
+        tokio::spawn(actor.run());
+        Self { commands }
+    }
+
+    pub async fn get(&self) -> io::Result<Room> {
+        self.request(|reply| RoomCommand::Get { reply }).await
+    }
+
+    pub async fn select(&self, from: Point) -> io::Result<(Room, Board)> {
+        self.request(|reply| RoomCommand::Select { from, reply })
+            .await?
+    }
+
+    /// Plays a move for `seat`; with `hash`, only if the position has not changed since.
+    pub async fn play(
+        &self,
+        from: Point,
+        to: Point,
+        seat: Seat,
+        user: Option<String>,
+        hash: Option<u64>,
+    ) -> io::Result<(Room, Option<Move>)> {
+        self.request(|reply| RoomCommand::Move {
+            from,
+            to,
+            seat,
+            user,
+            hash,
+            reply,
+        })
+        .await?
+    }
+
+    pub async fn reset(&self) -> io::Result<Room> {
+        self.request(|reply| RoomCommand::Reset { reply }).await?
+    }
+
+    /// Records the events `update` returns for the room's current state; an invalid event is
+    /// an `InvalidInput` error and records nothing.
+    pub async fn update(
+        &self,
+        update: impl FnOnce(&Room) -> Vec<RoomEvent> + Send + 'static,
+    ) -> io::Result<Room> {
+        self.request(|reply| RoomCommand::Update {
+            update: Box::new(update),
+            reply,
+        })
+        .await?
+    }
+
+    pub async fn join(&self) -> io::Result<(Room, broadcast::Receiver<String>)> {
+        self.request(|reply| RoomCommand::Join { reply }).await
+    }
+
+    fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> RoomCommand,
+    ) -> io::Result<T> {
+        let closed = || io::Error::new(io::ErrorKind::NotFound, "Room is closed");
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .await
+            .map_err(|_| closed())?;
+        response.await.map_err(|_| closed())
+    }
+}
+
+/// What happens to rooms that go without events for longer than the configured idle period.
+#[derive(Clone, Copy)]
+pub enum Expiry {
+    /// Unlisted and closed to moves, but kept with their history.
+    Archive,
+    Delete,
+}
+
+/// The actors of the rooms in use, spawned from the store on first access and dropped once
+/// they stop, and the summaries of every room for listing them.
+pub struct RoomRegistry {
+    handles: Mutex<HashMap<String, RoomHandle>>,
+    store: Arc<dyn Store>,
+    summaries: Summaries,
+}
+
+impl RoomRegistry {
+    /// Loads every room once to summarise it; rooms that do not load are left out.
+    pub fn new(store: Arc<dyn Store>) -> io::Result<Self> {
+        let summaries = store
+            .get_rooms()?
+            .into_iter()
+            .map(|(id, room)| (id, room.summary()))
+            .collect();
+        Ok(Self {
+            handles: Mutex::new(HashMap::new()),
+            store,
+            summaries: Arc::new(std::sync::Mutex::new(summaries)),
+        })
+    }
+
+    /// The summaries of every room, in no particular order.
+    pub fn summaries(&self) -> Vec<RoomSummary> {
+        self.summaries.lock().unwrap().values().cloned().collect()
+    }
+
+    /// The room's running actor, spawned from the store if needed. Unknown ids are a
+    /// `NotFound` error.
+    pub async fn get(&self, id: &str) -> io::Result<RoomHandle> {
+        let mut handles = self.handles.lock().await;
+        handles.retain(|_, handle| !handle.is_closed());
+        if let Some(handle) = handles.get(id) {
+            return Ok(handle.clone());
+        }
+        let room = self.store.get_room(id)?;
+        let handle = RoomHandle::spawn(room, self.store.clone(), self.summaries.clone());
+        handles.insert(id.to_string(), handle.clone());
+        Ok(handle)
+    }
+
+    /// Starts a new log for the room and spawns its actor. An id already in use is an
+    /// `AlreadyExists` error.
+    pub async fn create(&self, room: Room) -> io::Result<RoomHandle> {
+        let mut handles = self.handles.lock().await;
+        handles.retain(|_, handle| !handle.is_closed());
+        if handles.contains_key(&room.id) {
+            return Err(already_exists(&room.id));
+        }
+        self.start(&mut handles, room)
+    }
+
+    /// The room's running actor, spawned from the store or, for unknown ids, from the room
+    /// `make` returns. The lookup and the creation happen under one lock, so requests racing
+    /// to create a room all get the same one.
+    pub async fn get_or_create(
+        &self,
+        id: &str,
+        make: impl FnOnce() -> io::Result<Room>,
+    ) -> io::Result<RoomHandle> {
+        let mut handles = self.handles.lock().await;
+        handles.retain(|_, handle| !handle.is_closed());
+        if let Some(handle) = handles.get(id) {
+            return Ok(handle.clone());
+        }
+        match self.store.get_room(id) {
+            Ok(room) => {
+                let handle = RoomHandle::spawn(room, self.store.clone(), self.summaries.clone());
+                handles.insert(id.to_string(), handle.clone());
+                Ok(handle)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.start(&mut handles, make()?),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Logs the room's creation and spawns its actor. The log must be empty: appending the
+    /// first events to an existing room's log fails with `AlreadyExists`.
+    fn start(
+        &self,
+        handles: &mut HashMap<String, RoomHandle>,
+        room: Room,
+    ) -> io::Result<RoomHandle> {
+        let events: Vec<LoggedEvent> = room
+            .creation_events()
+            .into_iter()
+            .map(LoggedEvent::now)
+            .collect();
+        let mut created = Room::new(room.id.clone(), Board::new());
+        for logged in &events {
+            created.apply(&logged.event, logged.at)?;
+        }
+        self.store.save(&created, &events)?;
+        let id = created.id.clone();
+        self.summaries
+            .lock()
+            .unwrap()
+            .insert(id.clone(), created.summary());
+        let handle = RoomHandle::spawn(created, self.store.clone(), self.summaries.clone());
+        handles.insert(id, handle.clone());
+        Ok(handle)
+    }
+
+    /// Archives or deletes every room without events for longer than `idle`, returning how
+    /// many rooms expired.
+    pub async fn expire(&self, idle: Duration, expiry: Expiry) -> io::Result<usize> {
+        let cutoff = now().saturating_sub(idle.as_secs());
+        let mut expired = 0;
+        for room in self.summaries() {
+            let archived = room.archived && matches!(expiry, Expiry::Archive);
+            if room.last_activity > cutoff || archived {
+                continue;
+            }
+            // The room may have been played in since it was listed.
+            let handle = self.get(&room.id).await?;
+            match expiry {
+                Expiry::Archive => {
+                    let room = handle
+                        .update(move |room| match room.last_activity > cutoff {
+                            true => vec![],
+                            false => vec![RoomEvent::Archived],
+                        })
+                        .await?;
+                    expired += room.archived as usize;
+                }
+                Expiry::Delete => {
+                    if handle.get().await?.last_activity <= cutoff {
+                        self.delete(&room.id).await?;
+                        expired += 1;
+                    }
+                }
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Stops the room's actor and deletes it from the store.
+    pub async fn delete(&self, id: &str) -> io::Result<()> {
+        let mut handles = self.handles.lock().await;
+        if let Some(handle) = handles.remove(id) {
+            let _ = handle.commands.send(RoomCommand::Close).await;
+        }
+        self.summaries.lock().unwrap().remove(id);
+        self.store.delete_room(id)
+    }
+}
+
+fn already_exists(id: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("Room {id} already exists"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    async fn room() -> RoomHandle {
+        let registry = RoomRegistry::new(Arc::new(MemoryStore::default())).unwrap();
+        registry
+            .create(Room::new("room".to_string(), Board::new()))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn selecting_off_the_board_is_an_error() {
+        let handle = room().await;
+        for from in [Point::new(8, 0), Point::new(-1, 5)] {
+            let error = handle.select(from).await.err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        // The actor is still there to answer.
+        assert!(handle.select(Point::new(0, 5)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn concurrent_moves_are_played_one_at_a_time() {
+        let handle = room().await;
+        let moves = Engine::get_moves(&Board::new());
+        let (first, second) = (moves[0], moves[1]);
+        let play = |played: Move| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                let user = Some("player".to_string());
+                handle
+                    .play(played.from, played.to, Seat::Human, user, None)
+                    .await
+            })
+        };
+        let (a, b) = tokio::join!(play(first), play(second));
+        let played = [a.unwrap().unwrap().1, b.unwrap().unwrap().1];
+        // The second move finds Black to move, so only one of them is played.
+        assert_eq!(played.iter().flatten().count(), 1);
+        let room = handle.get().await.unwrap();
+        assert_eq!(room.history.len(), 1);
+        let played = room.history[0];
+        assert_eq!(
+            room.board,
+            Engine::make_move(Board::new(), played.from, played.to)
+        );
+    }
+}
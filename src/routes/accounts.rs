@@ -0,0 +1,236 @@
+use std::{io, sync::Arc};
+
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::{request::Parts, StatusCode},
+    response::Redirect,
+    routing::{get, post},
+    Form, Router,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use tracing::event;
+
+use crate::{
+    accounts::{Session, User},
+    store::now,
+    templates::{AccountFormTemplate, ProfileTemplate},
+    utility::random_id,
+    AppState,
+};
+
+use super::room_error;
+
+const SESSION_COOKIE: &str = "session";
+/// Attempts at a guest name nobody holds before giving up.
+const GUEST_NAME_ATTEMPTS: usize = 5;
+
+pub struct AccountsRouter {}
+
+impl AccountsRouter {
+    pub fn get() -> Router<Arc<AppState>> {
+        Router::new()
+            .route("/login", get(Self::login_page).post(Self::login))
+            .route("/register", get(Self::register_page).post(Self::register))
+            .route("/guest", post(Self::play_as_guest))
+            .route("/logout", post(Self::logout))
+            .route("/users/:name", get(Self::profile))
+    }
+
+    async fn login_page(user: CurrentUser) -> Response {
+        AccountFormTemplate::login(user.0).into_response()
+    }
+
+    async fn register_page(user: CurrentUser) -> Response {
+        AccountFormTemplate::register(user.0).into_response()
+    }
+
+    async fn login(
+        State(state): State<Arc<AppState>>,
+        user: CurrentUser,
+        Form(body): Form<CredentialsBody>,
+    ) -> Response {
+        let found = match state.store.find_user(body.name.trim()) {
+            Ok(found) => found,
+            Err(e) => return room_error(e),
+        };
+        let Some(account) = found.filter(|account| account.verify_password(&body.password)) else {
+            let form = AccountFormTemplate::login(user.0)
+                .with_error(&body.name, "Wrong name or password".to_string());
+            return (StatusCode::UNAUTHORIZED, form).into_response();
+        };
+        match CurrentUser::start_session(&state, &account) {
+            Ok(cookies) => (cookies, Redirect::to("/")).into_response(),
+            Err(e) => room_error(e),
+        }
+    }
+
+    /// Creates an account, or gives a signed-in guest a name and password so it keeps its
+    /// games.
+    async fn register(
+        State(state): State<Arc<AppState>>,
+        user: CurrentUser,
+        Form(body): Form<CredentialsBody>,
+    ) -> Response {
+        let mut account = match &user.0 {
+            Some(guest) if guest.is_guest() => guest.clone(),
+            _ => User::guest(),
+        };
+        let registered = account
+            .register(body.name.trim(), &body.password)
+            .and_then(|()| state.store.save_user(&account));
+        if let Err(e) = registered {
+            let status = match e.kind() {
+                io::ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+                io::ErrorKind::AlreadyExists => StatusCode::CONFLICT,
+                _ => return room_error(e),
+            };
+            let form = AccountFormTemplate::register(user.0).with_error(&body.name, e.to_string());
+            return (status, form).into_response();
+        }
+        match CurrentUser::start_session(&state, &account) {
+            Ok(cookies) => (cookies, Redirect::to("/")).into_response(),
+            Err(e) => room_error(e),
+        }
+    }
+
+    async fn play_as_guest(State(state): State<Arc<AppState>>, user: CurrentUser) -> Response {
+        match user.or_guest(&state) {
+            Ok((_, cookies)) => (cookies, Redirect::to("/")).into_response(),
+            Err(e) => room_error(e),
+        }
+    }
+
+    async fn logout(State(state): State<Arc<AppState>>, cookies: CookieJar) -> Response {
+        if let Some(token) = cookies.get(SESSION_COOKIE) {
+            if let Err(e) = state
+                .store
+                .delete_session(&Session::token_hash(token.value()))
+            {
+                return room_error(e);
+            }
+        }
+        let cookies = cookies.remove(Cookie::build(SESSION_COOKIE).path("/"));
+        (cookies, Redirect::to("/")).into_response()
+    }
+
+    /// The user's games, most recently played first. Private rooms are only listed to the
+    /// user themselves.
+    async fn profile(
+        Path(name): Path<String>,
+        State(state): State<Arc<AppState>>,
+        user: CurrentUser,
+    ) -> Response {
+        let profile = match state.store.find_user(&name) {
+            Ok(Some(profile)) => profile,
+            Ok(None) => return (StatusCode::NOT_FOUND, "User not found").into_response(),
+            Err(e) => return room_error(e),
+        };
+        let is_self = user.id() == Some(profile.id.as_str());
+        let mut games: Vec<_> = state
+            .rooms
+            .summaries()
+            .into_iter()
+            .filter(|room| room.public || is_self)
+            .filter(|room| {
+                [room.white_player.as_deref(), room.black_player.as_deref()]
+                    .contains(&Some(profile.id.as_str()))
+            })
+            .collect();
+        games.sort_by_key(|room| std::cmp::Reverse(room.last_activity));
+        ProfileTemplate::new(&profile, &games, &state, user.0).into_response()
+    }
+}
+
+/// The user signed in by the request's session cookie, if any.
+pub struct CurrentUser(pub Option<User>);
+
+impl CurrentUser {
+    pub fn id(&self) -> Option<&str> {
+        self.0.as_ref().map(|user| user.id.as_str())
+    }
+
+    /// The signed-in user, or else a new guest signed in by the returned cookies.
+    pub fn or_guest(self, state: &AppState) -> io::Result<(User, CookieJar)> {
+        if let Some(user) = self.0 {
+            return Ok((user, CookieJar::new()));
+        }
+        let mut guest = User::guest();
+        let cookies = CurrentUser::sign_in_guest(state, &mut guest)?;
+        Ok((guest, cookies))
+    }
+
+    /// Saves a guest made by `User::guest` and signs it in, returning the session cookie. Taken
+    /// names are drawn again, keeping the guest's id.
+    pub fn sign_in_guest(state: &AppState, guest: &mut User) -> io::Result<CookieJar> {
+        for _ in 0..GUEST_NAME_ATTEMPTS {
+            match state.store.save_user(guest) {
+                Ok(()) => return CurrentUser::start_session(state, guest),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    guest.name = User::guest().name;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::other("No free guest name"))
+    }
+
+    /// Signs the user in, returning the session cookie to set. The session itself ends after
+    /// `SESSION_LIFETIME` whatever the cookie's age.
+    pub fn start_session(state: &AppState, user: &User) -> io::Result<CookieJar> {
+        let token = random_id(Session::TOKEN_LENGTH);
+        let session = Session {
+            user_id: user.id.clone(),
+            created_at: now(),
+        };
+        state
+            .store
+            .create_session(&Session::token_hash(&token), &session)?;
+        let cookie = Cookie::build((SESSION_COOKIE, token))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .permanent();
+        Ok(CookieJar::new().add(cookie))
+    }
+
+    fn load(state: &AppState, token: &str) -> io::Result<Option<User>> {
+        match state.store.read_session(&Session::token_hash(token))? {
+            Some(session) if !session.is_expired() => state.store.get_user(&session.user_id),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for CurrentUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let cookies = CookieJar::from_headers(&parts.headers);
+        let Some(token) = cookies.get(SESSION_COOKIE) else {
+            return Ok(Self(None));
+        };
+        match CurrentUser::load(state, token.value()) {
+            Ok(user) => Ok(Self(user)),
+            Err(e) => {
+                event!(tracing::Level::ERROR, "Loading session failed: {e}");
+                Err(room_error(e))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CredentialsBody {
+    name: String,
+    password: String,
+}
@@ -0,0 +1,178 @@
+use std::{io, sync::Arc};
+
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{Engine, Notation, NotationStyle, TablebaseValue},
+    AppState,
+};
+
+use super::{open_room, read_room, room_error_status, CurrentUser, GamesRouter, RoomAccess};
+
+pub struct ApiRouter {}
+
+impl ApiRouter {
+    pub fn get() -> Router<Arc<AppState>> {
+        Router::new().nest(
+            "/games/:id",
+            Router::new()
+                .route("/moves", post(Self::make_move))
+                .route("/analysis", get(Self::get_analysis)),
+        )
+    }
+
+    async fn make_move(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+        Json(body): Json<MakeMoveBody>,
+    ) -> Response {
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return ApiError::room(e),
+        };
+        let Some((from, to)) = Notation::parse_move(&body.notation) else {
+            return ApiError::response(StatusCode::BAD_REQUEST, "Invalid move notation");
+        };
+        let played = GamesRouter::play_move(&state, &handle, from, to, user.0.as_ref()).await;
+        let (room, legal_move) = match played {
+            Ok(played) => played,
+            Err(e) => return ApiError::room(e),
+        };
+        let Some(legal_move) = legal_move else {
+            return ApiError::response(StatusCode::UNPROCESSABLE_ENTITY, "Illegal move");
+        };
+        let style = body.style.unwrap_or_default().into();
+        Json(MakeMoveResponse {
+            notation: Notation::format_move(&legal_move, style),
+            fen: room.board.to_fen(),
+            legal_moves: Engine::get_moves(&room.board)
+                .iter()
+                .map(|legal_move| Notation::format_move(legal_move, style))
+                .collect(),
+        })
+        .into_response()
+    }
+
+    async fn get_analysis(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        Query(query): Query<AnalysisQuery>,
+    ) -> Response {
+        let room = match read_room(&state, &id, &access).await {
+            Ok(room) => room,
+            Err(e) => return ApiError::room(e),
+        };
+        if !room.can_analyse() {
+            let message = "Analysis opens once the rated game is over";
+            return ApiError::response(StatusCode::FORBIDDEN, message);
+        }
+        let analysis = GamesRouter::analyse(&state, room.board, query.time_ms).await;
+        let style = query.style.unwrap_or_default().into();
+        let format = |legal_move| Notation::format_move(legal_move, style);
+        Json(AnalysisResponse {
+            score: analysis.score,
+            mate_in: analysis.mate_in(),
+            depth: analysis.depth,
+            nodes: analysis.nodes,
+            best_move: analysis.best_move().as_ref().map(format),
+            principal_variation: analysis.principal_variation.iter().map(format).collect(),
+            tablebase: analysis.tablebase.map(TablebaseResponse::from),
+        })
+        .into_response()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum Style {
+    #[default]
+    Numeric,
+    Algebraic,
+}
+
+impl From<Style> for NotationStyle {
+    fn from(value: Style) -> Self {
+        match value {
+            Style::Numeric => NotationStyle::Numeric,
+            Style::Algebraic => NotationStyle::Algebraic,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MakeMoveBody {
+    #[serde(rename = "move")]
+    notation: String,
+    style: Option<Style>,
+}
+
+#[derive(Serialize)]
+struct MakeMoveResponse {
+    notation: String,
+    fen: String,
+    legal_moves: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AnalysisQuery {
+    time_ms: Option<u64>,
+    style: Option<Style>,
+}
+
+#[derive(Serialize)]
+struct AnalysisResponse {
+    score: i32,
+    mate_in: Option<i32>,
+    depth: u8,
+    nodes: u64,
+    best_move: Option<String>,
+    principal_variation: Vec<String>,
+    tablebase: Option<TablebaseResponse>,
+}
+
+/// Exact result for the side to move, with the distance in plies.
+#[derive(Serialize)]
+struct TablebaseResponse {
+    result: &'static str,
+    distance: Option<u16>,
+}
+
+impl From<TablebaseValue> for TablebaseResponse {
+    fn from(value: TablebaseValue) -> Self {
+        let (result, distance) = match value {
+            TablebaseValue::Win(distance) => ("win", Some(distance)),
+            TablebaseValue::Loss(distance) => ("loss", Some(distance)),
+            TablebaseValue::Draw => ("draw", None),
+        };
+        Self { result, distance }
+    }
+}
+
+#[derive(Serialize)]
+struct ApiError {
+    error: String,
+}
+
+impl ApiError {
+    fn response(status: StatusCode, error: &str) -> Response {
+        let error = ApiError {
+            error: error.to_string(),
+        };
+        (status, Json(error)).into_response()
+    }
+
+    /// Responds to a failed room lookup or move with the status pages use for it.
+    fn room(e: io::Error) -> Response {
+        ApiError::response(room_error_status(&e), &e.to_string())
+    }
+}
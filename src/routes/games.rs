@@ -1,16 +1,38 @@
-use std::sync::Arc;
+use std::{io, sync::Arc, time::Duration};
 
-use askama::Template;
-use askama_axum::IntoResponse;
+use askama_axum::{IntoResponse, Response};
 use axum::{
-    extract::{Path, State},
-    routing::post,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    routing::{get, post},
     Form, Router,
 };
+use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 use tracing::event;
 
-use crate::{engine::Engine, store::Store, templates::BoardTemplate, utility::Point, AppState};
+use crate::{
+    accounts::User,
+    engine::{
+        Ai, Analysis, Board, HubEngine, Mcts, Move, PdnGame, Player, Playouts, Search,
+        SearchLimits, Turn,
+    },
+    room_actor::RoomHandle,
+    templates::{AnalysisTemplate, BoardTemplate},
+    utility::Point,
+    AppState, Room, Seat,
+};
+
+use super::{open_room, read_room, room_error, CurrentUser, RoomAccess};
+
+const DEFAULT_ANALYSIS_TIME: Duration = Duration::from_secs(1);
+const MAX_ANALYSIS_TIME: Duration = Duration::from_secs(5);
+const ANALYSIS_TABLE_BITS: u32 = 18;
+const HINT_DEPTH: u8 = 4;
+const HINT_TIME: Duration = Duration::from_millis(300);
+const HINT_TABLE_BITS: u32 = 14;
+const AI_DEPTH: u8 = 8;
+const AI_TIME: Duration = Duration::from_secs(1);
 
 pub struct GamesRouter {}
 
@@ -20,38 +42,298 @@ impl GamesRouter {
             "/:id",
             Router::new()
                 .route("/moves", post(Self::get_legal_moves))
-                .route("/make_move", post(Self::make_move)),
+                .route("/make_move", post(Self::make_move))
+                .route("/pdn", get(Self::export_pdn))
+                .route("/analysis", get(Self::get_analysis))
+                .route("/hint", post(Self::get_hint)),
         )
     }
 
     async fn get_legal_moves(
         Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
         Form(body): Form<GetLegalMovesBody>,
-    ) -> impl IntoResponse {
-        let room = Store::get_room(&id).unwrap();
+    ) -> Response {
         let from = Point::new(body.x, body.y);
-        let board = Engine::with_legal_moves(room.board, from);
-        BoardTemplate::new(&board, room.id, Some(from))
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
+        };
+        match handle.select(from).await {
+            Ok((room, board)) => BoardTemplate::new(&board, room.id.clone(), Some(from))
+                .with_room(&room)
+                .into_response(),
+            Err(e) => room_error(e),
+        }
     }
 
     async fn make_move(
         Path(id): Path<String>,
         State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
         Form(body): Form<MakeMoveBody>,
-    ) -> impl IntoResponse {
-        let room = Store::get_room(&id).unwrap();
+    ) -> Response {
         let from = Point::new(body.selected_x, body.selected_y);
         let to: Point = Point::new(body.x, body.y);
-        let board = Engine::make_move(room.board, from, to);
-        Store::update_board(room.id.clone(), board.clone()).unwrap();
-        let board = BoardTemplate::new(&board, room.id, None);
-        let senders = state.rooms.lock().await;
-        let sender = senders.get(&id).unwrap();
-        if let Err(e) = sender.send(board.render().unwrap()) {
-            event!(tracing::Level::ERROR, "Broadcasting failed: {e}");
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
+        };
+        // Moving takes the side's seat, so players are known by at least a guest account. The
+        // guest is only saved and signed in once its move is accepted.
+        let signed_in = user.0.is_some();
+        let mut user = user.0.unwrap_or_else(User::guest);
+        let (room, legal_move) = match Self::play_move(&state, &handle, from, to, Some(&user)).await
+        {
+            Ok(played) => played,
+            Err(e) => return room_error(e),
+        };
+        let session = if signed_in || legal_move.is_none() {
+            CookieJar::new()
+        } else {
+            match CurrentUser::sign_in_guest(&state, &mut user) {
+                Ok(session) => session,
+                Err(e) => return room_error(e),
+            }
+        };
+        let board = BoardTemplate::new(&room.board, room.id.clone(), None).with_room(&room);
+        (session, board).into_response()
+    }
+
+    /// Plays a human move for the user, then lets the computer answer if it holds the other
+    /// seat. Only registered users may take a free seat of a rated room.
+    pub async fn play_move(
+        state: &Arc<AppState>,
+        handle: &RoomHandle,
+        from: Point,
+        to: Point,
+        user: Option<&User>,
+    ) -> io::Result<(Room, Option<Move>)> {
+        if user.is_none_or(User::is_guest) {
+            let room = handle.get().await?;
+            if room.rated && room.player(room.board.turn).is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "Register to play rated games",
+                ));
+            }
+        }
+        let user = user.map(|user| user.id.clone());
+        let (room, legal_move) = handle.play(from, to, Seat::Human, user, None).await?;
+        if legal_move.is_some() {
+            Self::schedule_ai_move(state.clone(), &room);
+        }
+        Ok((room, legal_move))
+    }
+
+    /// Starts a background task playing the computer's moves while it is to move.
+    pub fn schedule_ai_move(state: Arc<AppState>, room: &Room) {
+        if room.is_computer_to_move() {
+            tokio::spawn(Self::play_ai_moves(state, room.id.clone()));
+        }
+    }
+
+    async fn play_ai_moves(state: Arc<AppState>, id: String) {
+        // Players are kept across moves so external engines are spawned once per turn sequence.
+        let mut players: [Option<(Seat, Box<dyn Player + Send>)>; 2] = [None, None];
+        let Ok(handle) = state.room(&id).await else {
+            return;
+        };
+        loop {
+            let Ok(room) = handle.get().await else {
+                return;
+            };
+            if !room.is_computer_to_move() {
+                return;
+            }
+            let turn = room.board.turn;
+            let seat = room.seat(turn);
+            let hash = room.board.hash();
+            let board = room.board.clone();
+            let player = players[turn as usize]
+                .take()
+                .filter(|(player_seat, _)| *player_seat == seat)
+                .map(|(_, player)| player);
+            let task_state = state.clone();
+            let task = move || {
+                let mut player = match player {
+                    Some(player) => player,
+                    None => match Self::create_player(&task_state, seat) {
+                        Ok(player) => player,
+                        Err(e) => return (None, Err(e)),
+                    },
+                };
+                let chosen = player.choose_move(&board);
+                (Some(player), chosen)
+            };
+            // External engines mostly wait on their process, which would idle a search thread.
+            let (player, chosen) = match seat {
+                Seat::External => tokio::task::spawn_blocking(task).await.unwrap(),
+                _ => state.spawn_search(task).await,
+            };
+            players[turn as usize] = player.map(|player| (seat, player));
+            let ai_move = match chosen {
+                Ok(Some(ai_move)) => ai_move,
+                Ok(None) => return,
+                Err(e) => {
+                    event!(
+                        tracing::Level::ERROR,
+                        "{turn} seat of room {id} failed: {e}"
+                    );
+                    return;
+                }
+            };
+            // The room may have been reset or changed while the computer was thinking.
+            let played = handle
+                .play(ai_move.from, ai_move.to, seat, None, Some(hash))
+                .await;
+            if !matches!(played, Ok((_, Some(_)))) {
+                return;
+            }
+        }
+    }
+
+    fn create_player(state: &AppState, seat: Seat) -> io::Result<Box<dyn Player + Send>> {
+        let limits = SearchLimits {
+            max_depth: AI_DEPTH,
+            time: AI_TIME,
+        };
+        match seat {
+            Seat::Human | Seat::Dxp => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Only automatic seats have a player",
+            )),
+            Seat::Computer => Ok(Box::new(
+                Ai::new(limits)
+                    .with_book(state.book.clone())
+                    .with_tablebase(state.tablebase.clone())
+                    .with_weights(state.weights)
+                    .with_threads(state.search_threads),
+            )),
+            Seat::Mcts => Ok(Box::new(Mcts::new(state.mcts_simulations, Playouts::Heavy))),
+            Seat::Network => {
+                let network = state.network.clone().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "No evaluation network is loaded")
+                })?;
+                Ok(Box::new(
+                    Ai::new(limits)
+                        .with_book(state.book.clone())
+                        .with_tablebase(state.tablebase.clone())
+                        .with_network(Some(network))
+                        .with_threads(state.search_threads),
+                ))
+            }
+            Seat::External => {
+                let command = state.external_engine.as_deref().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "No external engine is configured")
+                })?;
+                Ok(Box::new(HubEngine::spawn(command, limits)?))
+            }
+        }
+    }
+
+    async fn export_pdn(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+    ) -> Response {
+        let room = match read_room(&state, &id, &access).await {
+            Ok(room) => room,
+            Err(e) => return room_error(e),
+        };
+        let start = match room.history.is_empty() {
+            true => &room.board,
+            false => &room.start,
+        };
+        let tags = vec![
+            ("Event".to_string(), format!("Room {id}")),
+            ("White".to_string(), state.player_name(&room, Turn::White)),
+            ("Black".to_string(), state.player_name(&room, Turn::Black)),
+        ];
+        let game = PdnGame::from_moves(tags, start, &room.history, room.outcome());
+        let disposition = format!("attachment; filename=\"room-{id}.pdn\"");
+        (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    "text/plain; charset=utf-8".to_string(),
+                ),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            game.to_string(),
+        )
+            .into_response()
+    }
+
+    async fn get_analysis(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        Query(query): Query<AnalysisQuery>,
+    ) -> Response {
+        let room = match read_room(&state, &id, &access).await {
+            Ok(room) => room,
+            Err(e) => return room_error(e),
+        };
+        if !room.can_analyse() {
+            let message = "Analysis opens once the rated game is over";
+            return (StatusCode::FORBIDDEN, message).into_response();
         }
-        board
+        let analysis = Self::analyse(&state, room.board, query.time_ms).await;
+        AnalysisTemplate::new(id, &analysis).into_response()
     }
+
+    async fn get_hint(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+    ) -> Response {
+        let room = match read_room(&state, &id, &access).await {
+            Ok(room) => room,
+            Err(e) => return room_error(e),
+        };
+        if !room.can_hint() {
+            return (StatusCode::FORBIDDEN, "Hints are disabled in this room").into_response();
+        }
+        let board = room.board.clone();
+        let limits = SearchLimits {
+            max_depth: HINT_DEPTH,
+            time: HINT_TIME,
+        };
+        let mut search = Search::new(HINT_TABLE_BITS)
+            .with_tablebase(state.tablebase.clone())
+            .with_weights(state.weights)
+            .with_threads(state.search_threads);
+        let analysis = state.spawn_search(move || search.run(&board, limits)).await;
+        let board = BoardTemplate::new(&room.board, room.id.clone(), None).with_room(&room);
+        match analysis.best_move() {
+            Some(hint) => board.with_hint(hint).into_response(),
+            None => board.into_response(),
+        }
+    }
+
+    /// Searches the position on the search pool for at most `MAX_ANALYSIS_TIME`.
+    pub async fn analyse(state: &AppState, board: Board, time_ms: Option<u64>) -> Analysis {
+        let time = time_ms
+            .map_or(DEFAULT_ANALYSIS_TIME, Duration::from_millis)
+            .min(MAX_ANALYSIS_TIME);
+        let limits = SearchLimits {
+            time,
+            ..SearchLimits::default()
+        };
+        let mut search = Search::new(ANALYSIS_TABLE_BITS)
+            .with_tablebase(state.tablebase.clone())
+            .with_weights(state.weights)
+            .with_threads(state.search_threads);
+        state.spawn_search(move || search.run(&board, limits)).await
+    }
+}
+
+#[derive(Deserialize)]
+struct AnalysisQuery {
+    time_ms: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use askama_axum::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode, routing::post, Form, Router};
+use serde::Deserialize;
+
+use crate::{templates::SeekTemplate, AppState, TimeControl, Variant};
+
+use super::{room_error, CurrentUser};
+
+pub struct MatchmakingRouter {}
+
+impl MatchmakingRouter {
+    pub fn get() -> Router<Arc<AppState>> {
+        Router::new().route("/seek", post(Self::seek))
+    }
+
+    /// Starts seeking an opponent, signed in as a guest if needed. The seek itself lives as
+    /// long as the returned fragment's websocket.
+    async fn seek(
+        State(state): State<Arc<AppState>>,
+        user: CurrentUser,
+        Form(body): Form<SeekBody>,
+    ) -> Response {
+        let time_control = match TimeControl::from_form(&body.time_control) {
+            Ok(time_control) => time_control,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let (user, session) = match user.or_guest(&state) {
+            Ok(signed_in) => signed_in,
+            Err(e) => return room_error(e),
+        };
+        match state.store.rating(&user.id, body.variant) {
+            Ok(rating) => {
+                let seek = SeekTemplate::new(body.variant, time_control, rating);
+                (session, seek).into_response()
+            }
+            Err(e) => room_error(e),
+        }
+    }
+}
+
+/// The game sought, from the index page's form or the seek websocket's query.
+#[derive(Deserialize)]
+pub struct SeekBody {
+    pub variant: Variant,
+    /// Empty for untimed games, else e.g. `10+5`.
+    pub time_control: String,
+}
@@ -1,7 +1,162 @@
+use std::{convert::Infallible, io};
+
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+
+use crate::{room_actor::RoomHandle, AppState, Room};
+
+mod accounts;
+mod api;
 mod games;
+mod matchmaking;
 mod rooms;
 mod ws;
 
+pub use accounts::{AccountsRouter, CurrentUser};
+pub use api::ApiRouter;
 pub use games::GamesRouter;
+pub use matchmaking::MatchmakingRouter;
 pub use rooms::RoomsRouter;
 pub use ws::WSRouter;
+
+/// Responds to a failed room lookup or update with its message and `room_error_status`.
+fn room_error(e: io::Error) -> Response {
+    (room_error_status(&e), e.to_string()).into_response()
+}
+
+/// The status for a failed room lookup or update: unknown rooms are a 404, private rooms
+/// without their invite token a 403, changes the room does not allow a 400, ids already taken
+/// a 409, anything else a 500.
+fn room_error_status(e: &io::Error) -> StatusCode {
+    match e.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        io::ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+        io::ErrorKind::AlreadyExists => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The room's actor, if the request may use the room.
+async fn open_room(state: &AppState, id: &str, access: &RoomAccess) -> io::Result<RoomHandle> {
+    let handle = state.room(id).await?;
+    access.check(&handle.get().await?)?;
+    Ok(handle)
+}
+
+/// A snapshot of the room's current state, if the request may see it.
+async fn read_room(state: &AppState, id: &str, access: &RoomAccess) -> io::Result<Room> {
+    let room = state.get_room(id).await?;
+    access.check(&room)?;
+    Ok(room)
+}
+
+/// The tokens a request carries for private rooms: an `invite` query parameter, or the
+/// cookies set when the room was created or its invite link opened, which the room page's
+/// requests and websocket then send along.
+pub struct RoomAccess {
+    invite: Option<String>,
+    cookies: CookieJar,
+}
+
+impl RoomAccess {
+    fn invite_cookie(id: &str) -> String {
+        format!("invite-{id}")
+    }
+
+    fn creator_cookie(id: &str) -> String {
+        format!("creator-{id}")
+    }
+
+    fn cookie(&self, name: String) -> Option<&str> {
+        self.cookies.get(&name).map(Cookie::value)
+    }
+
+    pub fn admits(&self, room: &Room) -> bool {
+        let Some(invite) = &room.invite else {
+            return true;
+        };
+        let token = self
+            .invite
+            .as_deref()
+            .or_else(|| self.cookie(RoomAccess::invite_cookie(&room.id)));
+        token.is_some_and(|token| tokens_match(token, &invite.token)) || self.is_creator(room)
+    }
+
+    pub fn is_creator(&self, room: &Room) -> bool {
+        room.invite.as_ref().is_some_and(|invite| {
+            self.cookie(RoomAccess::creator_cookie(&room.id))
+                .is_some_and(|token| tokens_match(token, &invite.creator))
+        })
+    }
+
+    fn check(&self, room: &Room) -> io::Result<()> {
+        match self.admits(room) {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "This room is private; open it from its invite link",
+            )),
+        }
+    }
+
+    /// The cookies that keep the room's current tokens for this client, adding the creator's
+    /// when `creator` holds.
+    pub fn remember(room: &Room, creator: bool) -> CookieJar {
+        let Some(invite) = &room.invite else {
+            return CookieJar::new();
+        };
+        let cookie = |name: String, value: &str| {
+            Cookie::build((name, value.to_string()))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .permanent()
+                .build()
+        };
+        let jar = CookieJar::new().add(cookie(RoomAccess::invite_cookie(&room.id), &invite.token));
+        match creator {
+            true => jar.add(cookie(
+                RoomAccess::creator_cookie(&room.id),
+                &invite.creator,
+            )),
+            false => jar,
+        }
+    }
+}
+
+/// Compares a token in constant time, so response times do not reveal how much of it is
+/// right.
+fn tokens_match(token: &str, expected: &str) -> bool {
+    token.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+#[derive(Deserialize)]
+struct InviteQuery {
+    invite: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RoomAccess {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let invite = Query::<InviteQuery>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|query| query.0.invite);
+        Ok(Self {
+            invite,
+            cookies: CookieJar::from_headers(&parts.headers),
+        })
+    }
+}
@@ -1,62 +1,407 @@
-use askama_axum::IntoResponse;
+use askama_axum::{IntoResponse, Response};
 use axum::{
-    extract::Path,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Redirect,
     routing::{get, post},
-    Router,
+    Form, Router,
 };
+use serde::Deserialize;
 use std::{io, sync::Arc};
 
 use crate::{
-    engine::Board,
-    store::Store,
-    templates::{BoardTemplate, RoomTemplate, Side},
-    AppState, Room,
+    engine::{Board, PdnGame, Turn},
+    room_actor::RoomHandle,
+    store::RoomEvent,
+    templates::RoomTemplate,
+    utility::random_id,
+    AppState, Invite, Room, Seat, TimeControl, Variant,
 };
 
+const MAX_NAME_LENGTH: usize = 60;
+
+use super::{open_room, read_room, room_error, CurrentUser, GamesRouter, RoomAccess};
+
 pub struct RoomsRouter {}
 
 impl RoomsRouter {
     pub fn get() -> Router<Arc<AppState>> {
-        Router::new().nest(
-            "/:id",
-            Router::new()
-                .route("/", get(Self::get_room))
-                .route("/reset", post(Self::reset_room)),
-        )
-    }
-
-    pub async fn get_room(Path(id): Path<String>) -> impl IntoResponse {
-        let room = Store::get_room(&id).unwrap_or_else(|e| {
-            if e.kind() == io::ErrorKind::NotFound {
-                let new_room = Room {
-                    id: id.clone(),
-                    board: Board::new(),
-                };
-                Store::insert_room(id.clone(), new_room.clone()).unwrap();
-                new_room
-            } else {
-                panic!("{:?}", e);
-            }
-        });
-        RoomTemplate {
-            title: format!("Room {}", id.clone()),
-            board: BoardTemplate::new(&room.board, id.clone(), None),
-            id: id.clone(),
-            side: Side::White,
+        Router::new()
+            .route("/", post(Self::create_room))
+            .route("/import", post(Self::import_pdn))
+            .nest(
+                "/:id",
+                Router::new()
+                    .route("/", get(Self::get_room))
+                    .route("/reset", post(Self::reset_room))
+                    .route("/hints", post(Self::toggle_hints))
+                    .route("/seats", post(Self::set_seats))
+                    .route("/draw", post(Self::offer_draw))
+                    .route("/resign", post(Self::resign))
+                    .route("/invite", post(Self::regenerate_invite))
+                    .route("/delete", post(Self::delete_room)),
+            )
+    }
+
+    /// Creates a room from the index page's form with the creator, signed in as a guest if
+    /// needed, in their chosen seat. Rated rooms are for registered users against a human.
+    pub async fn create_room(
+        State(state): State<Arc<AppState>>,
+        user: CurrentUser,
+        Form(body): Form<CreateRoomBody>,
+    ) -> Response {
+        let (creator, session) = match user.or_guest(&state) {
+            Ok(signed_in) => signed_in,
+            Err(e) => return room_error(e),
+        };
+        if body.rated && (creator.is_guest() || body.opponent != Seat::Human) {
+            let message = "Rated games are between two registered players";
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+        let time_control = match TimeControl::from_form(&body.time_control) {
+            Ok(time_control) => time_control,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let side = match body.colour {
+            ColourPreference::White => Turn::White,
+            ColourPreference::Black => Turn::Black,
+            ColourPreference::Random if rand::random() => Turn::White,
+            ColourPreference::Random => Turn::Black,
+        };
+        let id = random_id(8);
+        let mut room = Room {
+            name: body.name.trim().chars().take(MAX_NAME_LENGTH).collect(),
+            variant: body.variant,
+            time_control,
+            rated: body.rated,
+            public: body.public,
+            invite: (!body.public).then(Invite::generate),
+            ..Room::new(id.clone(), Board::new())
+        };
+        match side {
+            Turn::White => {
+                room.white_player = Some(creator.id);
+                room.black = body.opponent;
+            }
+            Turn::Black => {
+                room.black_player = Some(creator.id);
+                room.white = body.opponent;
+            }
+        }
+        let created = match state.create_room(room).await {
+            Ok(handle) => handle.get().await,
+            Err(e) => Err(e),
+        };
+        match created {
+            Ok(room) => {
+                GamesRouter::schedule_ai_move(state.clone(), &room);
+                let invite = RoomAccess::remember(&room, true);
+                (session, invite, Redirect::to(&format!("/rooms/{id}"))).into_response()
+            }
+            Err(e) => room_error(e),
         }
     }
 
-    pub async fn reset_room(Path(id): Path<String>) -> impl IntoResponse {
-        let new_room = Room {
-            board: Board::new(),
-            id: id.clone(),
+    pub async fn get_room(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+        Query(query): Query<GetRoomQuery>,
+    ) -> Response {
+        // Visiting an unknown room's address creates it, from the `fen` position if given.
+        let make = || {
+            let board = match query.fen.as_deref().map(Board::from_fen) {
+                Some(Ok(board)) => board,
+                Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+                None => Board::new(),
+            };
+            Ok(Room::new(id.clone(), board))
+        };
+        let opened = match state.rooms.get_or_create(&id, make).await {
+            Ok(handle) => handle.get().await,
+            Err(e) => Err(e),
+        };
+        let room = match opened.and_then(|room| access.check(&room).map(|()| room)) {
+            Ok(room) => room,
+            Err(e) => return room_error(e),
+        };
+        // Remembers the token of an invite link for the room's other requests.
+        let cookies = RoomAccess::remember(&room, false);
+        (cookies, Self::page(&room, &state, &access, user)).into_response()
+    }
+
+    pub async fn reset_room(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+    ) -> Response {
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
+        };
+        // Resetting a rated room starts its players' next rated game.
+        match handle.get().await {
+            Ok(room) if room.rated && !room.is_player(user.id()) => {
+                let message = "Only the players may reset a rated game";
+                return (StatusCode::FORBIDDEN, message).into_response();
+            }
+            Ok(room) => {
+                if let Err(e) = Self::check_manager(&room, &access, &user) {
+                    return room_error(e);
+                }
+            }
+            Err(e) => return room_error(e),
+        }
+        match handle.reset().await {
+            Ok(new_room) => {
+                GamesRouter::schedule_ai_move(state.clone(), &new_room);
+                Self::page(&new_room, &state, &access, user)
+            }
+            Err(e) => room_error(e),
+        }
+    }
+
+    pub async fn toggle_hints(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+    ) -> Response {
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
+        };
+        if let Err(e) = Self::check_manager_of(&handle, &access, &user).await {
+            return room_error(e);
+        }
+        let toggled = handle
+            .update(|room| vec![RoomEvent::HintsChanged { hints: !room.hints }])
+            .await;
+        match toggled {
+            Ok(room) => Self::page(&room, &state, &access, user),
+            Err(e) => room_error(e),
+        }
+    }
+
+    pub async fn set_seats(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+        Form(body): Form<SetSeatsBody>,
+    ) -> Response {
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
         };
-        Store::insert_room(id.clone(), new_room.clone()).unwrap();
-        RoomTemplate {
+        if let Err(e) = Self::check_manager_of(&handle, &access, &user).await {
+            return room_error(e);
+        }
+        let updated = handle
+            .update(move |room| {
+                [(Turn::White, body.white), (Turn::Black, body.black)]
+                    .into_iter()
+                    .filter(|(turn, seat)| room.seat(*turn) != *seat)
+                    .map(|(turn, seat)| RoomEvent::SeatClaimed { turn, seat })
+                    .collect()
+            })
+            .await;
+        match updated {
+            Ok(room) => {
+                GamesRouter::schedule_ai_move(state.clone(), &room);
+                Self::page(&room, &state, &access, user)
+            }
+            Err(e) => room_error(e),
+        }
+    }
+
+    /// Offers a draw for the human side, or agrees to the other side's offer.
+    pub async fn offer_draw(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+    ) -> Response {
+        let event = |turn| RoomEvent::DrawOffered { turn };
+        Self::record_for_human(state, &id, access, user, event).await
+    }
+
+    pub async fn resign(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+    ) -> Response {
+        let event = |turn| RoomEvent::Resigned { turn };
+        Self::record_for_human(state, &id, access, user, event).await
+    }
+
+    /// Records the event for the side the user plays, if any.
+    async fn record_for_human(
+        state: Arc<AppState>,
+        id: &str,
+        access: RoomAccess,
+        user: CurrentUser,
+        event: impl FnOnce(Turn) -> RoomEvent + Send + 'static,
+    ) -> Response {
+        let handle = match open_room(&state, id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
+        };
+        let user_id = user.id().map(str::to_string);
+        let updated = handle
+            .update(move |room| {
+                let turn = room.human_turn(user_id.as_deref());
+                turn.map(event).into_iter().collect()
+            })
+            .await;
+        match updated {
+            Ok(room) => Self::page(&room, &state, &access, user),
+            Err(e) => room_error(e),
+        }
+    }
+
+    /// Replaces a private room's invite token, for its creator only.
+    pub async fn regenerate_invite(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+    ) -> Response {
+        let handle = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle,
+            Err(e) => return room_error(e),
+        };
+        match handle.get().await {
+            Ok(room) if access.is_creator(&room) => {}
+            Ok(_) => {
+                let message = "Only the room's creator may regenerate its invite link";
+                return (StatusCode::FORBIDDEN, message).into_response();
+            }
+            Err(e) => return room_error(e),
+        }
+        let token = random_id(Invite::TOKEN_LENGTH);
+        match handle
+            .update(|_| vec![RoomEvent::InviteRegenerated { token }])
+            .await
+        {
+            Ok(room) => {
+                let cookies = RoomAccess::remember(&room, true);
+                (cookies, Self::page(&room, &state, &access, user)).into_response()
+            }
+            Err(e) => room_error(e),
+        }
+    }
+
+    /// Deletes the room, disconnecting its clients, and goes back to the room list. Only its
+    /// creator and players may, and never during a rated game, whose result must be recorded.
+    pub async fn delete_room(
+        Path(id): Path<String>,
+        State(state): State<Arc<AppState>>,
+        access: RoomAccess,
+        user: CurrentUser,
+    ) -> Response {
+        let room = match read_room(&state, &id, &access).await {
+            Ok(room) => room,
+            Err(e) => return room_error(e),
+        };
+        if !access.is_creator(&room) && !room.is_player(user.id()) {
+            let message = "Only the players and the room's creator may delete it";
+            return (StatusCode::FORBIDDEN, message).into_response();
+        }
+        if room.rated && room.outcome().is_none() {
+            let message = "A rated game is deleted once it is over";
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+        match state.rooms.delete(&id).await {
+            Ok(()) => Redirect::to("/").into_response(),
+            Err(e) => room_error(e),
+        }
+    }
+
+    /// Refuses users who may not change the room's settings once its seats are taken.
+    fn check_manager(room: &Room, access: &RoomAccess, user: &CurrentUser) -> io::Result<()> {
+        match room.may_manage(user.id(), access.is_creator(room)) {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Only the players and the room's creator may change it",
+            )),
+        }
+    }
+
+    async fn check_manager_of(
+        handle: &RoomHandle,
+        access: &RoomAccess,
+        user: &CurrentUser,
+    ) -> io::Result<()> {
+        Self::check_manager(&handle.get().await?, access, user)
+    }
+
+    fn page(room: &Room, state: &AppState, access: &RoomAccess, user: CurrentUser) -> Response {
+        RoomTemplate::new(room, state, user.0)
+            .with_creator(access.is_creator(room))
+            .into_response()
+    }
+
+    pub async fn import_pdn(
+        State(state): State<Arc<AppState>>,
+        Form(body): Form<ImportPdnBody>,
+    ) -> Response {
+        let replay = match PdnGame::parse(&body.pdn).and_then(|game| game.replay()) {
+            Ok(replay) => replay,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+        let id = random_id(8);
+        let room = Room {
             id: id.clone(),
-            title: id.clone(),
-            board: BoardTemplate::new(&new_room.board, id, None),
-            side: Side::White,
+            board: replay.board,
+            start: replay.start,
+            history: replay.moves,
+            ..Room::new(id.clone(), Board::new())
+        };
+        if let Err(e) = state.create_room(room).await {
+            return room_error(e);
         }
+        Redirect::to(&format!("/rooms/{id}")).into_response()
     }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColourPreference {
+    White,
+    Black,
+    Random,
+}
+
+#[derive(Deserialize)]
+pub struct CreateRoomBody {
+    name: String,
+    variant: Variant,
+    /// Empty for untimed rooms, else e.g. `10+5`.
+    time_control: String,
+    colour: ColourPreference,
+    /// The seat of the side the creator does not play.
+    opponent: Seat,
+    public: bool,
+    rated: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ImportPdnBody {
+    pdn: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetSeatsBody {
+    white: Seat,
+    black: Seat,
+}
+
+#[derive(Deserialize)]
+pub struct GetRoomQuery {
+    fen: Option<String>,
+}
@@ -1,44 +1,124 @@
 use std::sync::Arc;
 
-use askama_axum::IntoResponse;
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
 use axum::{
     extract::{
         ws::{self},
-        Path, State, WebSocketUpgrade,
+        Path, Query, State, WebSocketUpgrade,
     },
+    http::StatusCode,
     routing::get,
     Router,
 };
+use tokio::sync::{broadcast, oneshot};
 use tracing::event;
 
-use crate::AppState;
+use crate::{matchmaking::Seek, templates::MatchedTemplate, AppState, TimeControl};
+
+use super::{matchmaking::SeekBody, open_room, room_error, CurrentUser, RoomAccess};
 
 pub struct WSRouter {}
 
 impl WSRouter {
     pub fn get() -> Router<Arc<AppState>> {
-        Router::new().nest("/", Router::new().route("/rooms/:id", get(Self::handle_ws)))
+        Router::new().nest(
+            "/",
+            Router::new()
+                .route("/rooms/:id", get(Self::handle_ws))
+                .route("/seek", get(Self::handle_seek)),
+        )
     }
 
     async fn handle_ws(
         ws: WebSocketUpgrade,
         Path(id): Path<String>,
         State(state): State<Arc<AppState>>,
-    ) -> impl IntoResponse {
-        ws.on_upgrade(move |socket| Self::handle_socket(socket, state, id))
+        access: RoomAccess,
+    ) -> Response {
+        let joined = match open_room(&state, &id, &access).await {
+            Ok(handle) => handle.join().await,
+            Err(e) => Err(e),
+        };
+        match joined {
+            Ok((_, rx)) => ws.on_upgrade(move |socket| Self::handle_socket(socket, rx)),
+            Err(e) => room_error(e),
+        }
+    }
+
+    /// Forwards the room's board updates until the room closes the channel, as when it is
+    /// deleted or its invite token replaced, then closes the socket. A client that closes the
+    /// socket or stops accepting messages is dropped, along with its subscription, so the room
+    /// can go idle.
+    async fn handle_socket(mut socket: ws::WebSocket, mut rx: broadcast::Receiver<String>) {
+        loop {
+            let message = tokio::select! {
+                message = rx.recv() => match message {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                received = socket.recv() => match received {
+                    Some(Ok(ws::Message::Close(_)) | Err(_)) | None => return,
+                    Some(Ok(_)) => continue,
+                },
+            };
+            if socket.send(ws::Message::Text(message)).await.is_err() {
+                return;
+            }
+        }
+        let _ = socket.send(ws::Message::Close(None)).await;
+    }
+
+    /// Seeks a game for the signed-in user while the socket is open.
+    async fn handle_seek(
+        ws: WebSocketUpgrade,
+        State(state): State<Arc<AppState>>,
+        user: CurrentUser,
+        Query(query): Query<SeekBody>,
+    ) -> Response {
+        let Some(user) = user.0 else {
+            return (StatusCode::UNAUTHORIZED, "Sign in to seek a game").into_response();
+        };
+        let time_control = match TimeControl::from_form(&query.time_control) {
+            Ok(time_control) => time_control,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let rating = match state.store.rating(&user.id, query.variant) {
+            Ok(rating) => rating,
+            Err(e) => return room_error(e),
+        };
+        let (seek, matched) = Seek::new(user, rating, query.variant, time_control);
+        ws.on_upgrade(move |socket| Self::wait_for_match(socket, state, seek, matched))
+    }
+
+    /// Sends the client to its game once the seek is paired. Closing the socket withdraws
+    /// the seek; a seek replaced from another page or whose room failed ends it instead.
+    async fn wait_for_match(
+        mut socket: ws::WebSocket,
+        state: Arc<AppState>,
+        seek: Seek,
+        matched: oneshot::Receiver<String>,
+    ) {
+        state.matchmaker.join(seek);
+        let room = tokio::select! {
+            room = matched => room.ok(),
+            () = Self::closed(&mut socket) => return,
+        };
+        let message = MatchedTemplate { room }.render().unwrap();
+        if let Err(e) = socket.send(ws::Message::Text(message)).await {
+            event!(
+                tracing::Level::ERROR,
+                "Error sending message by websocket: {e}"
+            );
+        }
     }
 
-    async fn handle_socket(mut socket: ws::WebSocket, state: Arc<AppState>, id: String) {
-        let senders = state.rooms.lock().await;
-        let sender = senders.get(&id).unwrap();
-        let mut rx: tokio::sync::broadcast::Receiver<String> = sender.subscribe();
-        drop(senders);
-        while let Ok(message) = rx.recv().await {
-            if let Err(e) = socket
-                .send(ws::Message::Text(message))
-                .await
-            {
-                event!(tracing::Level::ERROR, "Error sending message by websocket: {e}");
+    /// Waits for the client to close the socket, ignoring anything it sends.
+    async fn closed(socket: &mut ws::WebSocket) {
+        while let Some(Ok(message)) = socket.recv().await {
+            if let ws::Message::Close(_) = message {
+                return;
             }
         }
     }
@@ -0,0 +1,275 @@
+use std::{io, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{Board, Engine, Outcome, Turn},
+    utility::Point,
+    Invite, Room, Seat, TimeControl, Variant,
+};
+
+/// Something that happened in a room. A room's log of events is the source of truth for it:
+/// replaying the log from an empty room through `Room::apply` rebuilds its current state.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomEvent {
+    /// Sets the room up from scratch; the first event of every log.
+    Created {
+        /// The start position as FEN.
+        start: String,
+        hints: bool,
+        rated: bool,
+        white: Seat,
+        black: Seat,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        variant: Variant,
+        #[serde(default)]
+        time_control: Option<TimeControl>,
+        #[serde(default = "crate::enabled")]
+        public: bool,
+        #[serde(default)]
+        invite: Option<Invite>,
+    },
+    /// Changes who plays a side; a side no longer played by humans loses its player.
+    SeatClaimed {
+        turn: Turn,
+        seat: Seat,
+    },
+    /// Gives the user the side's human seat.
+    PlayerJoined {
+        turn: Turn,
+        user: String,
+    },
+    HintsChanged {
+        hints: bool,
+    },
+    Move {
+        from: Point,
+        to: Point,
+    },
+    /// Offers a draw, or agrees to one when the other side has offered it.
+    DrawOffered {
+        turn: Turn,
+    },
+    Resigned {
+        turn: Turn,
+    },
+    /// Starts a new game from the room's start position, keeping its settings and seats.
+    Reset,
+    /// Replaces a private room's invite token.
+    InviteRegenerated {
+        token: String,
+    },
+    /// Closes an idle room to moves and takes it off the index until it is reset.
+    Archived,
+}
+
+/// An event as logged, with when it happened.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LoggedEvent {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: RoomEvent,
+}
+
+impl LoggedEvent {
+    pub fn now(event: RoomEvent) -> Self {
+        Self { at: now(), event }
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl Room {
+    /// Applies the event, which happened at `at`, if it is valid in the room's current
+    /// state, leaving the room unchanged otherwise.
+    pub fn apply(&mut self, event: &RoomEvent, at: u64) -> io::Result<()> {
+        match event {
+            RoomEvent::Created {
+                start,
+                hints,
+                rated,
+                white,
+                black,
+                name,
+                variant,
+                time_control,
+                public,
+                invite,
+            } => {
+                let board = Board::from_fen(start).map_err(|e| invalid(e.to_string()))?;
+                *self = Room {
+                    hints: *hints,
+                    rated: *rated,
+                    white: *white,
+                    black: *black,
+                    version: self.version,
+                    name: name.clone(),
+                    variant: *variant,
+                    time_control: *time_control,
+                    public: *public,
+                    invite: invite.clone(),
+                    created_at: at,
+                    ..Room::new(self.id.clone(), board)
+                };
+            }
+            RoomEvent::SeatClaimed { turn, seat } => {
+                if self.rated && *seat != Seat::Human {
+                    return Err(invalid("Rated games are between humans".to_string()));
+                }
+                let (side, player) = match turn {
+                    Turn::White => (&mut self.white, &mut self.white_player),
+                    Turn::Black => (&mut self.black, &mut self.black_player),
+                };
+                *side = *seat;
+                if *seat != Seat::Human {
+                    *player = None;
+                }
+            }
+            RoomEvent::PlayerJoined { turn, user } => {
+                if self.seat(*turn) != Seat::Human {
+                    return Err(invalid(format!("{turn:?} is not played by a human")));
+                }
+                match turn {
+                    Turn::White => self.white_player = Some(user.clone()),
+                    Turn::Black => self.black_player = Some(user.clone()),
+                }
+            }
+            RoomEvent::HintsChanged { hints } => self.hints = *hints,
+            RoomEvent::Move { from, to } => {
+                self.check_unfinished()?;
+                let legal_move = Engine::find_move(&self.board, *from, *to)
+                    .ok_or_else(|| invalid("Illegal move".to_string()))?;
+                // Moving on declines the other side's offer.
+                if self.draw_offer.is_some_and(|turn| turn != self.board.turn) {
+                    self.draw_offer = None;
+                }
+                self.board = Engine::make_move(self.board.clone(), *from, *to);
+                self.history.push(legal_move);
+                self.positions.push(self.board.hash());
+            }
+            RoomEvent::DrawOffered { turn } => {
+                self.check_unfinished()?;
+                if self.draw_offer == Some(turn.next()) {
+                    self.result = Some(Outcome::Draw);
+                    self.draw_offer = None;
+                } else {
+                    self.draw_offer = Some(*turn);
+                }
+            }
+            RoomEvent::Resigned { turn } => {
+                self.check_unfinished()?;
+                self.result = Some(Outcome::win(turn.next()));
+                self.draw_offer = None;
+            }
+            RoomEvent::Reset => {
+                // Starting over must not undo a rated result, nor escape a losing game.
+                if self.rated && self.outcome().is_none() {
+                    return Err(invalid("A rated game is reset once it is over".to_string()));
+                }
+                *self = Room {
+                    hints: self.hints,
+                    rated: self.rated,
+                    white: self.white,
+                    black: self.black,
+                    white_player: self.white_player.take(),
+                    black_player: self.black_player.take(),
+                    version: self.version,
+                    name: self.name.clone(),
+                    variant: self.variant,
+                    time_control: self.time_control,
+                    public: self.public,
+                    invite: self.invite.take(),
+                    created_at: self.created_at,
+                    ..Room::new(self.id.clone(), self.start.clone())
+                };
+            }
+            RoomEvent::InviteRegenerated { token } => match &mut self.invite {
+                Some(invite) => invite.token = token.clone(),
+                None => return Err(invalid("The room is public".to_string())),
+            },
+            RoomEvent::Archived => self.archived = true,
+        }
+        self.version += 1;
+        // Archiving is the server's doing, not the players'.
+        if !matches!(event, RoomEvent::Archived) {
+            self.last_activity = at;
+        }
+        Ok(())
+    }
+
+    /// The events that build this room from nothing: its creation, its players taking their
+    /// seats and the moves played.
+    pub fn creation_events(&self) -> Vec<RoomEvent> {
+        let created = RoomEvent::Created {
+            start: self.start.to_fen(),
+            hints: self.hints,
+            rated: self.rated,
+            white: self.white,
+            black: self.black,
+            name: self.name.clone(),
+            variant: self.variant,
+            time_control: self.time_control,
+            public: self.public,
+            invite: self.invite.clone(),
+        };
+        let players = [Turn::White, Turn::Black].into_iter().filter_map(|turn| {
+            self.player(turn).map(|user| RoomEvent::PlayerJoined {
+                turn,
+                user: user.to_string(),
+            })
+        });
+        let moves = self.history.iter().map(|played_move| RoomEvent::Move {
+            from: played_move.from,
+            to: played_move.to,
+        });
+        std::iter::once(created)
+            .chain(players)
+            .chain(moves)
+            .collect()
+    }
+
+    fn check_unfinished(&self) -> io::Result<()> {
+        if self.archived {
+            return Err(invalid("The room is archived".to_string()));
+        }
+        match self.outcome() {
+            Some(outcome) => Err(invalid(format!("The game is over: {outcome}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resets_return_to_the_start_position() {
+        let start = Board::from_fen("W:WK10,25:B3,12").unwrap();
+        let mut room = Room::new("room".to_string(), start.clone());
+        let played = Engine::get_moves(&start)[0];
+        let played = RoomEvent::Move {
+            from: played.from,
+            to: played.to,
+        };
+        room.apply(&played, 0).unwrap();
+        assert_ne!(room.board, start);
+        room.apply(&RoomEvent::Reset, 0).unwrap();
+        assert_eq!(room.board, start);
+        assert_eq!(room.start, start);
+        assert!(room.history.is_empty());
+    }
+}
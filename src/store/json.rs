@@ -0,0 +1,276 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    conflict,
+    migrations::{migrate_json, JSON_VERSION},
+    name_taken, LoggedEvent, MemoryStore, Store,
+};
+use crate::{
+    accounts::{Session, User},
+    ratings::RatingChange,
+    Room, Variant,
+};
+
+/// One line of the log file.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    /// The first line, giving the file's format version.
+    Schema {
+        version: u32,
+    },
+    Event {
+        room: String,
+        event: LoggedEvent,
+    },
+    Deleted {
+        room: String,
+    },
+    /// A new user or a change to one.
+    User {
+        user: User,
+    },
+    Session {
+        token_hash: String,
+        session: Session,
+    },
+    SignedOut {
+        token_hash: String,
+    },
+    Rating {
+        change: RatingChange,
+    },
+}
+
+/// Every room's events, and every account, session and rating change, in one append-only file
+/// of JSON lines.
+///
+/// The file is replayed into memory when the store opens and only ever appended to; lines are
+/// written while holding the file's lock, so concurrent rooms never split each other's lines.
+/// Snapshots are kept in memory only, as the whole log is read at startup anyway. Files of
+/// older versions are migrated when opened, keeping the original beside them as
+/// `<name>.v<version>.bak`.
+pub struct JsonStore {
+    file: Mutex<File>,
+    memory: MemoryStore,
+}
+
+impl JsonStore {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let records = match fs::read_to_string(path) {
+            Ok(contents) => JsonStore::read(path, &contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let schema = Record::Schema {
+                    version: JSON_VERSION,
+                };
+                JsonStore::write(&mut File::create(path)?, &[schema])?;
+                vec![]
+            }
+            Err(e) => return Err(e),
+        };
+        let memory = MemoryStore::default();
+        for record in records {
+            match record {
+                Record::Schema { .. } => {}
+                Record::Event { room, event } => {
+                    memory.append_events(&room, memory.length(&room), &[event])?;
+                }
+                Record::Deleted { room } => memory.delete_room(&room)?,
+                Record::User { user } => memory.save_user(&user)?,
+                Record::Session {
+                    token_hash,
+                    session,
+                } => memory.create_session(&token_hash, &session)?,
+                Record::SignedOut { token_hash } => memory.delete_session(&token_hash)?,
+                Record::Rating { change } => memory.add_ratings(&[change])?,
+            }
+        }
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            memory,
+        })
+    }
+
+    /// The file's records, migrated to `JSON_VERSION` and written back first if older.
+    fn read(path: &Path, contents: &str) -> io::Result<Vec<Record>> {
+        let (version, data) = JsonStore::parse(contents)?;
+        let records: Vec<Record> = serde_json::from_value(migrate_json(version, data)?)?;
+        if version < JSON_VERSION {
+            fs::copy(path, path.with_extension(format!("v{version}.bak")))?;
+            let schema = Record::Schema {
+                version: JSON_VERSION,
+            };
+            let temporary = path.with_extension("tmp");
+            let mut file = File::create(&temporary)?;
+            JsonStore::write(&mut file, &[schema])?;
+            JsonStore::write(&mut file, &records)?;
+            fs::rename(temporary, path)?;
+        }
+        Ok(records)
+    }
+
+    /// The file's version and data: the object of rooms for version 0, else the array of its
+    /// lines without the schema record.
+    fn parse(contents: &str) -> io::Result<(u32, Value)> {
+        if let Ok(Value::Object(rooms)) = serde_json::from_str(contents) {
+            if !rooms.contains_key("record") {
+                return Ok((0, Value::Object(rooms)));
+            }
+        }
+        let mut lines = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<Vec<Value>>>()?;
+        // Logs written before the schema record was added are version 1.
+        let mut version = 1;
+        if lines.first().is_some_and(|line| line["record"] == "schema") {
+            let schema = lines.remove(0);
+            version = schema["version"]
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid schema record {schema}"),
+                    )
+                })?;
+        }
+        Ok((version, Value::Array(lines)))
+    }
+
+    fn write(file: &mut File, records: &[Record]) -> io::Result<()> {
+        let mut lines = String::new();
+        for record in records {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())?;
+        file.flush()
+    }
+}
+
+impl Store for JsonStore {
+    fn room_ids(&self) -> io::Result<Vec<String>> {
+        self.memory.room_ids()
+    }
+
+    fn read_events(&self, id: &str, from: usize) -> io::Result<Vec<LoggedEvent>> {
+        self.memory.read_events(id, from)
+    }
+
+    fn append_events(&self, id: &str, first: usize, events: &[LoggedEvent]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let length = self.memory.length(id);
+        if length != first {
+            return Err(conflict(id, first, length));
+        }
+        let records: Vec<Record> = events
+            .iter()
+            .map(|event| Record::Event {
+                room: id.to_string(),
+                event: event.clone(),
+            })
+            .collect();
+        JsonStore::write(&mut file, &records)?;
+        self.memory.append_events(id, first, events)
+    }
+
+    fn read_snapshot(&self, id: &str) -> io::Result<Option<Room>> {
+        self.memory.read_snapshot(id)
+    }
+
+    fn write_snapshot(&self, room: &Room) -> io::Result<()> {
+        self.memory.write_snapshot(room)
+    }
+
+    fn delete_room(&self, id: &str) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if self.memory.length(id) == 0 {
+            return Ok(());
+        }
+        JsonStore::write(
+            &mut file,
+            &[Record::Deleted {
+                room: id.to_string(),
+            }],
+        )?;
+        self.memory.delete_room(id)
+    }
+
+    fn save_user(&self, user: &User) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let existing = self.memory.find_user(&user.name)?;
+        if existing.is_some_and(|other| other.id != user.id) {
+            return Err(name_taken(&user.name));
+        }
+        JsonStore::write(&mut file, &[Record::User { user: user.clone() }])?;
+        self.memory.save_user(user)
+    }
+
+    fn get_user(&self, id: &str) -> io::Result<Option<User>> {
+        self.memory.get_user(id)
+    }
+
+    fn find_user(&self, name: &str) -> io::Result<Option<User>> {
+        self.memory.find_user(name)
+    }
+
+    fn create_session(&self, token_hash: &str, session: &Session) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let record = Record::Session {
+            token_hash: token_hash.to_string(),
+            session: session.clone(),
+        };
+        JsonStore::write(&mut file, &[record])?;
+        self.memory.create_session(token_hash, session)
+    }
+
+    fn read_session(&self, token_hash: &str) -> io::Result<Option<Session>> {
+        self.memory.read_session(token_hash)
+    }
+
+    fn delete_session(&self, token_hash: &str) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if self.memory.read_session(token_hash)?.is_none() {
+            return Ok(());
+        }
+        let record = Record::SignedOut {
+            token_hash: token_hash.to_string(),
+        };
+        JsonStore::write(&mut file, &[record])?;
+        self.memory.delete_session(token_hash)
+    }
+
+    /// Logs nothing: whether a session has expired follows from its record, so replaying the
+    /// file and pruning again forgets the same sessions.
+    fn delete_sessions_before(&self, created_before: u64) -> io::Result<usize> {
+        self.memory.delete_sessions_before(created_before)
+    }
+
+    fn rating_history(&self, user_id: &str, variant: Variant) -> io::Result<Vec<RatingChange>> {
+        self.memory.rating_history(user_id, variant)
+    }
+
+    fn add_ratings(&self, changes: &[RatingChange]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let records: Vec<Record> = changes
+            .iter()
+            .map(|change| Record::Rating {
+                change: change.clone(),
+            })
+            .collect();
+        JsonStore::write(&mut file, &records)?;
+        self.memory.add_ratings(changes)
+    }
+}
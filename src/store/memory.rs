@@ -0,0 +1,129 @@
+use std::{collections::HashMap, io, sync::Mutex};
+
+use super::{conflict, name_taken, LoggedEvent, Store};
+use crate::{
+    accounts::{Session, User},
+    ratings::RatingChange,
+    Room, Variant,
+};
+
+/// Keeps everything in memory only, for tests and throwaway servers.
+#[derive(Default)]
+pub struct MemoryStore {
+    logs: Mutex<HashMap<String, Vec<LoggedEvent>>>,
+    snapshots: Mutex<HashMap<String, Room>>,
+    users: Mutex<HashMap<String, User>>,
+    sessions: Mutex<HashMap<String, Session>>,
+    ratings: Mutex<Vec<RatingChange>>,
+}
+
+impl MemoryStore {
+    /// The number of events in the room's log.
+    pub(super) fn length(&self, id: &str) -> usize {
+        self.logs.lock().unwrap().get(id).map_or(0, Vec::len)
+    }
+}
+
+impl Store for MemoryStore {
+    fn room_ids(&self) -> io::Result<Vec<String>> {
+        Ok(self.logs.lock().unwrap().keys().cloned().collect())
+    }
+
+    fn read_events(&self, id: &str, from: usize) -> io::Result<Vec<LoggedEvent>> {
+        let logs = self.logs.lock().unwrap();
+        let log = logs.get(id).map_or(&[][..], Vec::as_slice);
+        Ok(log.iter().skip(from).cloned().collect())
+    }
+
+    fn append_events(&self, id: &str, first: usize, events: &[LoggedEvent]) -> io::Result<()> {
+        let mut logs = self.logs.lock().unwrap();
+        let length = logs.get(id).map_or(0, Vec::len);
+        if length != first {
+            return Err(conflict(id, first, length));
+        }
+        logs.entry(id.to_string())
+            .or_default()
+            .extend_from_slice(events);
+        Ok(())
+    }
+
+    fn read_snapshot(&self, id: &str) -> io::Result<Option<Room>> {
+        Ok(self.snapshots.lock().unwrap().get(id).cloned())
+    }
+
+    fn write_snapshot(&self, room: &Room) -> io::Result<()> {
+        self.snapshots
+            .lock()
+            .unwrap()
+            .insert(room.id.clone(), room.clone());
+        Ok(())
+    }
+
+    fn delete_room(&self, id: &str) -> io::Result<()> {
+        self.logs.lock().unwrap().remove(id);
+        self.snapshots.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn save_user(&self, user: &User) -> io::Result<()> {
+        let mut users = self.users.lock().unwrap();
+        let taken = users
+            .values()
+            .any(|other| other.id != user.id && other.name.eq_ignore_ascii_case(&user.name));
+        if taken {
+            return Err(name_taken(&user.name));
+        }
+        users.insert(user.id.clone(), user.clone());
+        Ok(())
+    }
+
+    fn get_user(&self, id: &str) -> io::Result<Option<User>> {
+        Ok(self.users.lock().unwrap().get(id).cloned())
+    }
+
+    fn find_user(&self, name: &str) -> io::Result<Option<User>> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .values()
+            .find(|user| user.name.eq_ignore_ascii_case(name))
+            .cloned())
+    }
+
+    fn create_session(&self, token_hash: &str, session: &Session) -> io::Result<()> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(token_hash.to_string(), session.clone());
+        Ok(())
+    }
+
+    fn read_session(&self, token_hash: &str) -> io::Result<Option<Session>> {
+        Ok(self.sessions.lock().unwrap().get(token_hash).cloned())
+    }
+
+    fn delete_session(&self, token_hash: &str) -> io::Result<()> {
+        self.sessions.lock().unwrap().remove(token_hash);
+        Ok(())
+    }
+
+    fn delete_sessions_before(&self, created_before: u64) -> io::Result<usize> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let count = sessions.len();
+        sessions.retain(|_, session| session.created_at >= created_before);
+        Ok(count - sessions.len())
+    }
+
+    fn rating_history(&self, user_id: &str, variant: Variant) -> io::Result<Vec<RatingChange>> {
+        let ratings = self.ratings.lock().unwrap();
+        Ok(ratings
+            .iter()
+            .filter(|change| change.user_id == user_id && change.variant == variant)
+            .cloned()
+            .collect())
+    }
+
+    fn add_ratings(&self, changes: &[RatingChange]) -> io::Result<()> {
+        self.ratings.lock().unwrap().extend_from_slice(changes);
+        Ok(())
+    }
+}
@@ -0,0 +1,637 @@
+//! Upgrades of data written by older servers.
+//!
+//! Each migration works on the stored representation itself (JSON values or SQL rows) rather
+//! than through the serde derives of `Room`, `Board` and friends, so it keeps reading its
+//! version's data however those types change later. A change to the persisted shape of an
+//! event adds a version and a migration here.
+
+use std::io;
+
+use rusqlite::{params, Connection, Transaction};
+use serde_json::{json, Value};
+
+use super::now;
+use crate::engine::{Board, Notation};
+
+/// The version of the JSON log format written by this server.
+///
+/// 0. One object of whole rooms keyed by id, serialized by serde.
+/// 1. One log record per line, optionally headed by a schema record.
+/// 2. Events carry the time they happened as `at`.
+/// 3. Records of users and sessions by token hash, and events of players taking seats.
+/// 4. Records of rating changes.
+pub const JSON_VERSION: u32 = 4;
+
+/// The version of the SQLite schema created by this server, kept in `PRAGMA user_version`.
+///
+/// 1. `games`, `rooms` and `moves` tables.
+/// 2. `events` and `snapshots` tables.
+/// 3. Events carry the time they happened as `at`.
+/// 4. `users` and `sessions` (by token hash) tables, and events of players taking seats.
+/// 5. `ratings` table.
+///
+/// Databases written before versioning read as 0 whichever layout they hold; every step only
+/// creates what is missing, so both upgrade the same way.
+pub const SQLITE_VERSION: u32 = 5;
+
+/// Upgrades the file from version `n` to `n + 1`. Version 0 files are one object, later ones
+/// an array of their lines.
+type JsonMigration = fn(Value) -> io::Result<Value>;
+
+const JSON_MIGRATIONS: [JsonMigration; JSON_VERSION as usize] =
+    [rooms_to_events, timestamp_events, add_accounts, add_ratings];
+
+type SqliteMigration = fn(&Transaction) -> rusqlite::Result<()>;
+
+const SQLITE_MIGRATIONS: [SqliteMigration; SQLITE_VERSION as usize] = [
+    create_games,
+    games_to_events,
+    timestamp_rows,
+    create_accounts,
+    create_ratings,
+];
+
+/// Upgrades a JSON store's data of `version` to `JSON_VERSION`.
+pub fn migrate_json(version: u32, data: Value) -> io::Result<Value> {
+    if version > JSON_VERSION {
+        return Err(newer(version, JSON_VERSION));
+    }
+    JSON_MIGRATIONS[version as usize..]
+        .iter()
+        .try_fold(data, |data, migration| migration(data))
+}
+
+/// Upgrades the database to `SQLITE_VERSION`, one transaction per version.
+pub fn migrate_sqlite(connection: &mut Connection) -> io::Result<()> {
+    let version: u32 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(io::Error::other)?;
+    if version > SQLITE_VERSION {
+        return Err(newer(version, SQLITE_VERSION));
+    }
+    for (from, migration) in SQLITE_MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let transaction = connection.transaction().map_err(io::Error::other)?;
+        migration(&transaction).map_err(io::Error::other)?;
+        transaction
+            .pragma_update(None, "user_version", from + 1)
+            .map_err(io::Error::other)?;
+        transaction.commit().map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+fn newer(version: u32, supported: u32) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Stored data is version {version}, newer than the supported {supported}"),
+    )
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 0 to 1: each room becomes its creation followed by its moves.
+fn rooms_to_events(data: Value) -> io::Result<Value> {
+    let Value::Object(rooms) = data else {
+        return Err(invalid("Expected an object of rooms".to_string()));
+    };
+    let mut records = vec![];
+    for (id, room) in rooms {
+        let field = |name: &str| room.get(name).filter(|value| !value.is_null());
+        let history = field("history")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        // Rooms saved before the start position was kept have no moves either.
+        let start = match (history.is_empty(), field("start")) {
+            (false, Some(start)) => board_fen(start)?,
+            (false, None) => Board::new().to_fen(),
+            (true, _) => board_fen(field("board").unwrap_or(&Value::Null))?,
+        };
+        let seat = |name| field(name).cloned().unwrap_or(json!("human"));
+        let created = json!({
+            "type": "created",
+            "start": start,
+            "hints": field("hints").cloned().unwrap_or(json!(true)),
+            "rated": field("rated").cloned().unwrap_or(json!(false)),
+            "white": seat("white"),
+            "black": seat("black"),
+        });
+        let moves = history.iter().map(|played_move| {
+            json!({ "type": "move", "from": played_move["from"], "to": played_move["to"] })
+        });
+        for event in std::iter::once(created).chain(moves) {
+            records.push(json!({ "record": "event", "room": id, "event": event }));
+        }
+    }
+    Ok(Value::Array(records))
+}
+
+/// The FEN of a board serialized as `{ "cells": [[cell; 8]; 8], "turn": "White" }`, where a
+/// cell is `"Empty"` or `{ "Checker": "WhiteQueen" }` and the like.
+fn board_fen(board: &Value) -> io::Result<String> {
+    let rows = board["cells"]
+        .as_array()
+        .ok_or_else(|| invalid(format!("Invalid board {board}")))?;
+    let mut white = vec![];
+    let mut black = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, cell) in row.as_array().into_iter().flatten().enumerate() {
+            let Some(checker) = cell["Checker"].as_str() else {
+                continue;
+            };
+            let square = y * 4 + x / 2 + 1;
+            let piece = match checker.ends_with("Queen") {
+                true => format!("K{square}"),
+                false => square.to_string(),
+            };
+            match checker.starts_with("White") {
+                true => white.push(piece),
+                false => black.push(piece),
+            }
+        }
+    }
+    let turn = match board["turn"].as_str() {
+        Some("Black") => "B",
+        _ => "W",
+    };
+    Ok(format!("{turn}:W{}:B{}", white.join(","), black.join(",")))
+}
+
+/// 0 to 1: the first schema.
+fn create_games(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS games (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            room_id TEXT NOT NULL,
+            start TEXT NOT NULL,
+            board TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS rooms (
+            id TEXT PRIMARY KEY,
+            game_id INTEGER NOT NULL REFERENCES games (id),
+            hints INTEGER NOT NULL,
+            rated INTEGER NOT NULL,
+            white TEXT NOT NULL,
+            black TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS moves (
+            game_id INTEGER NOT NULL REFERENCES games (id),
+            ply INTEGER NOT NULL,
+            from_square INTEGER NOT NULL,
+            to_square INTEGER NOT NULL,
+            is_capture INTEGER NOT NULL,
+            PRIMARY KEY (game_id, ply)
+        );
+        ",
+    )
+}
+
+/// 1 to 2: each room's games become its creation, a reset (or a new creation when the game
+/// did not start from the initial position) per later game, and their moves. Games of
+/// deleted rooms are dropped with the old tables.
+fn games_to_events(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS events (
+            room_id TEXT NOT NULL,
+            sequence INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (room_id, sequence)
+        );
+        CREATE TABLE IF NOT EXISTS snapshots (
+            room_id TEXT PRIMARY KEY,
+            version INTEGER NOT NULL,
+            room TEXT NOT NULL
+        );
+        ",
+    )?;
+    let initial = Board::new().to_fen();
+    let mut rooms = transaction.prepare(
+        "SELECT rooms.id, rooms.hints, rooms.rated, rooms.white, rooms.black, games.id, \
+         games.start FROM rooms JOIN games ON games.room_id = rooms.id \
+         ORDER BY rooms.id, games.id",
+    )?;
+    let games = rooms
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, bool>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut moves = transaction
+        .prepare("SELECT from_square, to_square FROM moves WHERE game_id = ?1 ORDER BY ply")?;
+    let mut insert =
+        transaction.prepare("INSERT INTO events (room_id, sequence, event) VALUES (?1, ?2, ?3)")?;
+    let mut previous: Option<(String, usize)> = None;
+    for (id, hints, rated, white, black, game_id, start) in games {
+        let sequence = match &previous {
+            Some((room, sequence)) if *room == id => *sequence,
+            _ => 0,
+        };
+        let mut events = vec![match sequence == 0 || start != initial {
+            true => json!({
+                "type": "created",
+                "start": start,
+                "hints": hints,
+                "rated": rated,
+                "white": white,
+                "black": black,
+            }),
+            false => json!({ "type": "reset" }),
+        }];
+        let game_moves = moves
+            .query_map(params![game_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(u8, u8)>>>()?;
+        for (from, to) in game_moves {
+            let point = |square| {
+                let point = Notation::from_square(square).unwrap_or_default();
+                json!({ "x": point.x, "y": point.y })
+            };
+            events.push(json!({ "type": "move", "from": point(from), "to": point(to) }));
+        }
+        for (offset, event) in events.iter().enumerate() {
+            insert.execute(params![id, sequence + offset, event.to_string()])?;
+        }
+        previous = Some((id, sequence + events.len()));
+    }
+    transaction.execute_batch("DROP TABLE moves; DROP TABLE rooms; DROP TABLE games;")
+}
+
+/// 1 to 2: events of unknown time count as happening now, so upgraded rooms are not expired
+/// straight away.
+fn timestamp_events(data: Value) -> io::Result<Value> {
+    let Value::Array(mut records) = data else {
+        return Err(invalid("Expected an array of records".to_string()));
+    };
+    let at = now();
+    for record in &mut records {
+        if let Some(event) = record.get_mut("event").and_then(Value::as_object_mut) {
+            event.entry("at").or_insert(json!(at));
+        }
+    }
+    Ok(Value::Array(records))
+}
+
+/// 2 to 3: events take the time their row was recorded. Snapshots predate the room's
+/// timestamps, so they are dropped and rebuilt.
+fn timestamp_rows(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "
+        UPDATE events
+        SET event = json_set(event, '$.at', CAST(strftime('%s', recorded_at) AS INTEGER));
+        DELETE FROM snapshots;
+        ",
+    )
+}
+
+/// 2 to 3: only adds kinds of records and events, so older files read as they are.
+fn add_accounts(data: Value) -> io::Result<Value> {
+    Ok(data)
+}
+
+/// 3 to 4: accounts and their sessions.
+fn create_accounts(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS users (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            password_hash TEXT,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS sessions (
+            token_hash TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL REFERENCES users (id),
+            created_at INTEGER NOT NULL
+        );
+        ",
+    )
+}
+
+/// 3 to 4: only adds a kind of record, so older files read as they are.
+fn add_ratings(data: Value) -> io::Result<Value> {
+    Ok(data)
+}
+
+/// 4 to 5: every rating change, the latest of a user and variant being their current rating.
+fn create_ratings(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ratings (
+            user_id TEXT NOT NULL REFERENCES users (id),
+            variant TEXT NOT NULL,
+            room_id TEXT NOT NULL,
+            rating REAL NOT NULL,
+            deviation REAL NOT NULL,
+            volatility REAL NOT NULL,
+            rated_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS ratings_by_user ON ratings (user_id, variant);
+        ",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+    use crate::{
+        accounts::{Session, User},
+        ratings::{Rating, RatingChange},
+        store::{json_path, JsonStore, SqliteStore, Store},
+        Room, Seat, Variant,
+    };
+
+    fn fixture(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", name]
+            .iter()
+            .collect()
+    }
+
+    /// A copy of the fixture in a fresh directory, as migrations rewrite it.
+    fn copy_fixture(name: &str, test: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("checkers-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        fs::copy(fixture(name), &path).unwrap();
+        path
+    }
+
+    fn room<'a>(rooms: &'a [(String, Room)], id: &str) -> &'a Room {
+        &rooms.iter().find(|(room_id, _)| room_id == id).unwrap().1
+    }
+
+    /// Every fixture was written by the same session: a fresh room, a game of six moves with
+    /// captures whose hints and black seat were then changed, a king moving in a set-up
+    /// position, a room reset after two moves, and a deleted room.
+    fn check_rooms(store: &dyn Store) {
+        let mut rooms: Vec<(String, Room)> = store.get_rooms().unwrap().into_iter().collect();
+        rooms.sort_by(|a, b| a.0.cmp(&b.0));
+        let ids: Vec<&str> = rooms.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["fresh", "king", "played", "reset"]);
+
+        let fresh = room(&rooms, "fresh");
+        assert_eq!(fresh.board, Board::new());
+        assert!(fresh.history.is_empty());
+
+        let played = room(&rooms, "played");
+        assert_eq!(
+            played.board.to_fen(),
+            "W:W19,21,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,10,12,14,15"
+        );
+        assert_eq!(played.history.len(), 6);
+        assert!(played.history[2].is_capture && played.history[3].is_capture);
+        assert!(!played.hints);
+        assert!(played.white == Seat::Human && played.black == Seat::Mcts);
+
+        let king = room(&rooms, "king");
+        assert_eq!(king.start.to_fen(), "W:WK10,25:B3,12");
+        assert_eq!(king.board.to_fen(), "B:WK6,25:B3,12");
+
+        let reset = room(&rooms, "reset");
+        assert_eq!(reset.start, Board::new());
+        assert_eq!(
+            reset.board.to_fen(),
+            "B:W20,21,22,23,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12"
+        );
+        assert_eq!(reset.history.len(), 1);
+
+        for (_, room) in &rooms {
+            assert!(room.created_at > 0 && room.last_activity >= room.created_at);
+        }
+    }
+
+    #[test]
+    fn json_rooms_become_event_logs() {
+        let path = copy_fixture("rooms-v0.json", "json-v0");
+        check_rooms(&JsonStore::open(&path).unwrap());
+        assert!(path.with_extension("v0.bak").exists());
+        let header = fs::read_to_string(&path).unwrap();
+        let schema = format!(r#"{{"record":"schema","version":{JSON_VERSION}}}"#);
+        assert!(header.starts_with(&schema));
+        // Opening the migrated file again must not change it.
+        check_rooms(&JsonStore::open(&path).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), header);
+    }
+
+    /// `data/rooms.json` as the first server wrote it: each room's board alone, here with a
+    /// king on each side and a selection's highlighted squares.
+    fn check_baseline_rooms(store: &dyn Store) {
+        let mut rooms: Vec<(String, Room)> = store.get_rooms().unwrap().into_iter().collect();
+        rooms.sort_by(|a, b| a.0.cmp(&b.0));
+        let ids: Vec<&str> = rooms.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["kings", "opening", "played"]);
+        let boards = [
+            (
+                "kings",
+                "B:WK10,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,11,12,K18",
+            ),
+            (
+                "opening",
+                "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
+            ),
+            (
+                "played",
+                "B:W20,21,23,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,9,10,12,15",
+            ),
+        ];
+        for (id, fen) in boards {
+            let room = room(&rooms, id);
+            assert_eq!(room.board, Board::from_fen(fen).unwrap(), "{id}");
+            // Without a history, the game starts from the board as it was saved.
+            assert_eq!(room.start, room.board, "{id}");
+            assert!(room.history.is_empty());
+            assert!(room.hints && !room.rated);
+            assert!(room.white == Seat::Human && room.black == Seat::Human);
+        }
+    }
+
+    #[test]
+    fn json_baseline_rooms_become_event_logs() {
+        let path = copy_fixture("rooms-baseline.json", "json-baseline");
+        check_baseline_rooms(&JsonStore::open(&path).unwrap());
+        assert!(path.with_extension("v0.bak").exists());
+    }
+
+    #[test]
+    fn json_rooms_file_is_renamed_to_the_event_log() {
+        let path = copy_fixture("rooms-baseline.json", "json-legacy");
+        let directory = path.parent().unwrap();
+        let legacy = directory.join("rooms.json");
+        fs::rename(&path, &legacy).unwrap();
+        let path = json_path(directory).unwrap();
+        assert_eq!(path, directory.join("events.jsonl"));
+        assert!(!legacy.exists());
+        check_baseline_rooms(&JsonStore::open(&path).unwrap());
+        // A log already in place is kept, whatever else the directory holds.
+        fs::write(&legacy, "{}").unwrap();
+        assert_eq!(json_path(directory).unwrap(), path);
+        assert!(legacy.exists());
+        check_baseline_rooms(&JsonStore::open(&path).unwrap());
+    }
+
+    #[test]
+    fn json_event_logs_gain_timestamps() {
+        let path = copy_fixture("events-v1.jsonl", "json-v1");
+        check_rooms(&JsonStore::open(&path).unwrap());
+        assert!(path.with_extension("v1.bak").exists());
+        check_rooms(&JsonStore::open(&path).unwrap());
+    }
+
+    #[test]
+    fn json_timestamped_event_logs_upgrade() {
+        let path = copy_fixture("events-v2.jsonl", "json-v2");
+        check_rooms(&JsonStore::open(&path).unwrap());
+        assert!(path.with_extension("v2.bak").exists());
+    }
+
+    #[test]
+    fn json_account_logs_upgrade() {
+        const TOKEN: &str = "9plYgfpjk4WkIwQVsdEwALyuMflVAQtJ";
+        let path = copy_fixture("events-v3.jsonl", "json-v3");
+        let store = JsonStore::open(&path).unwrap();
+        check_rooms(&store);
+        let alice = store.find_user("ALICE").unwrap().unwrap();
+        assert!(alice.verify_password("correct-horse") && !alice.verify_password("wrong"));
+        let session = store.read_session(&Session::token_hash(TOKEN)).unwrap();
+        assert_eq!(session.unwrap().user_id, alice.id);
+        assert!(path.with_extension("v3.bak").exists());
+    }
+
+    /// The v4 fixture adds a rated game, since deleted, that alice won against bob.
+    fn check_ratings(store: &dyn Store) {
+        let alice = store.find_user("alice").unwrap().unwrap();
+        let bob = store.find_user("bob").unwrap().unwrap();
+        let history = store.rating_history(&alice.id, Variant::Russian).unwrap();
+        assert_eq!(history.len(), 1);
+        let (won, lost) = (
+            store.rating(&alice.id, Variant::Russian).unwrap(),
+            store.rating(&bob.id, Variant::Russian).unwrap(),
+        );
+        assert!(won.rating > 1500.0 && lost.rating < 1500.0 && won.is_provisional());
+    }
+
+    #[test]
+    fn json_event_logs_open_unchanged() {
+        const TOKEN: &str = "MPVtZVpZOheUcU4K8gNo5UrnjqeWl4gG";
+        let path = copy_fixture("events-v4.jsonl", "json-v4");
+        let store = JsonStore::open(&path).unwrap();
+        check_rooms(&store);
+        check_ratings(&store);
+        let session = store.read_session(&Session::token_hash(TOKEN)).unwrap();
+        let alice = store.find_user("alice").unwrap().unwrap();
+        assert_eq!(session.unwrap().user_id, alice.id);
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            fs::read(fixture("events-v4.jsonl")).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_from_a_newer_server_is_refused() {
+        let path = copy_fixture("events-v1.jsonl", "json-newer");
+        let log = fs::read_to_string(&path).unwrap();
+        fs::write(
+            &path,
+            format!("{{\"record\":\"schema\",\"version\":9}}\n{log}"),
+        )
+        .unwrap();
+        let error = JsonStore::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn sqlite_games_become_event_logs() {
+        let path = copy_fixture("checkers-v1.db", "sqlite-v1");
+        check_rooms(&SqliteStore::open(&path).unwrap());
+        let connection = Connection::open(&path).unwrap();
+        let version: u32 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SQLITE_VERSION);
+        let tables: u32 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name IN ('games', 'rooms', 'moves')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn sqlite_unversioned_event_logs_upgrade() {
+        let path = copy_fixture("checkers-v2.db", "sqlite-v2");
+        check_rooms(&SqliteStore::open(&path).unwrap());
+        check_rooms(&SqliteStore::open(&path).unwrap());
+    }
+
+    #[test]
+    fn sqlite_upgrades_keep_accounts_and_ratings() {
+        let path = copy_fixture("checkers-v2.db", "sqlite-accounts");
+        let store = SqliteStore::open(&path).unwrap();
+        let mut alice = User::guest();
+        alice.register("alice", "correct-horse").unwrap();
+        store.save_user(&alice).unwrap();
+        let mut impostor = User::guest();
+        impostor.register("Alice", "correct-horse").unwrap();
+        let taken = store.save_user(&impostor).err().unwrap();
+        assert_eq!(taken.kind(), io::ErrorKind::AlreadyExists);
+        let session = Session {
+            user_id: alice.id.clone(),
+            created_at: now(),
+        };
+        store.create_session("token", &session).unwrap();
+
+        let store = SqliteStore::open(&path).unwrap();
+        let found = store.find_user("ALICE").unwrap().unwrap();
+        assert!(found.id == alice.id && found.verify_password("correct-horse"));
+        assert_eq!(
+            store.read_session("token").unwrap().unwrap().user_id,
+            alice.id
+        );
+        store.delete_session("token").unwrap();
+        assert!(store.read_session("token").unwrap().is_none());
+
+        let rated = Rating::default().update(&[(Rating::default(), 1.0)]);
+        let change = RatingChange {
+            user_id: alice.id.clone(),
+            variant: Variant::Russian,
+            room_id: "played".to_string(),
+            rating: rated,
+            at: now(),
+        };
+        store.add_ratings(&[change]).unwrap();
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.rating(&alice.id, Variant::Russian).unwrap(), rated);
+    }
+
+    #[test]
+    fn sqlite_from_a_newer_server_is_refused() {
+        let path = copy_fixture("checkers-v2.db", "sqlite-newer");
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", SQLITE_VERSION + 1)
+            .unwrap();
+        let error = SqliteStore::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn boards_convert_to_fen() {
+        let board = serde_json::to_value(Board::from_fen("B:WK6,25:B3,K12").unwrap()).unwrap();
+        assert_eq!(board_fen(&board).unwrap(), "B:WK6,25:B3,K12");
+    }
+}
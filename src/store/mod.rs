@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tracing::event;
+
+use crate::{
+    accounts::{Session, User},
+    engine::Board,
+    ratings::{Rating, RatingChange},
+    Room, Variant,
+};
+
+mod events;
+mod json;
+mod memory;
+mod migrations;
+mod sqlite;
+
+pub use events::{now, LoggedEvent, RoomEvent};
+pub use json::JsonStore;
+pub use memory::MemoryStore;
+pub use sqlite::SqliteStore;
+
+/// A room's state is saved whenever its log grows past a multiple of this many events, so
+/// loading it replays at most this many.
+const SNAPSHOT_INTERVAL: usize = 64;
+
+/// Persistence of rooms as append-only event logs with snapshots, and of user accounts, their
+/// sessions and ratings, shared by every request through `AppState`.
+pub trait Store: Send + Sync {
+    /// The ids of every room with a log.
+    fn room_ids(&self) -> io::Result<Vec<String>>;
+
+    /// The room's events from the `from`th on.
+    fn read_events(&self, id: &str, from: usize) -> io::Result<Vec<LoggedEvent>>;
+
+    /// Appends events to the room's log, which must hold exactly `first` events already, so a
+    /// writer with a stale room can never interleave its events with newer ones.
+    fn append_events(&self, id: &str, first: usize, events: &[LoggedEvent]) -> io::Result<()>;
+
+    /// The room's latest snapshot, built from its first `version` events.
+    fn read_snapshot(&self, id: &str) -> io::Result<Option<Room>>;
+
+    fn write_snapshot(&self, room: &Room) -> io::Result<()>;
+
+    /// Removes the room's log and snapshot; unknown ids are not an error.
+    fn delete_room(&self, id: &str) -> io::Result<()>;
+
+    /// Adds or replaces the user with its id. A name another user holds, in any case, is an
+    /// `AlreadyExists` error.
+    fn save_user(&self, user: &User) -> io::Result<()>;
+
+    fn get_user(&self, id: &str) -> io::Result<Option<User>>;
+
+    /// The user with the name, in any case.
+    fn find_user(&self, name: &str) -> io::Result<Option<User>>;
+
+    /// Keeps the session by `Session::token_hash` of its token, never the token itself.
+    fn create_session(&self, token_hash: &str, session: &Session) -> io::Result<()>;
+
+    fn read_session(&self, token_hash: &str) -> io::Result<Option<Session>>;
+
+    /// Signs the session out; unknown tokens are not an error.
+    fn delete_session(&self, token_hash: &str) -> io::Result<()>;
+
+    /// Forgets the sessions started before the time, returning how many there were.
+    fn delete_sessions_before(&self, created_before: u64) -> io::Result<usize>;
+
+    /// The user's ratings in the variant after each of their rated games, oldest first.
+    fn rating_history(&self, user_id: &str, variant: Variant) -> io::Result<Vec<RatingChange>>;
+
+    /// Records the changes together, such as both players' after a game.
+    fn add_ratings(&self, changes: &[RatingChange]) -> io::Result<()>;
+
+    /// The user's current rating in the variant, the default one before any rated game.
+    fn rating(&self, user_id: &str, variant: Variant) -> io::Result<Rating> {
+        let history = self.rating_history(user_id, variant)?;
+        Ok(history
+            .last()
+            .map_or_else(Rating::default, |change| change.rating))
+    }
+
+    /// Rebuilds the room from its latest snapshot and the events after it. Snapshots are only
+    /// a cache, so one that no longer decodes is ignored and the whole log replayed.
+    fn get_room(&self, id: &str) -> io::Result<Room> {
+        let snapshot = self.read_snapshot(id).unwrap_or_else(|e| {
+            event!(tracing::Level::WARN, "Snapshot of room {id} ignored: {e}");
+            None
+        });
+        let mut room = snapshot.unwrap_or_else(|| Room::new(id.to_string(), Board::new()));
+        room.replay_positions();
+        let events = self.read_events(id, room.version)?;
+        if room.version == 0 && events.is_empty() {
+            return Err(not_found());
+        }
+        for logged in &events {
+            let version = room.version;
+            room.apply(&logged.event, logged.at).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Event {version} of room {id} does not replay: {e}"),
+                )
+            })?;
+        }
+        Ok(room)
+    }
+
+    /// Every room that loads; the others are logged and left out, so one bad log cannot hide
+    /// the rest.
+    fn get_rooms(&self) -> io::Result<HashMap<String, Room>> {
+        let mut rooms = HashMap::new();
+        for id in self.room_ids()? {
+            match self.get_room(&id) {
+                Ok(room) => {
+                    rooms.insert(id, room);
+                }
+                Err(e) => event!(tracing::Level::ERROR, "Loading room {id} failed: {e}"),
+            }
+        }
+        Ok(rooms)
+    }
+
+    /// Appends the events that turned the room into `room`, then snapshots it if its log
+    /// crossed a multiple of `SNAPSHOT_INTERVAL`.
+    fn save(&self, room: &Room, events: &[LoggedEvent]) -> io::Result<()> {
+        let first = room.version - events.len();
+        self.append_events(&room.id, first, events)?;
+        if first / SNAPSHOT_INTERVAL != room.version / SNAPSHOT_INTERVAL {
+            self.write_snapshot(room)?;
+        }
+        Ok(())
+    }
+}
+
+/// Opens the store chosen by `STORE` (`json`, `sqlite` or `memory`, default `json`) at
+/// `STORE_PATH`, which defaults to `data/events.jsonl` or `data/checkers.db`. Without a path,
+/// a JSON store left at the old `data/rooms.json` is moved to the new one and migrated.
+pub fn open_store() -> io::Result<Arc<dyn Store>> {
+    let kind = env::var("STORE").unwrap_or("json".to_string());
+    let path = env::var("STORE_PATH").ok();
+    match kind.as_str() {
+        "json" => {
+            let path = match path {
+                Some(path) => PathBuf::from(path),
+                None => json_path(Path::new("data"))?,
+            };
+            Ok(Arc::new(JsonStore::open(path)?))
+        }
+        "sqlite" => Ok(Arc::new(SqliteStore::open(
+            path.as_deref().unwrap_or("data/checkers.db"),
+        )?)),
+        "memory" => Ok(Arc::new(MemoryStore::default())),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown store {kind:?}, expected json, sqlite or memory"),
+        )),
+    }
+}
+
+/// The event log in `directory`, renamed from the `rooms.json` older servers kept there if
+/// there is no log yet.
+fn json_path(directory: &Path) -> io::Result<PathBuf> {
+    let path = directory.join("events.jsonl");
+    let legacy = directory.join("rooms.json");
+    if !path.exists() && legacy.exists() {
+        fs::rename(legacy, &path)?;
+    }
+    Ok(path)
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "Room not found")
+}
+
+fn name_taken(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("The name {name} is taken"),
+    )
+}
+
+/// The error for appending at `first` to a log holding `length` events.
+fn conflict(id: &str, first: usize, length: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("Room {id} has {length} events, not {first}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::engine::Engine;
+
+    /// A file path for the test's store in a fresh directory.
+    fn store_path(test: &str, name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("checkers-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory.join(name)
+    }
+
+    /// Every backend must behave the same to the rest of the server.
+    fn stores(test: &str) -> Vec<(&'static str, Box<dyn Store>)> {
+        vec![
+            ("memory", Box::new(MemoryStore::default())),
+            (
+                "json",
+                Box::new(
+                    JsonStore::open(store_path(&format!("{test}-json"), "events.jsonl")).unwrap(),
+                ),
+            ),
+            (
+                "sqlite",
+                Box::new(
+                    SqliteStore::open(store_path(&format!("{test}-sqlite"), "checkers.db"))
+                        .unwrap(),
+                ),
+            ),
+        ]
+    }
+
+    /// Saves a new room with one move played, as its actor would.
+    fn save_played(store: &dyn Store, id: &str) -> Room {
+        let mut events: Vec<LoggedEvent> = Room::new(id.to_string(), Board::new())
+            .creation_events()
+            .into_iter()
+            .map(LoggedEvent::now)
+            .collect();
+        let first_move = Engine::get_moves(&Board::new())[0];
+        events.push(LoggedEvent::now(RoomEvent::Move {
+            from: first_move.from,
+            to: first_move.to,
+        }));
+        let mut room = Room::new(id.to_string(), Board::new());
+        for logged in &events {
+            room.apply(&logged.event, logged.at).unwrap();
+        }
+        store.save(&room, &events).unwrap();
+        room
+    }
+
+    #[test]
+    fn stores_refuse_appends_to_a_stale_log() {
+        for (name, store) in stores("stale") {
+            let room = save_played(store.as_ref(), "room");
+            let reset = [LoggedEvent::now(RoomEvent::Reset)];
+            for stale in [0, room.version - 1, room.version + 1] {
+                let e = store.append_events("room", stale, &reset).unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::AlreadyExists, "{name}");
+            }
+            store.append_events("room", room.version, &reset).unwrap();
+            let loaded = store.get_room("room").unwrap();
+            assert_eq!(loaded.version, room.version + 1, "{name}");
+            assert!(loaded.history.is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn stores_forget_deleted_rooms() {
+        for (name, store) in stores("delete") {
+            save_played(store.as_ref(), "kept");
+            save_played(store.as_ref(), "deleted");
+            store.delete_room("deleted").unwrap();
+            store.delete_room("unknown").unwrap();
+            assert_eq!(store.room_ids().unwrap(), ["kept"], "{name}");
+            assert_eq!(store.get_room("kept").unwrap().history.len(), 1, "{name}");
+            let e = store.get_room("deleted").err().unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::NotFound, "{name}");
+            // A deleted room's id starts a new log.
+            let room = save_played(store.as_ref(), "deleted");
+            assert_eq!(
+                store.get_room("deleted").unwrap().version,
+                room.version,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn stores_report_unknown_rooms_as_not_found() {
+        for (name, store) in stores("unknown") {
+            let e = store.get_room("unknown").err().unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::NotFound, "{name}");
+            assert!(
+                store.read_events("unknown", 0).unwrap().is_empty(),
+                "{name}"
+            );
+            assert!(store.get_rooms().unwrap().is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn stores_match_names_in_any_case() {
+        for (name, store) in stores("names") {
+            let mut user = User::guest();
+            user.name = "Alice".to_string();
+            store.save_user(&user).unwrap();
+            let found = store.find_user("aLICE").unwrap().unwrap();
+            assert_eq!(found.id, user.id, "{name}");
+            let mut other = User::guest();
+            other.name = "ALICE".to_string();
+            let e = store.save_user(&other).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::AlreadyExists, "{name}");
+            assert!(store.get_user(&other.id).unwrap().is_none(), "{name}");
+            // Its owner may change the name's case.
+            user.name = "alice".to_string();
+            store.save_user(&user).unwrap();
+            assert_eq!(
+                store.find_user("ALICE").unwrap().unwrap().name,
+                "alice",
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn stores_prune_sessions_started_before() {
+        for (name, store) in stores("sessions") {
+            let user = User::guest();
+            store.save_user(&user).unwrap();
+            for (token_hash, created_at) in [("old", 100), ("new", 200)] {
+                let session = Session {
+                    user_id: user.id.clone(),
+                    created_at,
+                };
+                store.create_session(token_hash, &session).unwrap();
+            }
+            assert_eq!(store.delete_sessions_before(200).unwrap(), 1, "{name}");
+            assert!(store.read_session("old").unwrap().is_none(), "{name}");
+            assert!(store.read_session("new").unwrap().is_some(), "{name}");
+        }
+    }
+}
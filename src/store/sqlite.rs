@@ -0,0 +1,272 @@
+use std::{error::Error, io, path::Path, sync::Mutex};
+
+use rusqlite::{params, types::Type, Connection, ErrorCode, OptionalExtension};
+
+use super::{conflict, migrations::migrate_sqlite, name_taken, LoggedEvent, Store};
+use crate::{
+    accounts::{Session, User},
+    ratings::{Rating, RatingChange},
+    Room, Variant,
+};
+
+/// Rooms in an embedded SQLite database.
+///
+/// Each event is a row of JSON numbered by its position in the room's log and stamped with
+/// the time it was recorded; a room's latest snapshot is one JSON row beside its log. Users,
+/// sessions and rating changes are plain rows. Older databases are migrated when opened.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut connection = Connection::open(path).map_err(io::Error::other)?;
+        migrate_sqlite(&mut connection)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// The user matching `condition` on the single parameter `value`.
+    fn query_user(&self, condition: &str, value: &str) -> io::Result<Option<User>> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .query_row(
+                &format!("SELECT id, name, password_hash, created_at FROM users WHERE {condition}"),
+                params![value],
+                |row| {
+                    Ok(User {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        password_hash: row.get(2)?,
+                        created_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(io::Error::other)
+    }
+
+    /// A stored column that does not decode back into its type.
+    fn invalid(column: usize, error: impl Into<Box<dyn Error + Send + Sync>>) -> rusqlite::Error {
+        rusqlite::Error::FromSqlConversionFailure(column, Type::Text, error.into())
+    }
+}
+
+impl Store for SqliteStore {
+    fn room_ids(&self) -> io::Result<Vec<String>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT DISTINCT room_id FROM events")
+            .map_err(io::Error::other)?;
+        statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .and_then(|ids| ids.collect())
+            .map_err(io::Error::other)
+    }
+
+    fn read_events(&self, id: &str, from: usize) -> io::Result<Vec<LoggedEvent>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(
+                "SELECT event FROM events WHERE room_id = ?1 AND sequence >= ?2 \
+                 ORDER BY sequence",
+            )
+            .map_err(io::Error::other)?;
+        statement
+            .query_map(params![id, from], |row| {
+                let event = row.get::<_, String>(0)?;
+                serde_json::from_str(&event).map_err(|e| SqliteStore::invalid(0, e))
+            })
+            .and_then(|events| events.collect())
+            .map_err(io::Error::other)
+    }
+
+    fn append_events(&self, id: &str, first: usize, events: &[LoggedEvent]) -> io::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(io::Error::other)?;
+        let length: usize = transaction
+            .query_row(
+                "SELECT COUNT(*) FROM events WHERE room_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(io::Error::other)?;
+        if length != first {
+            return Err(conflict(id, first, length));
+        }
+        for (sequence, event) in (first..).zip(events) {
+            transaction
+                .execute(
+                    "INSERT INTO events (room_id, sequence, event) VALUES (?1, ?2, ?3)",
+                    params![id, sequence, serde_json::to_string(event)?],
+                )
+                .map_err(io::Error::other)?;
+        }
+        transaction.commit().map_err(io::Error::other)
+    }
+
+    fn read_snapshot(&self, id: &str) -> io::Result<Option<Room>> {
+        let connection = self.connection.lock().unwrap();
+        let room = connection
+            .query_row(
+                "SELECT room FROM snapshots WHERE room_id = ?1",
+                params![id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(io::Error::other)?;
+        match room {
+            Some(room) => Ok(Some(serde_json::from_str(&room)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn write_snapshot(&self, room: &Room) -> io::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO snapshots (room_id, version, room) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (room_id) DO UPDATE SET version = ?2, room = ?3",
+                params![room.id, room.version, serde_json::to_string(room)?],
+            )
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    fn delete_room(&self, id: &str) -> io::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(io::Error::other)?;
+        for table in ["events", "snapshots"] {
+            transaction
+                .execute(
+                    &format!("DELETE FROM {table} WHERE room_id = ?1"),
+                    params![id],
+                )
+                .map_err(io::Error::other)?;
+        }
+        transaction.commit().map_err(io::Error::other)
+    }
+
+    fn save_user(&self, user: &User) -> io::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        let saved = connection.execute(
+            "INSERT INTO users (id, name, password_hash, created_at) VALUES (?1, ?2, ?3, ?4) \
+             ON CONFLICT (id) DO UPDATE SET name = ?2, password_hash = ?3",
+            params![user.id, user.name, user.password_hash, user.created_at],
+        );
+        match saved {
+            Ok(_) => Ok(()),
+            Err(e) if e.sqlite_error_code() == Some(ErrorCode::ConstraintViolation) => {
+                Err(name_taken(&user.name))
+            }
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+
+    fn get_user(&self, id: &str) -> io::Result<Option<User>> {
+        self.query_user("id = ?1", id)
+    }
+
+    fn find_user(&self, name: &str) -> io::Result<Option<User>> {
+        self.query_user("name = ?1", name)
+    }
+
+    fn create_session(&self, token_hash: &str, session: &Session) -> io::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO sessions (token_hash, user_id, created_at) VALUES (?1, ?2, ?3)",
+                params![token_hash, session.user_id, session.created_at],
+            )
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    fn read_session(&self, token_hash: &str) -> io::Result<Option<Session>> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .query_row(
+                "SELECT user_id, created_at FROM sessions WHERE token_hash = ?1",
+                params![token_hash],
+                |row| {
+                    Ok(Session {
+                        user_id: row.get(0)?,
+                        created_at: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(io::Error::other)
+    }
+
+    fn delete_session(&self, token_hash: &str) -> io::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM sessions WHERE token_hash = ?1",
+                params![token_hash],
+            )
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    fn delete_sessions_before(&self, created_before: u64) -> io::Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM sessions WHERE created_at < ?1",
+                params![created_before],
+            )
+            .map_err(io::Error::other)
+    }
+
+    fn rating_history(&self, user_id: &str, variant: Variant) -> io::Result<Vec<RatingChange>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(
+                "SELECT room_id, rating, deviation, volatility, rated_at FROM ratings \
+                 WHERE user_id = ?1 AND variant = ?2 ORDER BY rowid",
+            )
+            .map_err(io::Error::other)?;
+        statement
+            .query_map(params![user_id, variant.key()], |row| {
+                Ok(RatingChange {
+                    user_id: user_id.to_string(),
+                    variant,
+                    room_id: row.get(0)?,
+                    rating: Rating {
+                        rating: row.get(1)?,
+                        deviation: row.get(2)?,
+                        volatility: row.get(3)?,
+                    },
+                    at: row.get(4)?,
+                })
+            })
+            .and_then(|changes| changes.collect())
+            .map_err(io::Error::other)
+    }
+
+    fn add_ratings(&self, changes: &[RatingChange]) -> io::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(io::Error::other)?;
+        for change in changes {
+            transaction
+                .execute(
+                    "INSERT INTO ratings (user_id, variant, room_id, rating, deviation, \
+                     volatility, rated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        change.user_id,
+                        change.variant.key(),
+                        change.room_id,
+                        change.rating.rating,
+                        change.rating.deviation,
+                        change.rating.volatility,
+                        change.at
+                    ],
+                )
+                .map_err(io::Error::other)?;
+        }
+        transaction.commit().map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,49 @@
+use askama::Template;
+use serde::Deserialize;
+
+use crate::accounts::User;
+
+/// The login and registration forms, which share their fields.
+#[derive(Deserialize, Template)]
+#[template(path = "pages/account.html")]
+pub struct AccountFormTemplate {
+    pub title: String,
+    pub user: Option<User>,
+    /// Where the form posts: `/login` or `/register`.
+    pub action: String,
+    /// The name entered before the form was rejected.
+    pub name: String,
+    pub error: Option<String>,
+}
+
+impl AccountFormTemplate {
+    pub fn login(user: Option<User>) -> Self {
+        Self {
+            title: "Log in".to_string(),
+            user,
+            action: "/login".to_string(),
+            name: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn register(user: Option<User>) -> Self {
+        let title = match &user {
+            Some(user) if user.is_guest() => "Register to keep your games",
+            _ => "Register",
+        };
+        Self {
+            title: title.to_string(),
+            user,
+            action: "/register".to_string(),
+            name: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn with_error(mut self, name: &str, error: String) -> Self {
+        self.name = name.to_string();
+        self.error = Some(error);
+        self
+    }
+}
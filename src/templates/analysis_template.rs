@@ -0,0 +1,41 @@
+use askama::Template;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{Analysis, Notation, NotationStyle, TablebaseValue};
+
+#[derive(Deserialize, Template, Serialize)]
+#[template(path = "components/analysis.html")]
+pub struct AnalysisTemplate {
+    pub id: String,
+    pub score: String,
+    pub depth: u8,
+    pub nodes: u64,
+    pub principal_variation: Vec<String>,
+    pub tablebase: Option<String>,
+}
+
+impl AnalysisTemplate {
+    pub fn new(id: String, analysis: &Analysis) -> Self {
+        let score = match analysis.mate_in() {
+            Some(plies) if plies > 0 => format!("White wins in {plies}"),
+            Some(plies) => format!("Black wins in {}", -plies),
+            None => format!("{:+.2}", analysis.score as f32 / 100.0),
+        };
+        Self {
+            id,
+            score,
+            depth: analysis.depth,
+            nodes: analysis.nodes,
+            principal_variation: analysis
+                .principal_variation
+                .iter()
+                .map(|legal_move| Notation::format_move(legal_move, NotationStyle::Algebraic))
+                .collect(),
+            tablebase: analysis.tablebase.map(|value| match value {
+                TablebaseValue::Win(plies) => format!("Side to move wins in {plies}"),
+                TablebaseValue::Loss(plies) => format!("Side to move loses in {plies}"),
+                TablebaseValue::Draw => "Draw".to_string(),
+            }),
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use askama::Template;
 use serde::{Deserialize, Serialize};
 
-use crate::{engine::Board, utility::Point};
+use crate::{
+    engine::{Board, Move, Notation, NotationStyle, Outcome},
+    utility::Point,
+    Room,
+};
 
-use super::CellTemplate;
+use super::{CellTemplate, Highlight};
 
 #[derive(Deserialize, Template, Serialize, Default)]
 #[template(path = "components/board.html")]
@@ -11,6 +15,9 @@ pub struct BoardTemplate {
     pub id: String,
     pub cells: Vec<Vec<CellTemplate>>,
     pub selected_point: Option<Point>,
+    pub fen: String,
+    pub moves: Vec<String>,
+    pub outcome: Option<Outcome>,
 }
 
 impl BoardTemplate {
@@ -25,7 +32,10 @@ impl BoardTemplate {
                     y,
                     id: id.clone(),
                     turn: board.turn,
-                    is_selected: selected_point.map_or(false, |p| p.x == x as i8 && p.y == y as i8),
+                    highlight: match selected_point {
+                        Some(p) if p.x == x as i8 && p.y == y as i8 => Highlight::Selected,
+                        _ => Highlight::None,
+                    },
                 };
             }
         }
@@ -33,6 +43,33 @@ impl BoardTemplate {
             id,
             selected_point,
             cells,
+            fen: board.to_fen(),
+            moves: vec![],
+            outcome: None,
         }
     }
+
+    pub fn with_room(mut self, room: &Room) -> Self {
+        let is_black_first = room.start.turn.is_black();
+        self.moves =
+            Notation::format_history(&room.history, is_black_first, NotationStyle::Algebraic);
+        self.outcome = room.outcome();
+        self
+    }
+
+    /// Highlights the piece and landing square of a suggested move.
+    pub fn with_hint(mut self, hint: Move) -> Self {
+        for point in [hint.from, hint.to] {
+            self.cells[point.y as usize][point.x as usize].highlight = Highlight::Hint;
+        }
+        self
+    }
+
+    pub fn ranks(&self) -> Vec<usize> {
+        (1..=self.cells.len()).rev().collect()
+    }
+
+    pub fn files(&self) -> Vec<char> {
+        (b'a'..).take(self.cells.len()).map(char::from).collect()
+    }
 }
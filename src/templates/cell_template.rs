@@ -1,7 +1,11 @@
 use askama::Template;
 use serde::{Deserialize, Serialize};
 
-use crate::{engine::Turn, Cell};
+use crate::{
+    engine::{Notation, Turn},
+    utility::Point,
+    Cell,
+};
 
 #[derive(Deserialize, Template, Serialize, Default, Clone)]
 #[template(path = "components/cell.html")]
@@ -11,5 +15,24 @@ pub struct CellTemplate {
     pub y: usize,
     pub turn: Turn,
     pub id: String,
-    pub is_selected: bool,
+    pub highlight: Highlight,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+pub enum Highlight {
+    #[default]
+    None,
+    Selected,
+    Hint,
+}
+
+impl CellTemplate {
+    pub fn label(&self) -> String {
+        let point = Point::new(self.x as i8, self.y as i8);
+        let algebraic = Notation::algebraic(point).unwrap_or_default();
+        match Notation::square(point) {
+            Some(square) => format!("{algebraic} ({square})"),
+            None => algebraic,
+        }
+    }
 }
@@ -1,19 +1,32 @@
 use askama::Template;
 use serde::{Deserialize, Serialize};
 
+use crate::{accounts::User, engine::Turn, store::now, AppState, RoomSummary};
+
+mod account_template;
+mod analysis_template;
 mod board_template;
 mod cell_template;
+mod profile_template;
 mod room_template;
+mod seek_template;
 
+pub use account_template::AccountFormTemplate;
+pub use analysis_template::AnalysisTemplate;
 pub use board_template::BoardTemplate;
-pub use cell_template::CellTemplate;
+pub use cell_template::{CellTemplate, Highlight};
+pub use profile_template::ProfileTemplate;
 pub use room_template::RoomTemplate;
+pub use seek_template::{MatchedTemplate, SeekTemplate};
 
 #[derive(Deserialize, Template)]
 #[template(path = "pages/index.html")]
 pub struct IndexTemplate {
     pub title: String,
     pub rooms: Vec<RoomHrefTemplate>,
+    pub has_external_engine: bool,
+    pub has_network: bool,
+    pub user: Option<User>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Copy, Default)]
@@ -28,4 +41,46 @@ pub enum Side {
 pub struct RoomHrefTemplate {
     pub id: String,
     pub title: String,
+    pub variant: String,
+    pub time_control: String,
+    pub rated: bool,
+    pub status: String,
+    pub players: String,
+    pub created: String,
+    pub active: String,
+}
+
+impl RoomHrefTemplate {
+    pub fn new(room: &RoomSummary, state: &AppState) -> Self {
+        Self {
+            id: room.id.clone(),
+            title: room.title.clone(),
+            variant: room.variant.to_string(),
+            time_control: room
+                .time_control
+                .map_or("Untimed".to_string(), |time_control| {
+                    time_control.to_string()
+                }),
+            rated: room.rated,
+            status: room.status.to_string(),
+            players: format!(
+                "{} vs {}",
+                state.player_label(room, Turn::White),
+                state.player_label(room, Turn::Black)
+            ),
+            created: ago(room.created_at),
+            active: ago(room.last_activity),
+        }
+    }
+}
+
+/// How long ago a Unix time was, roughly, e.g. `5 min ago`.
+fn ago(at: u64) -> String {
+    let seconds = now().saturating_sub(at);
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
 }
@@ -0,0 +1,63 @@
+use askama::Template;
+use serde::Deserialize;
+
+use tracing::event;
+
+use super::{ago, RoomHrefTemplate};
+use crate::{accounts::User, AppState, RoomSummary, Variant};
+
+#[derive(Deserialize, Template)]
+#[template(path = "pages/profile.html")]
+pub struct ProfileTemplate {
+    pub title: String,
+    pub user: Option<User>,
+    pub name: String,
+    pub is_guest: bool,
+    pub joined: String,
+    /// The current rating in each variant with rated games, e.g. `Russian draughts: 1623 after
+    /// 12 rated games`, marked when still provisional.
+    pub ratings: Vec<String>,
+    pub games: Vec<RoomHrefTemplate>,
+}
+
+impl ProfileTemplate {
+    pub fn new(
+        profile: &User,
+        games: &[RoomSummary],
+        state: &AppState,
+        user: Option<User>,
+    ) -> Self {
+        Self {
+            title: profile.name.clone(),
+            user,
+            name: profile.name.clone(),
+            is_guest: profile.is_guest(),
+            joined: ago(profile.created_at),
+            ratings: Variant::ALL
+                .into_iter()
+                .filter_map(|variant| {
+                    let history = state
+                        .store
+                        .rating_history(&profile.id, variant)
+                        .unwrap_or_else(|e| {
+                            event!(tracing::Level::ERROR, "Loading ratings failed: {e}");
+                            vec![]
+                        });
+                    let rating = history.last()?.rating;
+                    let games = match history.len() {
+                        1 => "1 rated game".to_string(),
+                        games => format!("{games} rated games"),
+                    };
+                    Some(match rating.is_provisional() {
+                        true => format!("{variant}: {rating} after {games}, provisional"),
+                        false => format!("{variant}: {rating} after {games}"),
+                    })
+                })
+                .collect(),
+            games: games
+                .iter()
+                .map(|room| RoomHrefTemplate::new(room, state))
+                .collect(),
+        }
+    }
+}
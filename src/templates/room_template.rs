@@ -2,6 +2,8 @@ use askama::Template;
 use serde::{Deserialize, Serialize};
 
 use super::{BoardTemplate, Side};
+use crate::engine::Turn;
+use crate::{accounts::User, AppState, Room, Seat};
 
 #[derive(Deserialize, Template, Serialize)]
 #[template(path = "pages/room.html")]
@@ -10,4 +12,79 @@ pub struct RoomTemplate {
     pub title: String,
     pub board: BoardTemplate,
     pub side: Side,
+    pub hints: bool,
+    pub can_hint: bool,
+    pub can_analyse: bool,
+    pub white: Seat,
+    pub black: Seat,
+    /// The names and ratings of the users holding the human seats.
+    pub white_player: Option<String>,
+    pub black_player: Option<String>,
+    pub has_external_engine: bool,
+    pub has_network: bool,
+    /// The viewer's side can still resign or offer a draw.
+    pub can_resign: bool,
+    pub draw_offer: Option<Turn>,
+    /// The variant, time control and whether the game is rated.
+    pub rules: String,
+    pub archived: bool,
+    /// The invite link of a private room.
+    pub invite: Option<String>,
+    /// Whether the page is for the room's creator, who may regenerate the invite link.
+    pub is_creator: bool,
+    pub user: Option<User>,
+}
+
+impl RoomTemplate {
+    /// The room as `user` sees it, from the side they hold when they hold only black.
+    pub fn new(room: &Room, state: &AppState, user: Option<User>) -> Self {
+        let user_id = user.as_ref().map(|user| user.id.as_str());
+        let side = match (room.player(Turn::White), room.player(Turn::Black)) {
+            (white, Some(black)) if Some(black) == user_id && white != user_id => Side::Black,
+            _ => Side::White,
+        };
+        Self {
+            id: room.id.clone(),
+            title: room.title(),
+            board: BoardTemplate::new(&room.board, room.id.clone(), None).with_room(room),
+            side,
+            hints: room.hints,
+            can_hint: room.can_hint(),
+            can_analyse: room.can_analyse(),
+            white: room.seat(Turn::White),
+            black: room.seat(Turn::Black),
+            white_player: state
+                .player(room, Turn::White)
+                .map(|user| state.rated_name(&user, room.variant)),
+            black_player: state
+                .player(room, Turn::Black)
+                .map(|user| state.rated_name(&user, room.variant)),
+            has_external_engine: state.external_engine.is_some(),
+            has_network: state.network.is_some(),
+            can_resign: room.human_turn(user_id).is_some() && room.outcome().is_none(),
+            draw_offer: room.draw_offer,
+            rules: [
+                Some(room.variant.to_string()),
+                room.time_control
+                    .map(|time_control| time_control.to_string()),
+                room.rated.then(|| "rated".to_string()),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", "),
+            archived: room.archived,
+            invite: room
+                .invite
+                .as_ref()
+                .map(|invite| format!("/rooms/{}?invite={}", room.id, invite.token)),
+            is_creator: false,
+            user,
+        }
+    }
+
+    pub fn with_creator(mut self, is_creator: bool) -> Self {
+        self.is_creator = is_creator;
+        self
+    }
 }
@@ -0,0 +1,41 @@
+use askama::Template;
+use serde::Deserialize;
+
+use crate::{ratings::Rating, TimeControl, Variant};
+
+/// Shown while seeking; its websocket keeps the seek open and brings the game's room.
+#[derive(Deserialize, Template)]
+#[template(path = "components/seek.html")]
+pub struct SeekTemplate {
+    /// The variant and time control sought.
+    pub rules: String,
+    pub rating: String,
+    /// The websocket address carrying the seek's variant and time control.
+    pub socket: String,
+}
+
+impl SeekTemplate {
+    pub fn new(variant: Variant, time_control: Option<TimeControl>, rating: Rating) -> Self {
+        let time_control = time_control.map(|time_control| time_control.to_string());
+        Self {
+            rules: match &time_control {
+                Some(time_control) => format!("{variant}, {time_control}"),
+                None => variant.to_string(),
+            },
+            rating: rating.to_string(),
+            socket: format!(
+                "/ws/seek?variant={}&time_control={}",
+                variant.key(),
+                time_control.unwrap_or_default().replace('+', "%2B")
+            ),
+        }
+    }
+}
+
+/// Sent over the seek's websocket once it ends: to the new game's room when paired, else back
+/// to the room list.
+#[derive(Deserialize, Template)]
+#[template(path = "components/matched.html")]
+pub struct MatchedTemplate {
+    pub room: Option<String>,
+}
@@ -1,5 +1,6 @@
 use std::fmt::Display;
 
+use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Default)]
@@ -21,10 +22,6 @@ impl Point {
         Point::new(self.x + other.x, self.y + other.y)
     }
 
-    pub fn divide(&self, divider: i8) -> Point {
-        Point::new(self.x / divider, self.y / divider)
-    }
-
     pub fn signum(&self) -> Point {
         Point::new(self.x.signum(), self.y.signum())
     }
@@ -36,55 +33,10 @@ impl Display for Point {
     }
 }
 
-pub trait Shift<T> {
-    fn shift(&mut self) -> Option<T>;
-}
-
-impl<T> Shift<T> for Vec<T> {
-    fn shift(&mut self) -> Option<T> {
-        if self.is_empty() {
-            return None;
-        }
-        Some(self.remove(0))
-    }
-}
-
-pub trait FindAndRemove<T> {
-    fn find_and_remove<P>(&mut self, predicate: P) -> Option<T>
-    where
-        P: FnMut(&T) -> bool;
-}
-
-impl<T> FindAndRemove<T> for Vec<T> {
-    fn find_and_remove<P>(&mut self, predicate: P) -> Option<T>
-    where
-        P: FnMut(&T) -> bool,
-    {
-        match self.iter().position(predicate) {
-            Some(value_index) => {
-                let route = self.remove(value_index);
-                Some(route)
-            }
-            None => None,
-        }
-    }
-}
-
-pub trait FindAndRemoveAll<T> {
-    fn find_and_remove_all<P>(&mut self, predicate: P) -> Vec<T>
-    where
-        P: FnMut(&T) -> bool;
-}
-
-impl<T> FindAndRemoveAll<T> for Vec<T> {
-    fn find_and_remove_all<P>(&mut self, mut predicate: P) -> Vec<T>
-    where
-        P: FnMut(&T) -> bool,
-    {
-        let mut result = Vec::new();
-        while let Some(value) = self.find_and_remove(&mut predicate) {
-            result.push(value);
-        }
-        result
-    }
+pub fn random_id(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
 }
@@ -0,0 +1,69 @@
+use std::{io, time::Duration};
+
+use checkers::engine::{Board, Engine, HubEngine, Notation, SearchLimits};
+
+fn limits() -> SearchLimits {
+    SearchLimits {
+        max_depth: 3,
+        time: Duration::from_millis(200),
+    }
+}
+
+#[test]
+fn engines_play_through_the_hub_protocol() {
+    let mut engine = HubEngine::spawn(env!("CARGO_BIN_EXE_hub_stub"), limits()).unwrap();
+    assert_eq!(engine.name, "Stub");
+    let mut board = Board::new();
+    for _ in 0..6 {
+        let played = engine.best_move(&board).unwrap();
+        assert!(Engine::get_moves(&board).contains(&played));
+        board = Engine::make_move(board, played.from, played.to);
+    }
+}
+
+#[test]
+fn positions_round_trip() {
+    let start = HubEngine::format_position(&Board::new());
+    assert_eq!(start, "Wbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww");
+    assert_eq!(HubEngine::parse_position(&start).unwrap(), Board::new());
+    let board = Board::from_fen("B:W18,K30:BK1,K2,14").unwrap();
+    let position = HubEngine::format_position(&board);
+    assert_eq!(
+        HubEngine::parse_position(&position).unwrap().to_fen(),
+        board.to_fen()
+    );
+    assert!(HubEngine::parse_position("W:W21-32:B1-12").is_none());
+    assert!(HubEngine::parse_position("Wbbbb").is_none());
+}
+
+#[test]
+fn captures_list_the_pieces_taken() {
+    let board = Board::from_fen("W:W22:B18,11").unwrap();
+    let from = Notation::from_square(22).unwrap();
+    let to = Notation::from_square(8).unwrap();
+    let capture = Engine::find_move(&board, from, to).unwrap();
+    assert_eq!(HubEngine::format_move(&board, &capture), "22x8x11x18");
+    for notation in ["22x8x11x18", "22x8x18x11", "22x8"] {
+        assert_eq!(
+            HubEngine::parse_move(&board, notation),
+            Some(capture),
+            "{notation}"
+        );
+    }
+    for notation in ["22x8x11", "22-8", "22x8x11x18x14", "22-18"] {
+        assert_eq!(HubEngine::parse_move(&board, notation), None, "{notation}");
+    }
+    let opening = Engine::find_move(
+        &Board::new(),
+        Notation::from_square(22).unwrap(),
+        Notation::from_square(18).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(HubEngine::format_move(&Board::new(), &opening), "22-18");
+}
+
+#[test]
+fn silent_engines_time_out() {
+    let error = HubEngine::spawn("sleep 60", limits()).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+}